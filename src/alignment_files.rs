@@ -0,0 +1,688 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io::{BufRead, BufReader},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, PoisonError},
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use rust_htslib::{
+    bam::{
+        self,
+        record::{Aux, Cigar, CigarString},
+        HeaderView, Read,
+    },
+    bgzf, faidx,
+};
+use serde::Serialize;
+
+use crate::datasets::fastx::FastxReader;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AlignmentKind {
+    Primary,
+    Secondary,
+    Supplementary,
+}
+
+// the parts of an alignment that the analyses need, independent of the file format
+#[derive(Debug, Clone)]
+pub struct AlignmentSummary {
+    pub kind: AlignmentKind,
+    // 255 if not available
+    pub mapq: u8,
+    pub reverse_strand: bool,
+    // including clipped bases
+    pub query_length: usize,
+    // PAF does not distinguish between soft and hard clipping, it is counted as soft clipping
+    pub soft_clipped_bases: usize,
+    pub hard_clipped_bases: usize,
+    // aligned interval of the query in its original orientation (also for reverse strand alignments)
+    pub query_start: usize,
+    pub query_end: usize,
+    pub reference_name: String,
+    // 0-based
+    pub reference_start: usize,
+    pub reference_span: usize,
+    pub edit_distance: Option<usize>,
+    pub longest_indel: usize,
+    // total length per CIGAR operation, e.g. 950M12I8D30S, None if the CIGAR is not available
+    pub cigar_summary: Option<String>,
+}
+
+impl AlignmentSummary {
+    pub fn clipped_bases(&self) -> usize {
+        self.soft_clipped_bases + self.hard_clipped_bases
+    }
+
+    pub fn aligned_query_length(&self) -> usize {
+        self.query_length.saturating_sub(self.clipped_bases())
+    }
+}
+
+// all alignments by query name, unmapped queries have no alignments
+pub type AlignmentsPerQuery = HashMap<String, Vec<AlignmentSummary>>;
+
+// the sequences of the contigs by name
+pub type ReferenceSequences = HashMap<String, Vec<u8>>;
+
+// SAM and BAM are read with htslib, PAF is recognized by its file extension (.paf or .paf.gz).
+// With a recomputer, the edit distances of SAM and BAM records are recomputed and checked.
+pub fn read_alignments_per_query(
+    path: &Path,
+    edit_distance_recomputer: Option<&mut EditDistanceRecomputer<'_>>,
+) -> Result<AlignmentsPerQuery> {
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+
+    if file_name.ends_with(".paf") || file_name.ends_with(".paf.gz") {
+        read_paf(path)
+    } else {
+        read_sam_or_bam(path, edit_distance_recomputer)
+    }
+}
+
+fn read_sam_or_bam(
+    path: &Path,
+    mut edit_distance_recomputer: Option<&mut EditDistanceRecomputer<'_>>,
+) -> Result<AlignmentsPerQuery> {
+    let mut bam = bam::Reader::from_path(path)
+        .with_context(|| format!("opening mapped reads {}", path.display()))?;
+    let reference_names = reference_names(bam.header());
+    let mut alignments = AlignmentsPerQuery::new();
+
+    for record in bam.records() {
+        let record = record?;
+        let query_name = String::from_utf8_lossy(record.qname()).into_owned();
+
+        if record.is_unmapped() {
+            alignments.entry(query_name).or_default();
+            continue;
+        }
+
+        let reported_edit_distance = match record.aux(b"NM") {
+            Ok(aux) => Some(aux_integer(aux)?),
+            Err(_) => None,
+        };
+        let edit_distance = match edit_distance_recomputer.as_deref_mut() {
+            Some(recomputer) => recomputer.check(
+                &query_name,
+                &record,
+                &reference_names,
+                reported_edit_distance,
+            )?,
+            None => reported_edit_distance,
+        };
+
+        let query_alignments = alignments.entry(query_name).or_default();
+
+        let kind = if record.is_supplementary() {
+            AlignmentKind::Supplementary
+        } else if record.is_secondary() {
+            AlignmentKind::Secondary
+        } else {
+            AlignmentKind::Primary
+        };
+
+        let cigar = record.cigar();
+        let cigar_stats = CigarStats::from_cigar(cigar.iter());
+
+        // floxer can be configured to not output CIGAR strings
+        let query_length = if record.cigar_len() == 0 {
+            record.seq_len()
+        } else {
+            cigar_stats.query_length
+        };
+
+        // SAM clipping is given in the orientation of the reference
+        let (query_start, query_end) = if record.is_reverse() {
+            (
+                cigar_stats.trailing_clip,
+                query_length - cigar_stats.leading_clip,
+            )
+        } else {
+            (
+                cigar_stats.leading_clip,
+                query_length - cigar_stats.trailing_clip,
+            )
+        };
+
+        // without a CIGAR, the query length is the best guess for the reference span
+        let reference_span = if record.cigar_len() == 0 {
+            query_length
+        } else {
+            cigar_stats.reference_span
+        };
+
+        query_alignments.push(AlignmentSummary {
+            kind,
+            mapq: record.mapq(),
+            reverse_strand: record.is_reverse(),
+            query_length,
+            soft_clipped_bases: cigar_stats.soft_clipped_bases,
+            hard_clipped_bases: cigar_stats.hard_clipped_bases,
+            query_start,
+            query_end,
+            reference_name: usize::try_from(record.tid())
+                .ok()
+                .and_then(|tid| reference_names.get(tid))
+                .cloned()
+                .unwrap_or_else(|| String::from("*")),
+            reference_start: record.pos().max(0) as usize,
+            reference_span,
+            edit_distance,
+            longest_indel: cigar_stats.longest_indel,
+            cigar_summary: (!cigar.is_empty()).then(|| summarize_cigar(cigar.iter())),
+        });
+    }
+
+    Ok(alignments)
+}
+
+// minimap2 PAF: the first tp:A:P (or inversion) line of a query is the primary alignment,
+// further ones are supplementary. Unmapped queries only appear with --paf-no-hit.
+fn read_paf(path: &Path) -> Result<AlignmentsPerQuery> {
+    let mut alignments = AlignmentsPerQuery::new();
+
+    for line in paf_lines(path)? {
+        let line = line?;
+        let fields = match split_paf_line(&line, path)? {
+            Some(fields) => fields,
+            None => continue,
+        };
+
+        let query_alignments = alignments.entry(fields[0].to_owned()).or_default();
+
+        // unmapped
+        if fields[4] == "*" {
+            continue;
+        }
+
+        let query_length: usize = fields[1].parse()?;
+        let query_start: usize = fields[2].parse()?;
+        let query_end: usize = fields[3].parse()?;
+        let target_start: usize = fields[7].parse()?;
+        let target_end: usize = fields[8].parse()?;
+        let num_matches: usize = fields[9].parse()?;
+        let alignment_block_length: usize = fields[10].parse()?;
+
+        let tag = |name: &str| paf_tag(&fields, name);
+
+        let kind = paf_alignment_kind(
+            tag("tp:A:"),
+            query_alignments
+                .iter()
+                .any(|alignment| alignment.kind == AlignmentKind::Primary),
+        );
+
+        // without NM, the number of non-matching columns is the edit distance
+        let edit_distance = match tag("NM:i:") {
+            Some(edit_distance) => edit_distance.parse()?,
+            None => alignment_block_length.saturating_sub(num_matches),
+        };
+
+        let (longest_indel, cigar_summary) = match tag("cg:Z:") {
+            Some(cigar) => {
+                let cigar = CigarString::try_from(cigar)
+                    .with_context(|| format!("invalid PAF cigar of {}", fields[0]))?;
+                (
+                    CigarStats::from_cigar(cigar.iter()).longest_indel,
+                    Some(summarize_cigar(cigar.iter())),
+                )
+            }
+            None => (0, None),
+        };
+
+        query_alignments.push(AlignmentSummary {
+            kind,
+            mapq: fields[11].parse()?,
+            reverse_strand: fields[4] == "-",
+            query_length,
+            soft_clipped_bases: query_start + query_length.saturating_sub(query_end),
+            hard_clipped_bases: 0,
+            query_start,
+            query_end,
+            reference_name: fields[5].to_owned(),
+            reference_start: target_start,
+            reference_span: target_end.saturating_sub(target_start),
+            edit_distance: Some(edit_distance),
+            longest_indel,
+            cigar_summary,
+        });
+    }
+
+    Ok(alignments)
+}
+
+fn paf_lines(path: &Path) -> Result<std::io::Lines<BufReader<bgzf::Reader>>> {
+    Ok(BufReader::new(
+        bgzf::Reader::from_path(path)
+            .with_context(|| format!("opening mapped reads {}", path.display()))?,
+    )
+    .lines())
+}
+
+// None for empty lines
+fn split_paf_line<'a>(line: &'a str, path: &Path) -> Result<Option<Vec<&'a str>>> {
+    if line.trim().is_empty() {
+        return Ok(None);
+    }
+
+    let fields: Vec<_> = line.split('\t').collect();
+    if fields.len() < 12 {
+        bail!("PAF line with less than 12 fields in {}", path.display());
+    }
+
+    Ok(Some(fields))
+}
+
+fn paf_tag<'a>(fields: &[&'a str], name: &str) -> Option<&'a str> {
+    fields[12..]
+        .iter()
+        .find_map(|field| field.strip_prefix(name))
+}
+
+// minimap2 reports inversions (tp:A:I and tp:A:i) as primary chains, so they are treated like
+// tp:A:P and only tp:A:S is secondary
+fn paf_alignment_kind(alignment_type: Option<&str>, has_primary: bool) -> AlignmentKind {
+    match alignment_type {
+        Some("S") => AlignmentKind::Secondary,
+        _ if has_primary => AlignmentKind::Supplementary,
+        _ => AlignmentKind::Primary,
+    }
+}
+
+// operations in the order of the SAM specification
+fn summarize_cigar<'a>(cigar: impl Iterator<Item = &'a Cigar>) -> String {
+    let mut totals = [0u64; 9];
+
+    for op in cigar {
+        let index = match op {
+            Cigar::Match(_) => 0,
+            Cigar::Ins(_) => 1,
+            Cigar::Del(_) => 2,
+            Cigar::RefSkip(_) => 3,
+            Cigar::SoftClip(_) => 4,
+            Cigar::HardClip(_) => 5,
+            Cigar::Pad(_) => 6,
+            Cigar::Equal(_) => 7,
+            Cigar::Diff(_) => 8,
+        };
+        totals[index] += op.len() as u64;
+    }
+
+    totals
+        .iter()
+        .zip("MIDNSHP=X".chars())
+        .filter(|(total, _)| **total > 0)
+        .map(|(total, op)| format!("{total}{op}"))
+        .collect()
+}
+
+struct CigarStats {
+    query_length: usize,
+    reference_span: usize,
+    soft_clipped_bases: usize,
+    hard_clipped_bases: usize,
+    leading_clip: usize,
+    trailing_clip: usize,
+    longest_indel: usize,
+}
+
+impl CigarStats {
+    fn from_cigar<'a>(cigar: impl Iterator<Item = &'a Cigar>) -> Self {
+        let mut stats = Self {
+            query_length: 0,
+            reference_span: 0,
+            soft_clipped_bases: 0,
+            hard_clipped_bases: 0,
+            leading_clip: 0,
+            trailing_clip: 0,
+            longest_indel: 0,
+        };
+        let mut seen_non_clip = false;
+
+        for op in cigar {
+            let len = op.len() as usize;
+
+            match op {
+                Cigar::Match(_) | Cigar::Equal(_) | Cigar::Diff(_) => {
+                    stats.query_length += len;
+                    stats.reference_span += len;
+                }
+                Cigar::Ins(_) => {
+                    stats.query_length += len;
+                    stats.longest_indel = stats.longest_indel.max(len);
+                }
+                Cigar::Del(_) => {
+                    stats.reference_span += len;
+                    stats.longest_indel = stats.longest_indel.max(len);
+                }
+                Cigar::SoftClip(_) | Cigar::HardClip(_) => {
+                    stats.query_length += len;
+
+                    if let Cigar::SoftClip(_) = op {
+                        stats.soft_clipped_bases += len;
+                    } else {
+                        stats.hard_clipped_bases += len;
+                    }
+
+                    if seen_non_clip {
+                        stats.trailing_clip += len;
+                    } else {
+                        stats.leading_clip += len;
+                    }
+                }
+                Cigar::RefSkip(_) => stats.reference_span += len,
+                Cigar::Pad(_) => {}
+            }
+
+            if !matches!(op, Cigar::SoftClip(_) | Cigar::HardClip(_)) {
+                seen_non_clip = true;
+            }
+        }
+
+        stats
+    }
+}
+
+// BAM stores integer tags in the smallest type that fits the value. The tags this is used for
+// (like NM) can't be negative, so negative values are an error instead of being wrapped around.
+pub fn aux_integer(aux: Aux) -> Result<usize> {
+    let value = match aux {
+        Aux::I8(value) => value as i64,
+        Aux::U8(value) => value as i64,
+        Aux::I16(value) => value as i64,
+        Aux::U16(value) => value as i64,
+        Aux::I32(value) => value as i64,
+        Aux::U32(value) => value as i64,
+        _ => bail!("wrong integer tag type: {:?}", aux),
+    };
+
+    usize::try_from(value).with_context(|| format!("negative integer tag value {value}"))
+}
+
+pub fn reference_names(header: &HeaderView) -> Vec<String> {
+    header
+        .target_names()
+        .into_iter()
+        .map(|name| String::from_utf8_lossy(name).into_owned())
+        .collect()
+}
+
+// recomputes the edit distance of alignments from the CIGAR (if it contains =/X instead of M),
+// from the CIGAR and the MD tag or from the CIGAR and the reference (in this order of preference)
+pub struct EditDistanceRecomputer<'a> {
+    // without the reference, alignments with M operations and without MD tag are not recomputable
+    reference: Option<&'a ReferenceSequences>,
+    report: EditDistanceReport,
+}
+
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct EditDistanceReport {
+    pub num_alignments: usize,
+    pub num_missing_nm: usize,
+    // no CIGAR or neither a MD tag nor a sequence
+    pub num_not_recomputable: usize,
+    pub num_mismatches: usize,
+    pub mismatches: Vec<EditDistanceMismatch>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EditDistanceMismatch {
+    pub query_name: String,
+    pub reported: usize,
+    pub recomputed: usize,
+}
+
+impl<'a> EditDistanceRecomputer<'a> {
+    pub fn new(reference: Option<&'a ReferenceSequences>) -> Self {
+        Self {
+            reference,
+            report: EditDistanceReport::default(),
+        }
+    }
+
+    pub fn into_report(self) -> EditDistanceReport {
+        self.report
+    }
+
+    // returns the recomputed edit distance if possible and the reported one otherwise
+    fn check(
+        &mut self,
+        query_name: &str,
+        record: &bam::Record,
+        reference_names: &[String],
+        reported: Option<usize>,
+    ) -> Result<Option<usize>> {
+        self.report.num_alignments += 1;
+
+        if reported.is_none() {
+            self.report.num_missing_nm += 1;
+        }
+
+        let Some(recomputed) = self.recompute(record, reference_names)? else {
+            self.report.num_not_recomputable += 1;
+            return Ok(reported);
+        };
+
+        if let Some(reported) = reported.filter(|reported| *reported != recomputed) {
+            self.report.num_mismatches += 1;
+            self.report.mismatches.push(EditDistanceMismatch {
+                query_name: query_name.to_owned(),
+                reported,
+                recomputed,
+            });
+        }
+
+        Ok(Some(recomputed))
+    }
+
+    fn recompute(
+        &mut self,
+        record: &bam::Record,
+        reference_names: &[String],
+    ) -> Result<Option<usize>> {
+        let cigar = record.cigar();
+        if cigar.is_empty() {
+            return Ok(None);
+        }
+
+        let num_indel_bases: usize = cigar
+            .iter()
+            .filter(|op| matches!(op, Cigar::Ins(_) | Cigar::Del(_)))
+            .map(|op| op.len() as usize)
+            .sum();
+
+        if !cigar.iter().any(|op| matches!(op, Cigar::Match(_))) {
+            let num_mismatches: usize = cigar
+                .iter()
+                .filter(|op| matches!(op, Cigar::Diff(_)))
+                .map(|op| op.len() as usize)
+                .sum();
+
+            return Ok(Some(num_indel_bases + num_mismatches));
+        }
+
+        if let Ok(Aux::String(md)) = record.aux(b"MD") {
+            return Ok(Some(num_indel_bases + md_mismatches(md)));
+        }
+
+        let query = record.seq().as_bytes();
+        if query.is_empty() {
+            return Ok(None);
+        }
+
+        let (Some(reference), Some(contig_name)) = (
+            self.reference,
+            usize::try_from(record.tid())
+                .ok()
+                .and_then(|tid| reference_names.get(tid)),
+        ) else {
+            return Ok(None);
+        };
+
+        let contig = reference
+            .get(contig_name)
+            .with_context(|| format!("contig {contig_name} is not in the reference"))?;
+
+        let mut reference_pos = record.pos() as usize;
+        let mut query_pos = 0;
+        let mut num_mismatches = 0;
+
+        for op in cigar.iter() {
+            let len = op.len() as usize;
+
+            match op {
+                Cigar::Match(_) | Cigar::Equal(_) | Cigar::Diff(_) => {
+                    let (Some(reference_part), Some(query_part)) = (
+                        contig.get(reference_pos..reference_pos + len),
+                        query.get(query_pos..query_pos + len),
+                    ) else {
+                        bail!("alignment out of bounds of the reference or query");
+                    };
+
+                    num_mismatches += reference_part
+                        .iter()
+                        .zip(query_part)
+                        .filter(|(r, q)| !r.eq_ignore_ascii_case(q))
+                        .count();
+
+                    reference_pos += len;
+                    query_pos += len;
+                }
+                Cigar::Ins(_) | Cigar::SoftClip(_) => query_pos += len,
+                Cigar::Del(_) | Cigar::RefSkip(_) => reference_pos += len,
+                Cigar::HardClip(_) | Cigar::Pad(_) => {}
+            }
+        }
+
+        Ok(Some(num_indel_bases + num_mismatches))
+    }
+}
+
+// the number of mismatched bases, deleted bases (after ^) are not counted
+fn md_mismatches(md: &str) -> usize {
+    let mut in_deletion = false;
+    let mut num_mismatches = 0;
+
+    for c in md.chars() {
+        if c == '^' {
+            in_deletion = true;
+        } else if c.is_ascii_digit() {
+            in_deletion = false;
+        } else if !in_deletion {
+            num_mismatches += 1;
+        }
+    }
+
+    num_mismatches
+}
+
+// the most recently loaded reference, such that all instances of a suite run that use the same
+// reference share it. Only one is kept, because a human reference takes about 3 GB.
+static LOADED_REFERENCE: Mutex<Option<(PathBuf, Arc<ReferenceSequences>)>> = Mutex::new(None);
+
+pub fn load_reference(reference_path: &Path) -> Result<Arc<ReferenceSequences>> {
+    let mut loaded_reference = LOADED_REFERENCE
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+
+    if let Some((path, reference)) = loaded_reference.as_ref() {
+        if path == reference_path {
+            return Ok(Arc::clone(reference));
+        }
+    }
+
+    // the previous reference is freed before the next one is read
+    *loaded_reference = None;
+
+    println!("- Loading the reference {}", reference_path.display());
+
+    let reference: ReferenceSequences = FastxReader::from_path(reference_path)?
+        .map(|record| {
+            let record = record?;
+            Ok((record.id().to_owned(), record.seq))
+        })
+        .collect::<Result<_>>()?;
+    let reference = Arc::new(reference);
+
+    *loaded_reference = Some((reference_path.to_owned(), Arc::clone(&reference)));
+
+    Ok(reference)
+}
+
+// from the FASTA index (.fai) next to the reference, which htslib creates if it doesn't exist yet,
+// such that the sequences don't have to be read. The index is parsed here, because the faidx
+// reader of rust-htslib doesn't report when htslib fails to load it.
+pub fn reference_lengths(reference_path: &Path) -> Result<Vec<(String, usize)>> {
+    let mut index_path = reference_path.as_os_str().to_owned();
+    index_path.push(".fai");
+    let index_path = PathBuf::from(index_path);
+
+    if !index_path.exists() {
+        faidx::build(reference_path).map_err(|err| {
+            anyhow!(
+                "creating the index of the reference {} failed (it has to be uncompressed or \
+                bgzip compressed and its folder writable): {err}",
+                reference_path.display()
+            )
+        })?;
+    }
+
+    let index = fs::read_to_string(&index_path)
+        .with_context(|| format!("reading the reference index {}", index_path.display()))?;
+
+    index
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            let mut fields = line.split('\t');
+            let (Some(name), Some(length)) = (fields.next(), fields.next()) else {
+                bail!(
+                    "invalid line in the reference index {}",
+                    index_path.display()
+                );
+            };
+
+            Ok((name.to_owned(), length.parse()?))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paf_alignment_kinds() {
+        assert_eq!(paf_alignment_kind(Some("P"), false), AlignmentKind::Primary);
+        assert_eq!(
+            paf_alignment_kind(Some("P"), true),
+            AlignmentKind::Supplementary
+        );
+        assert_eq!(
+            paf_alignment_kind(Some("S"), false),
+            AlignmentKind::Secondary
+        );
+        assert_eq!(
+            paf_alignment_kind(Some("S"), true),
+            AlignmentKind::Secondary
+        );
+        assert_eq!(paf_alignment_kind(None, false), AlignmentKind::Primary);
+    }
+
+    #[test]
+    fn paf_inversions_are_not_secondary() {
+        for inversion in ["I", "i"] {
+            assert_eq!(
+                paf_alignment_kind(Some(inversion), false),
+                AlignmentKind::Primary
+            );
+            assert_eq!(
+                paf_alignment_kind(Some(inversion), true),
+                AlignmentKind::Supplementary
+            );
+        }
+    }
+}
@@ -0,0 +1,321 @@
+use std::{fs, path::Path, thread};
+
+use anyhow::{Context, Result};
+use rust_htslib::bam::{self, record::Cigar, Read};
+use serde::Serialize;
+
+use crate::{
+    alignment_files::{self, ReferenceSequences},
+    edit_distance::{self, AlignmentMode},
+    readmappers::floxer::QueryErrors,
+};
+
+// number of records that are validated in parallel
+const CHUNK_SIZE: usize = 10_000;
+
+// floxer reports all alignments within the error bound, so every alignment has to be
+// re-alignable with at most that many errors and the NM tag has to be exact
+pub fn validate_alignments(
+    mapped_reads_path: &Path,
+    reference: &ReferenceSequences,
+    query_errors: QueryErrors,
+) -> Result<AlignmentValidationReport> {
+    let mut bam = bam::Reader::from_path(mapped_reads_path)
+        .with_context(|| format!("opening mapped reads {}", mapped_reads_path.display()))?;
+    let reference_names = alignment_files::reference_names(bam.header());
+
+    let num_threads = thread::available_parallelism().map_or(1, |num| num.get());
+    let mut report = AlignmentValidationReport::default();
+    let mut chunk = Vec::with_capacity(CHUNK_SIZE);
+    let mut records = bam.records();
+
+    loop {
+        chunk.clear();
+        for record in records.by_ref() {
+            let record = record?;
+            if !record.is_unmapped() {
+                chunk.push(record);
+            }
+
+            if chunk.len() == CHUNK_SIZE {
+                break;
+            }
+        }
+
+        if chunk.is_empty() {
+            break;
+        }
+
+        let results: Vec<Result<RecordValidation>> = thread::scope(|scope| {
+            let handles: Vec<_> = chunk
+                .chunks(chunk.len().div_ceil(num_threads))
+                .map(|records| {
+                    scope.spawn(|| {
+                        records
+                            .iter()
+                            .map(|record| {
+                                validate_record(record, &reference_names, reference, query_errors)
+                            })
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+
+            handles
+                .into_iter()
+                .flat_map(|handle| handle.join().expect("validation thread panicked"))
+                .collect()
+        });
+
+        for result in results {
+            report.add(result?);
+        }
+    }
+
+    report
+        .violations
+        .sort_by(|a, b| a.query_name.cmp(&b.query_name));
+
+    Ok(report)
+}
+
+enum RecordValidation {
+    // no sequence in the record (e.g. for secondary alignments)
+    NotCheckable,
+    Valid,
+    Violation(AlignmentViolation),
+}
+
+fn validate_record(
+    record: &bam::Record,
+    reference_names: &[String],
+    reference: &ReferenceSequences,
+    query_errors: QueryErrors,
+) -> Result<RecordValidation> {
+    let sequence = record.seq().as_bytes();
+    if sequence.is_empty() {
+        return Ok(RecordValidation::NotCheckable);
+    }
+
+    let cigar = record.cigar();
+    let hard_clipped_bases: usize = cigar
+        .iter()
+        .filter(|op| matches!(op, Cigar::HardClip(_)))
+        .map(|op| op.len() as usize)
+        .sum();
+    let query_length = sequence.len() + hard_clipped_bases;
+
+    let max_errors = query_errors.max_errors(query_length);
+
+    let reference_name = usize::try_from(record.tid())
+        .ok()
+        .and_then(|tid| reference_names.get(tid))
+        .context("mapped record without reference")?;
+    let contig = reference
+        .get(reference_name)
+        .with_context(|| format!("contig {reference_name} is not in the reference"))?;
+
+    let reported_edit_distance = match record.aux(b"NM") {
+        Ok(aux) => Some(alignment_files::aux_integer(aux)?),
+        Err(_) => None,
+    };
+    let band = max_errors.max(reported_edit_distance.unwrap_or(0));
+    let start = record.pos() as usize;
+
+    let mut violation = AlignmentViolation {
+        query_name: String::from_utf8_lossy(record.qname()).into_owned(),
+        reference_name: reference_name.clone(),
+        position: start as u64 + 1,
+        reverse_strand: record.is_reverse(),
+        secondary: record.is_secondary(),
+        max_errors,
+        reported_edit_distance,
+        realigned_edit_distance: None,
+        problems: Vec::new(),
+    };
+
+    // malformed records may clip more bases than the sequence has
+    let aligned_query_start = cigar.leading_softclips() as usize;
+    let Some(aligned_query) = sequence
+        .len()
+        .checked_sub(cigar.trailing_softclips() as usize)
+        .filter(|aligned_query_end| aligned_query_start <= *aligned_query_end)
+        .map(|aligned_query_end| &sequence[aligned_query_start..aligned_query_end])
+    else {
+        violation.problems.push(Problem::SoftClipsExceedSequence);
+        return Ok(RecordValidation::Violation(violation));
+    };
+
+    // without a CIGAR string, the end of the alignment in the reference is unknown
+    let (end, mode) = if cigar.is_empty() {
+        (
+            contig.len().min(start + aligned_query.len() + band),
+            AlignmentMode::FreeReferenceEnd,
+        )
+    } else {
+        (cigar.end_pos() as usize, AlignmentMode::Global)
+    };
+
+    let Some(reference_span) = contig.get(start..end) else {
+        violation.problems.push(Problem::OutOfReferenceBounds);
+        return Ok(RecordValidation::Violation(violation));
+    };
+
+    let realigned_edit_distance =
+        edit_distance::banded_edit_distance(aligned_query, reference_span, band, mode);
+    violation.realigned_edit_distance = realigned_edit_distance;
+
+    match reported_edit_distance {
+        None => violation.problems.push(Problem::MissingNm),
+        Some(reported) if Some(reported) != realigned_edit_distance => {
+            violation.problems.push(Problem::NmMismatch)
+        }
+        _ => {}
+    }
+
+    if realigned_edit_distance.is_none_or(|edit_distance| edit_distance > max_errors) {
+        violation.problems.push(Problem::ExceedsErrorBound);
+    }
+
+    Ok(if violation.problems.is_empty() {
+        RecordValidation::Valid
+    } else {
+        RecordValidation::Violation(violation)
+    })
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct AlignmentValidationReport {
+    pub num_alignments: usize,
+    pub num_not_checkable: usize,
+    pub num_valid: usize,
+    pub num_violations: usize,
+    pub violations: Vec<AlignmentViolation>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AlignmentViolation {
+    pub query_name: String,
+    pub reference_name: String,
+    // 1-based
+    pub position: u64,
+    pub reverse_strand: bool,
+    pub secondary: bool,
+    pub max_errors: usize,
+    pub reported_edit_distance: Option<usize>,
+    // None if it is larger than the error bound and the reported edit distance
+    pub realigned_edit_distance: Option<usize>,
+    pub problems: Vec<Problem>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Problem {
+    MissingNm,
+    NmMismatch,
+    ExceedsErrorBound,
+    OutOfReferenceBounds,
+    SoftClipsExceedSequence,
+}
+
+impl AlignmentValidationReport {
+    fn add(&mut self, validation: RecordValidation) {
+        self.num_alignments += 1;
+
+        match validation {
+            RecordValidation::NotCheckable => self.num_not_checkable += 1,
+            RecordValidation::Valid => self.num_valid += 1,
+            RecordValidation::Violation(violation) => {
+                self.num_violations += 1;
+                self.violations.push(violation);
+            }
+        }
+    }
+
+    pub fn print(&self) {
+        println!(
+            "- Alignment validation: {} valid, {} violations, {} not checkable (of {} alignments)",
+            self.num_valid, self.num_violations, self.num_not_checkable, self.num_alignments
+        );
+
+        for violation in self.violations.iter().take(10) {
+            println!(
+                "  - {} at {}:{}: {:?}",
+                violation.query_name,
+                violation.reference_name,
+                violation.position,
+                violation.problems
+            );
+        }
+
+        if self.violations.len() > 10 {
+            println!("  - ... (all violations are in the report file)");
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        fs::write(path, toml::to_string(self)?)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_htslib::bam::record::CigarString;
+
+    use super::*;
+
+    fn record(cigar: Vec<Cigar>, sequence: &[u8]) -> bam::Record {
+        let mut record = bam::Record::new();
+        record.set(
+            b"read",
+            Some(&CigarString(cigar)),
+            sequence,
+            &vec![30; sequence.len()],
+        );
+        record.set_tid(0);
+        record.set_pos(2);
+        record.push_aux(b"NM", bam::record::Aux::U8(0)).unwrap();
+        record
+    }
+
+    fn validate(record: &bam::Record) -> RecordValidation {
+        let reference =
+            ReferenceSequences::from([(String::from("chr1"), b"GGACGTACGTGG".to_vec())]);
+
+        validate_record(
+            record,
+            &[String::from("chr1")],
+            &reference,
+            QueryErrors::Exact(0),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn soft_clipped_alignment_is_valid() {
+        let record = record(
+            vec![Cigar::SoftClip(2), Cigar::Match(8), Cigar::SoftClip(1)],
+            b"TTACGTACGTC",
+        );
+
+        assert!(matches!(validate(&record), RecordValidation::Valid));
+    }
+
+    #[test]
+    fn soft_clips_longer_than_the_sequence_are_a_violation() {
+        let record = record(
+            vec![Cigar::SoftClip(6), Cigar::Match(4), Cigar::SoftClip(6)],
+            b"ACGTACGTAC",
+        );
+
+        let RecordValidation::Violation(violation) = validate(&record) else {
+            panic!("expected a violation");
+        };
+        assert!(matches!(
+            violation.problems.as_slice(),
+            [Problem::SoftClipsExceedSequence]
+        ));
+    }
+}
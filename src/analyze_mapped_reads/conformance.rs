@@ -0,0 +1,386 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fs,
+    path::Path,
+};
+
+use anyhow::{Context, Result};
+use rust_htslib::bam::{self, record::Cigar, Read};
+use serde::Serialize;
+use strum::Display;
+
+use crate::alignment_files;
+
+// all findings are counted, but only this many are listed per kind
+const MAX_LISTED_FINDINGS_PER_KIND: usize = 100;
+
+// checks a SAM/BAM file of a mapper for violations of the SAM specification and of the conventions
+// that the analyses rely on
+pub fn check_conformance(
+    mapped_reads_path: &Path,
+    reference_path: &Path,
+) -> Result<ConformanceReport> {
+    let mut report = ConformanceReport::default();
+
+    let mut bam = bam::Reader::from_path(mapped_reads_path)
+        .with_context(|| format!("opening mapped reads {}", mapped_reads_path.display()))?;
+    let header = bam.header().clone();
+    let reference_names = alignment_files::reference_names(&header);
+
+    check_header(&header, reference_path, &mut report);
+
+    let mut queries: HashMap<String, QueryRecords> = HashMap::new();
+
+    for record in bam.records() {
+        let record = record?;
+        report.num_records += 1;
+
+        let query_name = String::from_utf8_lossy(record.qname()).into_owned();
+        check_record(&record, &query_name, &header, &mut report);
+
+        let query = queries.entry(query_name).or_default();
+
+        if record.is_unmapped() {
+            query.num_unmapped += 1;
+            if !record.is_secondary() && !record.is_supplementary() {
+                query.num_primary += 1;
+            }
+            continue;
+        }
+
+        if record.is_secondary() {
+            query.max_secondary_mapq = query.max_secondary_mapq.max(Some(record.mapq()));
+        } else if !record.is_supplementary() {
+            query.num_primary += 1;
+            query.primary_mapq = Some(record.mapq());
+        }
+
+        let reference_name = usize::try_from(record.tid())
+            .ok()
+            .and_then(|tid| reference_names.get(tid).cloned());
+        let alignment = (
+            reference_name,
+            record.pos(),
+            record.is_reverse(),
+            record.cigar().to_string(),
+        );
+        if !query.alignments.insert(alignment) {
+            query.num_duplicates += 1;
+        }
+    }
+
+    report.num_queries = queries.len();
+
+    let mut queries: Vec<_> = queries.into_iter().collect();
+    queries.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+
+    for (query_name, query) in queries {
+        check_query(query_name, query, &mut report);
+    }
+
+    Ok(report)
+}
+
+#[derive(Debug, Default)]
+struct QueryRecords {
+    num_primary: usize,
+    num_unmapped: usize,
+    num_duplicates: usize,
+    primary_mapq: Option<u8>,
+    max_secondary_mapq: Option<u8>,
+    alignments: HashSet<(Option<String>, i64, bool, String)>,
+}
+
+fn check_header(header: &bam::HeaderView, reference_path: &Path, report: &mut ConformanceReport) {
+    let header_map = bam::Header::from_template(header).to_hashmap();
+    if !header_map.contains_key("PG") {
+        report.add(
+            FindingKind::MissingProgramLine,
+            None,
+            "no @PG line".to_owned(),
+        );
+    }
+
+    // e.g. a gzip compressed reference or a read-only folder without an index
+    let reference_lengths: HashMap<_, _> = match alignment_files::reference_lengths(reference_path)
+    {
+        Ok(reference_lengths) => reference_lengths.into_iter().collect(),
+        Err(err) => {
+            println!(
+                "- Warning: skipped the comparison of the @SQ lines with the reference: {err:#}"
+            );
+            return;
+        }
+    };
+
+    let mut header_names = HashSet::new();
+
+    for (tid, name) in alignment_files::reference_names(header)
+        .into_iter()
+        .enumerate()
+    {
+        let header_length = header.target_len(tid as u32).unwrap_or_default() as usize;
+
+        match reference_lengths.get(&name) {
+            None => report.add(
+                FindingKind::UnknownContig,
+                None,
+                format!("@SQ {name} is not in the reference"),
+            ),
+            Some(&reference_length) if reference_length != header_length => report.add(
+                FindingKind::ContigLengthMismatch,
+                None,
+                format!("@SQ {name} has LN {header_length}, but the reference has length {reference_length}"),
+            ),
+            _ => {}
+        }
+
+        header_names.insert(name);
+    }
+
+    let mut missing_contigs: Vec<_> = reference_lengths
+        .keys()
+        .filter(|name| !header_names.contains(*name))
+        .collect();
+    missing_contigs.sort_unstable();
+
+    for name in missing_contigs {
+        report.add(
+            FindingKind::MissingContig,
+            None,
+            format!("reference contig {name} has no @SQ line"),
+        );
+    }
+}
+
+fn check_record(
+    record: &bam::Record,
+    query_name: &str,
+    header: &bam::HeaderView,
+    report: &mut ConformanceReport,
+) {
+    let mut add = |kind, detail: String| report.add(kind, Some(query_name), detail);
+
+    if record.is_secondary() && record.is_supplementary() {
+        add(
+            FindingKind::ConflictingFlags,
+            format!("flag {} is secondary and supplementary", record.flags()),
+        );
+    }
+
+    // a missing QUAL is stored as 0xff bytes
+    let has_sequence = record.seq_len() > 0;
+    let has_quality = record
+        .qual()
+        .first()
+        .is_some_and(|&quality| quality != 0xff);
+
+    if has_quality && !has_sequence {
+        add(
+            FindingKind::QualityWithoutSequence,
+            "QUAL is given, but SEQ is *".to_owned(),
+        );
+    }
+
+    // secondary alignments may omit SEQ, everything else has to contain it
+    if !has_sequence && !record.is_secondary() {
+        add(
+            FindingKind::MissingSequence,
+            format!("SEQ is * for flag {}", record.flags()),
+        );
+    }
+
+    if record.is_unmapped() {
+        if record.is_secondary() || record.is_supplementary() || record.is_reverse() {
+            add(
+                FindingKind::UnmappedWithAlignmentFlags,
+                format!(
+                    "flag {} is unmapped and has alignment flags",
+                    record.flags()
+                ),
+            );
+        }
+
+        if record.mapq() != 0 {
+            add(
+                FindingKind::UnmappedWithMapq,
+                format!("MAPQ {} for an unmapped record", record.mapq()),
+            );
+        }
+
+        return;
+    }
+
+    if record.tid() < 0 || record.pos() < 0 {
+        add(
+            FindingKind::MappedWithoutPosition,
+            "mapped record without RNAME or POS".to_owned(),
+        );
+        return;
+    }
+
+    // a * CIGAR is valid, e.g. floxer only outputs CIGARs with --cigar-output on
+    let cigar = record.cigar();
+
+    let cigar_query_length: usize = cigar
+        .iter()
+        .filter(|op| {
+            matches!(
+                op,
+                Cigar::Match(_)
+                    | Cigar::Ins(_)
+                    | Cigar::SoftClip(_)
+                    | Cigar::Equal(_)
+                    | Cigar::Diff(_)
+            )
+        })
+        .map(|op| op.len() as usize)
+        .sum();
+
+    if !cigar.is_empty() && has_sequence && cigar_query_length != record.seq_len() {
+        add(
+            FindingKind::CigarSequenceLengthMismatch,
+            format!(
+                "CIGAR {cigar} covers {cigar_query_length} bases, but SEQ has {} bases",
+                record.seq_len()
+            ),
+        );
+    }
+
+    let contig_length = header.target_len(record.tid() as u32).unwrap_or_default();
+    if cigar.end_pos() as u64 > contig_length {
+        add(
+            FindingKind::AlignmentOutOfContigBounds,
+            format!(
+                "alignment ends at {}, but the contig has length {contig_length}",
+                cigar.end_pos()
+            ),
+        );
+    }
+}
+
+fn check_query(query_name: String, query: QueryRecords, report: &mut ConformanceReport) {
+    let query_name = Some(query_name.as_str());
+
+    match query.num_primary {
+        0 => report.add(
+            FindingKind::NoPrimaryRecord,
+            query_name,
+            "no primary or unmapped record".to_owned(),
+        ),
+        1 => {}
+        num_primary => report.add(
+            FindingKind::MultiplePrimaryRecords,
+            query_name,
+            format!("{num_primary} primary or unmapped records"),
+        ),
+    }
+
+    if query.num_unmapped > 0 && !query.alignments.is_empty() {
+        report.add(
+            FindingKind::UnmappedAndMapped,
+            query_name,
+            "unmapped record for a query with alignments".to_owned(),
+        );
+    }
+
+    if let (Some(primary_mapq), Some(secondary_mapq)) =
+        (query.primary_mapq, query.max_secondary_mapq)
+    {
+        if primary_mapq != 255 && secondary_mapq != 255 && secondary_mapq > primary_mapq {
+            report.add(
+                FindingKind::SecondaryMapqAbovePrimary,
+                query_name,
+                format!("secondary MAPQ {secondary_mapq} is above primary MAPQ {primary_mapq}"),
+            );
+        }
+    }
+
+    if query.num_duplicates > 0 {
+        report.add(
+            FindingKind::DuplicateAlignment,
+            query_name,
+            format!(
+                "{} alignments with the same position, strand and CIGAR as another one",
+                query.num_duplicates
+            ),
+        );
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Display)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum FindingKind {
+    MissingProgramLine,
+    UnknownContig,
+    ContigLengthMismatch,
+    MissingContig,
+    ConflictingFlags,
+    UnmappedWithAlignmentFlags,
+    UnmappedWithMapq,
+    MappedWithoutPosition,
+    MissingSequence,
+    QualityWithoutSequence,
+    CigarSequenceLengthMismatch,
+    AlignmentOutOfContigBounds,
+    NoPrimaryRecord,
+    MultiplePrimaryRecords,
+    UnmappedAndMapped,
+    SecondaryMapqAbovePrimary,
+    DuplicateAlignment,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ConformanceFinding {
+    pub kind: FindingKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub query_name: Option<String>,
+    pub detail: String,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct ConformanceReport {
+    pub num_records: usize,
+    pub num_queries: usize,
+    pub num_findings: usize,
+    pub num_findings_per_kind: BTreeMap<String, usize>,
+    pub findings: Vec<ConformanceFinding>,
+}
+
+impl ConformanceReport {
+    fn add(&mut self, kind: FindingKind, query_name: Option<&str>, detail: String) {
+        self.num_findings += 1;
+
+        let count = self
+            .num_findings_per_kind
+            .entry(kind.to_string())
+            .or_default();
+        *count += 1;
+
+        if *count <= MAX_LISTED_FINDINGS_PER_KIND {
+            self.findings.push(ConformanceFinding {
+                kind,
+                query_name: query_name.map(str::to_owned),
+                detail,
+            });
+        }
+    }
+
+    pub fn print(&self) {
+        println!(
+            "- SAM/BAM conformance: {} findings in {} records of {} queries",
+            self.num_findings, self.num_records, self.num_queries
+        );
+
+        for (kind, count) in &self.num_findings_per_kind {
+            println!("  - {kind}: {count}");
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        fs::write(path, toml::to_string(self)?)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,235 @@
+use std::{collections::HashMap, fs, path::Path, str::FromStr};
+
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+
+use crate::{
+    alignment_files::{AlignmentKind, AlignmentsPerQuery},
+    datasets::fastx::FastxReader,
+};
+
+// roughly log-spaced, nanopore reads range from a few hundred bases to more than 100 kb
+pub const DEFAULT_LENGTH_BINS: &str = "500,1000,2000,5000,10000,20000,50000,100000";
+
+// the smallest threshold of log-spaced bins
+const LOG_BINS_START: f64 = 100.0;
+const LOG_BINS_END: f64 = 1_000_000.0;
+
+// same binning as the floxer histograms: a query belongs to the first threshold that is at least as
+// large as its length, the last bin contains all queries that are longer than every threshold
+#[derive(Debug, Clone)]
+pub struct LengthBins {
+    pub thresholds: Vec<usize>,
+}
+
+impl LengthBins {
+    fn bin_index(&self, query_length: usize) -> usize {
+        self.thresholds
+            .partition_point(|&threshold| threshold < query_length)
+    }
+
+    fn labels(&self) -> Vec<String> {
+        self.thresholds
+            .iter()
+            .map(|threshold| format!("<={threshold}"))
+            .chain(self.thresholds.last().map(|last| format!(">{last}")))
+            .collect()
+    }
+}
+
+// either comma separated thresholds (e.g. 1000,10000,100000) or log:<bins per decade>
+// for log-spaced thresholds from 100 to 1,000,000
+impl FromStr for LengthBins {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let thresholds: Vec<usize> = if let Some(bins_per_decade) = s.strip_prefix("log:") {
+            let bins_per_decade: u32 = bins_per_decade
+                .parse()
+                .ok()
+                .filter(|&bins| bins > 0)
+                .ok_or_else(|| format!("invalid number of bins per decade: {bins_per_decade}"))?;
+
+            let num_thresholds = (LOG_BINS_END / LOG_BINS_START).log10() as u32 * bins_per_decade;
+            let mut thresholds: Vec<_> = (0..=num_thresholds)
+                .map(|i| {
+                    (LOG_BINS_START * 10f64.powf(i as f64 / bins_per_decade as f64)).round()
+                        as usize
+                })
+                .collect();
+            thresholds.dedup();
+            thresholds
+        } else {
+            s.split(',')
+                .map(|threshold| {
+                    threshold
+                        .trim()
+                        .parse()
+                        .map_err(|_| format!("invalid length bin threshold: {threshold}"))
+                })
+                .collect::<Result<_, _>>()?
+        };
+
+        if thresholds.is_empty() || !thresholds.windows(2).all(|pair| pair[0] < pair[1]) {
+            return Err(String::from(
+                "length bin thresholds must be strictly increasing",
+            ));
+        }
+
+        Ok(Self { thresholds })
+    }
+}
+
+// search and verification time of a single query, from the per query stats of floxer
+#[derive(Debug, Clone, Copy)]
+pub struct QueryTimings {
+    pub milliseconds_search: f64,
+    pub milliseconds_verification: f64,
+}
+
+// floxer writes a TSV with a header and the columns query_id, query_length, milliseconds_search
+// and milliseconds_verification (in this order)
+pub fn read_query_timings(path: &Path) -> Result<HashMap<String, QueryTimings>> {
+    let query_stats_str = fs::read_to_string(path)
+        .with_context(|| format!("reading the per query stats {}", path.display()))?;
+
+    query_stats_str
+        .lines()
+        .enumerate()
+        .skip(1)
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(line_index, line)| {
+            let fields: Vec<_> = line.split('\t').collect();
+            let [query_id, _, search, verification] = fields[..] else {
+                bail!(
+                    "line {} of {} does not have 4 columns",
+                    line_index + 1,
+                    path.display()
+                );
+            };
+
+            let parse = |value: &str| {
+                value.parse::<f64>().with_context(|| {
+                    format!(
+                        "invalid time {value} in line {} of {}",
+                        line_index + 1,
+                        path.display()
+                    )
+                })
+            };
+
+            Ok((
+                query_id.to_owned(),
+                QueryTimings {
+                    milliseconds_search: parse(search)?,
+                    milliseconds_verification: parse(verification)?,
+                },
+            ))
+        })
+        .collect()
+}
+
+// the read lengths are taken from the queries, such that unmapped reads are binned as well. With
+// the per query timings (only available for floxer), the mean times per read are reported as well
+pub fn analyze_by_query_length(
+    alignments_per_query: &AlignmentsPerQuery,
+    queries_path: &Path,
+    length_bins: &LengthBins,
+    query_timings: Option<&HashMap<String, QueryTimings>>,
+) -> Result<LengthBinnedStats> {
+    let num_bins = length_bins.thresholds.len() + 1;
+    let mut num_queries = vec![0; num_bins];
+    let mut num_mapped = vec![0; num_bins];
+    let mut edit_distance_rates: Vec<Vec<f64>> = vec![Vec::new(); num_bins];
+    let mut timings: Vec<Vec<QueryTimings>> = vec![Vec::new(); num_bins];
+
+    for record in FastxReader::from_path(queries_path)? {
+        let record = record?;
+        let bin_index = length_bins.bin_index(record.seq.len());
+        num_queries[bin_index] += 1;
+
+        if let Some(query_timings) = query_timings.and_then(|timings| timings.get(record.id())) {
+            timings[bin_index].push(*query_timings);
+        }
+
+        let Some(primary) = alignments_per_query
+            .get(record.id())
+            .into_iter()
+            .flatten()
+            .find(|alignment| alignment.kind == AlignmentKind::Primary)
+        else {
+            continue;
+        };
+
+        num_mapped[bin_index] += 1;
+
+        if let Some(edit_distance) = primary.edit_distance {
+            edit_distance_rates[bin_index]
+                .push(edit_distance as f64 / primary.aligned_query_length().max(1) as f64);
+        }
+    }
+
+    let bins = length_bins
+        .labels()
+        .into_iter()
+        .enumerate()
+        .map(|(index, label)| LengthBinStats {
+            label,
+            num_queries: num_queries[index],
+            num_mapped: num_mapped[index],
+            mapping_rate: num_mapped[index] as f64 / num_queries[index].max(1) as f64,
+            mean_edit_distance_rate: mean(&edit_distance_rates[index]),
+            mean_milliseconds_search_per_read: mean(
+                &timings[index]
+                    .iter()
+                    .map(|timings| timings.milliseconds_search)
+                    .collect::<Vec<_>>(),
+            ),
+            mean_milliseconds_verification_per_read: mean(
+                &timings[index]
+                    .iter()
+                    .map(|timings| timings.milliseconds_verification)
+                    .collect::<Vec<_>>(),
+            ),
+        })
+        .collect();
+
+    Ok(LengthBinnedStats {
+        thresholds: length_bins.thresholds.clone(),
+        bins,
+    })
+}
+
+fn mean(values: &[f64]) -> Option<f64> {
+    (!values.is_empty()).then(|| values.iter().sum::<f64>() / values.len() as f64)
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LengthBinnedStats {
+    pub thresholds: Vec<usize>,
+    pub bins: Vec<LengthBinStats>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LengthBinStats {
+    pub label: String,
+    pub num_queries: i64,
+    pub num_mapped: i64,
+    pub mapping_rate: f64,
+    // edit distance divided by the aligned query length of the primary alignments
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mean_edit_distance_rate: Option<f64>,
+    // only for floxer builds that write the per query stats
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mean_milliseconds_search_per_read: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mean_milliseconds_verification_per_read: Option<f64>,
+}
+
+impl LengthBinnedStats {
+    pub fn save(&self, path: &Path) -> Result<()> {
+        fs::write(path, toml::to_string(self)?)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,87 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+// SAM uses 255 if the MAPQ is not available
+const MAPQ_UNAVAILABLE: u8 = 255;
+
+// mapeval-style calibration of the MAPQ values of a mapper on a simulated dataset. The primary
+// alignments are bucketed by MAPQ and accumulated from the highest MAPQ downwards, such that every
+// point of the curve is the sensitivity and error rate when only alignments with at least this
+// MAPQ are kept.
+#[derive(Debug, Clone, Serialize)]
+pub struct MapqCalibration {
+    pub num_reads: usize,
+    pub num_mapq_unavailable: usize,
+    // in descending order of MAPQ
+    pub points: Vec<MapqCalibrationPoint>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MapqCalibrationPoint {
+    pub mapq: u8,
+    // primary alignments with exactly this MAPQ
+    pub num_mapped: usize,
+    // primary alignments with this MAPQ that do not hit the true origin of the read
+    pub num_wrong: usize,
+    // MAPQ that the observed error rate of this bucket corresponds to, with a pseudo count such
+    // that buckets without wrong alignments are finite
+    pub empirical_mapq: f64,
+    // of all primary alignments with at least this MAPQ
+    pub cumulative_num_mapped: usize,
+    pub cumulative_num_wrong: usize,
+    // relative to all reads of the dataset
+    pub cumulative_fraction_mapped: f64,
+    pub cumulative_error_rate: f64,
+}
+
+impl MapqCalibration {
+    // the primary alignments are given as (MAPQ, whether it hits the truth)
+    pub fn new(num_reads: usize, primary_alignments: impl IntoIterator<Item = (u8, bool)>) -> Self {
+        let mut num_mapq_unavailable = 0;
+        let mut counts_per_mapq: BTreeMap<u8, (usize, usize)> = BTreeMap::new();
+
+        for (mapq, hits_truth) in primary_alignments {
+            if mapq == MAPQ_UNAVAILABLE {
+                num_mapq_unavailable += 1;
+                continue;
+            }
+
+            let (num_mapped, num_wrong) = counts_per_mapq.entry(mapq).or_default();
+            *num_mapped += 1;
+            *num_wrong += !hits_truth as usize;
+        }
+
+        let mut cumulative_num_mapped = 0;
+        let mut cumulative_num_wrong = 0;
+
+        let points = counts_per_mapq
+            .into_iter()
+            .rev()
+            .map(|(mapq, (num_mapped, num_wrong))| {
+                cumulative_num_mapped += num_mapped;
+                cumulative_num_wrong += num_wrong;
+
+                MapqCalibrationPoint {
+                    mapq,
+                    num_mapped,
+                    num_wrong,
+                    empirical_mapq: -10.0
+                        * ((num_wrong as f64 + 1.0) / (num_mapped as f64 + 1.0)).log10(),
+                    cumulative_num_mapped,
+                    cumulative_num_wrong,
+                    cumulative_fraction_mapped: cumulative_num_mapped as f64
+                        / num_reads.max(1) as f64,
+                    cumulative_error_rate: cumulative_num_wrong as f64
+                        / cumulative_num_mapped as f64,
+                }
+            })
+            .collect();
+
+        Self {
+            num_reads,
+            num_mapq_unavailable,
+            points,
+        }
+    }
+}
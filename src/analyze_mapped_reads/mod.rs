@@ -0,0 +1,1326 @@
+pub mod conformance;
+pub mod length_bins;
+pub mod mapq_calibration;
+pub mod multi_mapping;
+pub mod oracle;
+pub mod regions;
+
+use core::str;
+use std::{
+    collections::{HashMap, HashSet},
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    process::Command,
+    rc::Rc,
+};
+
+use anyhow::{bail, Result};
+use rust_htslib::bam::{self, Read};
+use serde::{Deserialize, Serialize};
+use strum::Display;
+
+use crate::{
+    alignment_files::{
+        self, AlignmentKind, AlignmentSummary, AlignmentsPerQuery, EditDistanceRecomputer,
+        EditDistanceReport, ReferenceSequences,
+    },
+    config::BenchmarkSuiteConfig,
+    datasets::{
+        self,
+        simulated::SimulatedDataset,
+        simulator::{self, ReadTruth},
+    },
+    folder_structure::BenchmarkFolder,
+    readmappers::{floxer::HistogramData, Queries},
+    statistics::{self, McNemarTest, WilcoxonTest},
+};
+
+use mapq_calibration::MapqCalibration;
+
+// reads the output of a mapper (SAM, BAM or PAF) once for all analyses of an instance. Optionally,
+// the edit distances are recomputed (with the reference, if it is loaded) and checked against the
+// reported NM values.
+pub fn read_instance_alignments(
+    mapped_reads_path: &Path,
+    recompute_edit_distances: bool,
+    reference: Option<&ReferenceSequences>,
+) -> Result<(AlignmentsPerQuery, Option<EditDistanceReport>)> {
+    let mut edit_distance_recomputer =
+        recompute_edit_distances.then(|| EditDistanceRecomputer::new(reference));
+
+    let alignments_per_query = alignment_files::read_alignments_per_query(
+        mapped_reads_path,
+        edit_distance_recomputer.as_mut(),
+    )?;
+
+    let edit_distance_check = edit_distance_recomputer.map(EditDistanceRecomputer::into_report);
+    if let Some(report) = edit_distance_check
+        .as_ref()
+        .filter(|report| report.num_mismatches > 0)
+    {
+        println!(
+            "- Warning: the reported NM of {} of {} alignments in {} differs from the recomputed edit distance",
+            report.num_mismatches,
+            report.num_alignments,
+            mapped_reads_path.display()
+        );
+    }
+
+    Ok((alignments_per_query, edit_distance_check))
+}
+
+// supplementary alignments are grouped per query
+pub fn analyze_alignments_simple(
+    alignments_per_query: &AlignmentsPerQuery,
+    edit_distance_check: Option<EditDistanceReport>,
+) -> SimpleMappedReadsStats {
+    let mut num_mapped = 0;
+    let mut num_supplementary_alignments = 0;
+    let mut chimeric_reads = Vec::new();
+    let mut primary_alignment_values = AlignmentValues::default();
+    let mut secondary_alignment_values = AlignmentValues::default();
+
+    for (query_name, query_alignments) in alignments_per_query {
+        for alignment in query_alignments {
+            match alignment.kind {
+                AlignmentKind::Primary => primary_alignment_values.add(alignment),
+                AlignmentKind::Secondary => secondary_alignment_values.add(alignment),
+                AlignmentKind::Supplementary => {}
+            }
+        }
+
+        if !query_alignments
+            .iter()
+            .any(|alignment| alignment.kind == AlignmentKind::Primary)
+        {
+            continue;
+        }
+
+        num_mapped += 1;
+
+        // the primary alignment and all supplementary alignments form a chimeric read
+        let segments: Vec<_> = query_alignments
+            .iter()
+            .filter(|alignment| alignment.kind != AlignmentKind::Secondary)
+            .collect();
+
+        if segments.len() > 1 {
+            num_supplementary_alignments += segments.len() as i32 - 1;
+
+            chimeric_reads.push(ChimericRead {
+                query_name: query_name.clone(),
+                num_segments: segments.len() as i32,
+                covered_fraction: covered_fraction(&segments),
+            });
+        }
+    }
+
+    chimeric_reads.sort_unstable_by(|a, b| a.query_name.cmp(&b.query_name));
+
+    SimpleMappedReadsStats {
+        num_mapped,
+        num_chimeric: chimeric_reads.len() as i32,
+        num_supplementary_alignments,
+        primary_alignment_stats: primary_alignment_values.into_histograms(),
+        secondary_alignment_stats: secondary_alignment_values.into_histograms(),
+        chimeric_reads,
+        edit_distance_check,
+    }
+}
+
+pub const EDIT_DISTANCE_THRESHOLDS: [usize; 9] = [0, 10, 50, 100, 200, 500, 1000, 2000, 5000];
+
+#[derive(Default)]
+struct AlignmentValues {
+    mapqs: Vec<usize>,
+    identities_percent: Vec<usize>,
+    edit_distances: Vec<usize>,
+    soft_clipping: Vec<usize>,
+    hard_clipping: Vec<usize>,
+    longest_indels: Vec<usize>,
+    strands: Vec<usize>,
+    reference_spans: Vec<usize>,
+}
+
+impl AlignmentValues {
+    fn add(&mut self, alignment: &AlignmentSummary) {
+        self.mapqs.push(alignment.mapq as usize);
+
+        if let Some(edit_distance) = alignment.edit_distance {
+            let aligned_length = alignment.aligned_query_length().max(1);
+            let identity = 1.0 - edit_distance as f64 / aligned_length as f64;
+
+            self.identities_percent
+                .push((identity.max(0.0) * 100.0).round() as usize);
+            self.edit_distances.push(edit_distance);
+        }
+
+        self.soft_clipping.push(alignment.soft_clipped_bases);
+        self.hard_clipping.push(alignment.hard_clipped_bases);
+        self.longest_indels.push(alignment.longest_indel);
+        self.strands.push(alignment.reverse_strand as usize);
+        self.reference_spans.push(alignment.reference_span);
+    }
+
+    fn into_histograms(self) -> AlignmentHistograms {
+        let clipping_thresholds = vec![0, 10, 100, 500, 1000, 5000, 10000];
+
+        AlignmentHistograms {
+            mapq: HistogramData::from_values(self.mapqs, vec![0, 1, 5, 10, 20, 30, 40, 50, 60]),
+            identity_percent: HistogramData::from_values(
+                self.identities_percent,
+                vec![50, 60, 70, 80, 85, 90, 95, 98, 99, 100],
+            ),
+            edit_distance: HistogramData::from_values(
+                self.edit_distances,
+                EDIT_DISTANCE_THRESHOLDS.to_vec(),
+            ),
+            soft_clipping: HistogramData::from_values(
+                self.soft_clipping,
+                clipping_thresholds.clone(),
+            ),
+            hard_clipping: HistogramData::from_values(self.hard_clipping, clipping_thresholds),
+            longest_indel: HistogramData::from_values(
+                self.longest_indels,
+                vec![0, 1, 2, 5, 10, 20, 50, 100, 500],
+            ),
+            strand: HistogramData::from_values(self.strands, vec![0, 1]),
+            reference_span: HistogramData::from_values(
+                self.reference_spans,
+                vec![100, 500, 1000, 2000, 5000, 10000, 20000, 50000, 100000],
+            ),
+        }
+    }
+}
+
+// fraction of the query that is part of at least one of the alignments
+fn covered_fraction(alignments: &[&AlignmentSummary]) -> f64 {
+    let mut intervals: Vec<_> = alignments
+        .iter()
+        .map(|alignment| (alignment.query_start, alignment.query_end))
+        .collect();
+    intervals.sort_unstable();
+
+    let mut covered = 0;
+    let mut covered_until = 0;
+    for (start, end) in intervals {
+        let start = start.max(covered_until);
+        if end > start {
+            covered += end - start;
+            covered_until = end;
+        }
+    }
+
+    let query_length = alignments
+        .iter()
+        .map(|alignment| alignment.query_length)
+        .max()
+        .unwrap_or(0);
+
+    covered as f64 / query_length.max(1) as f64
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SimpleMappedReadsStats {
+    pub num_mapped: i32,
+    pub num_chimeric: i32,
+    pub num_supplementary_alignments: i32,
+    pub primary_alignment_stats: AlignmentHistograms,
+    pub secondary_alignment_stats: AlignmentHistograms,
+    pub chimeric_reads: Vec<ChimericRead>,
+    // only if the edit distances were recomputed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub edit_distance_check: Option<EditDistanceReport>,
+}
+
+// same shape as the stats of floxer, such that they can be plotted the same way
+#[derive(Debug, Clone, Serialize)]
+pub struct AlignmentHistograms {
+    pub mapq: HistogramData,
+    pub identity_percent: HistogramData,
+    pub edit_distance: HistogramData,
+    pub soft_clipping: HistogramData,
+    pub hard_clipping: HistogramData,
+    pub longest_indel: HistogramData,
+    // 0 is the forward and 1 the reverse strand
+    pub strand: HistogramData,
+    pub reference_span: HistogramData,
+}
+
+impl AlignmentHistograms {
+    pub fn iter_histograms(&self) -> impl Iterator<Item = &HistogramData> {
+        [
+            &self.mapq,
+            &self.identity_percent,
+            &self.edit_distance,
+            &self.soft_clipping,
+            &self.hard_clipping,
+            &self.longest_indel,
+            &self.strand,
+            &self.reference_span,
+        ]
+        .into_iter()
+    }
+
+    pub fn iter_metric_names(&self) -> impl Iterator<Item = &'static str> {
+        [
+            "MAPQ",
+            "Identity (%)",
+            "Edit distance",
+            "Soft clipping",
+            "Hard clipping",
+            "Longest indel",
+            "Strand (0 = forward, 1 = reverse)",
+            "Reference span",
+        ]
+        .into_iter()
+    }
+}
+
+impl SimpleMappedReadsStats {
+    pub fn save(&self, path: &Path) -> Result<()> {
+        fs::write(path, toml::to_string(self)?)?;
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ChimericRead {
+    pub query_name: String,
+    // primary and supplementary alignments
+    pub num_segments: i32,
+    pub covered_fraction: f64,
+}
+
+// fraction of the query that has to be clipped for an alignment to count as significantly clipped
+const SIGNIFICANT_CLIPPING_FRACTION: f64 = 0.1;
+
+// compares the alignments of any number of read mappers (or read mapper configurations)
+// and writes the result to the benchmark folder
+pub fn analyze_alignments_comparison<S: AsRef<str>, P: AsRef<Path>>(
+    labelled_alignments: impl IntoIterator<Item = (S, P, Rc<AlignmentsPerQuery>)>,
+    error_rate: f64,
+    benchmark_folder: &BenchmarkFolder,
+) -> Result<MapperComparison> {
+    let comparison = compare_mappers(labelled_alignments, error_rate)?;
+
+    let mut result_file_path = benchmark_folder.get().to_owned();
+    result_file_path.push("mapper_comparison.toml");
+
+    fs::write(result_file_path, toml::to_string(&comparison)?)?;
+
+    Ok(comparison)
+}
+
+// floxer is expected to be the first and minimap the second mapper of the comparison
+pub fn analyze_alignments_detailed_comparison(
+    comparison: &MapperComparison,
+    benchmark_folder: &BenchmarkFolder,
+) -> Result<DetailedMappedReadsComparison> {
+    let detailed_comparison = DetailedMappedReadsComparison::from_two_way(comparison)?;
+
+    let mut result_file_path = benchmark_folder.get().to_owned();
+    result_file_path.push("detailed_aligner_comparison.toml");
+
+    fs::write(result_file_path, toml::to_string(&detailed_comparison)?)?;
+
+    Ok(detailed_comparison)
+}
+
+// writes a TSV report for the queries that only one mapper mapped, for every mapper. With the
+// source queries, these queries are also written as a query dataset and registered as
+// <dataset name prefix>_only_<mapper>_mapped
+pub fn export_discrepancies(
+    comparison: &MapperComparison,
+    output_folder: &Path,
+    source_queries_and_dataset_name_prefix: Option<(&Queries, &str)>,
+    suite_config: &BenchmarkSuiteConfig,
+) -> Result<()> {
+    fs::create_dir_all(output_folder)?;
+
+    for (mapper_index, mapper) in comparison.mappers.iter().enumerate() {
+        let query_names = &comparison.exclusively_mapped_queries[mapper_index];
+        let file_name = format!("only_{}_mapped", sanitize_name(mapper));
+
+        let mut report_path = output_folder.to_owned();
+        report_path.push(&file_name);
+        report_path.set_extension("tsv");
+        write_discrepancy_report(
+            &report_path,
+            query_names,
+            &comparison.mappers,
+            &comparison.alignments_per_mapper,
+        )?;
+
+        println!(
+            "- {} queries were only mapped by {mapper}, see {}",
+            query_names.len(),
+            report_path.display()
+        );
+
+        let Some((source_queries, dataset_name_prefix)) = source_queries_and_dataset_name_prefix
+        else {
+            continue;
+        };
+
+        if query_names.is_empty() {
+            continue;
+        }
+
+        let dataset_name = format!("{}_{file_name}", sanitize_name(dataset_name_prefix));
+        let dataset_path = datasets::derived::derive_only_mapped_by_queries(
+            &dataset_name,
+            source_queries,
+            &query_names.iter().map(String::as_str).collect(),
+            mapper,
+            &comparison.mapped_reads_paths[mapper_index],
+            output_folder,
+            suite_config,
+        )?;
+
+        println!(
+            "- Wrote them to {}, use them with --queries {dataset_name}",
+            dataset_path.display()
+        );
+    }
+
+    Ok(())
+}
+
+fn write_discrepancy_report(
+    report_path: &Path,
+    query_names: &[String],
+    mappers: &[String],
+    alignments_per_mapper: &[Rc<AlignmentsPerQuery>],
+) -> Result<()> {
+    let mut writer = BufWriter::new(File::create(report_path)?);
+
+    write!(writer, "read_id\tlength")?;
+    for mapper in mappers {
+        write!(
+            writer,
+            "\t{mapper}_position\t{mapper}_edit_distance\t{mapper}_cigar\t{mapper}_num_alignments"
+        )?;
+    }
+    writeln!(writer)?;
+
+    for query_name in query_names {
+        let query_alignments: Vec<_> = alignments_per_mapper
+            .iter()
+            .map(|alignments| alignments.get(query_name).map_or(&[][..], Vec::as_slice))
+            .collect();
+
+        let length = query_alignments
+            .iter()
+            .flat_map(|alignments| alignments.iter())
+            .map(|alignment| alignment.query_length)
+            .max()
+            .unwrap_or(0);
+
+        write!(writer, "{query_name}\t{length}")?;
+
+        for alignments in query_alignments {
+            let best = alignments
+                .iter()
+                .find(|alignment| alignment.kind == AlignmentKind::Primary)
+                .or_else(|| alignments.first());
+
+            match best {
+                Some(best) => write!(
+                    writer,
+                    "\t{}:{}:{}\t{}\t{}\t{}",
+                    best.reference_name,
+                    best.reference_start + 1,
+                    if best.reverse_strand { '-' } else { '+' },
+                    best.edit_distance
+                        .map_or(String::from("*"), |edit_distance| edit_distance.to_string()),
+                    best.cigar_summary.as_deref().unwrap_or("*"),
+                    alignments.len()
+                )?,
+                None => write!(writer, "\t*\t*\t*\t0")?,
+            }
+        }
+
+        writeln!(writer)?;
+    }
+
+    writer.flush()?;
+
+    Ok(())
+}
+
+// mapper labels may contain characters that are not allowed in file or dataset names
+fn sanitize_name(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+// the alignments that the instance analyses already read, with the mapper outputs (SAM, BAM or PAF)
+// they were read from. Every query that occurs in at least one of the outputs is counted.
+pub fn compare_mappers<S: AsRef<str>, P: AsRef<Path>>(
+    labelled_alignments: impl IntoIterator<Item = (S, P, Rc<AlignmentsPerQuery>)>,
+    error_rate: f64,
+) -> Result<MapperComparison> {
+    let mut mappers = Vec::new();
+    let mut mapped_reads_paths = Vec::new();
+    let mut query_indices: HashMap<String, usize> = HashMap::new();
+    let mut outcomes_per_mapper: Vec<Vec<Option<QueryOutcome>>> = Vec::new();
+    let mut alignments_per_mapper = Vec::new();
+
+    for (label, path, alignments_per_query) in labelled_alignments {
+        let mut outcomes = Vec::new();
+
+        for (query_name, query_alignments) in alignments_per_query.iter() {
+            let next_index = query_indices.len();
+            let index = *query_indices
+                .entry(query_name.clone())
+                .or_insert(next_index);
+
+            if outcomes.len() <= index {
+                outcomes.resize(index + 1, None);
+            }
+            outcomes[index] = QueryOutcome::from_alignments(query_alignments, error_rate);
+        }
+
+        if mappers.iter().any(|mapper| mapper == label.as_ref()) {
+            bail!("mapper label {} is used more than once", label.as_ref());
+        }
+
+        mappers.push(label.as_ref().to_owned());
+        mapped_reads_paths.push(path.as_ref().to_owned());
+        outcomes_per_mapper.push(outcomes);
+        alignments_per_mapper.push(alignments_per_query);
+    }
+
+    if mappers.is_empty() {
+        bail!("at least one mapper output is needed for a comparison");
+    }
+
+    let mut stats_if_mapped: Vec<_> = mappers
+        .iter()
+        .map(|_| ScopedStatsBuilder::default())
+        .collect();
+    let mut intersections: HashMap<Vec<usize>, (i64, Vec<ScopedStatsBuilder>)> = HashMap::new();
+    // query index -> index of the only mapper that mapped it
+    let mut exclusively_mapped: HashMap<usize, usize> = HashMap::new();
+
+    for query_index in 0..query_indices.len() {
+        let outcomes: Vec<_> = outcomes_per_mapper
+            .iter()
+            .enumerate()
+            .filter_map(|(mapper_index, outcomes)| {
+                outcomes
+                    .get(query_index)
+                    .copied()
+                    .flatten()
+                    .map(|outcome| (mapper_index, outcome))
+            })
+            .collect();
+
+        let members: Vec<_> = outcomes
+            .iter()
+            .map(|(mapper_index, _)| *mapper_index)
+            .collect();
+        if let [mapper_index] = members.as_slice() {
+            exclusively_mapped.insert(query_index, *mapper_index);
+        }
+
+        let (num_queries, member_stats) = intersections.entry(members).or_insert_with(|| {
+            (
+                0,
+                outcomes
+                    .iter()
+                    .map(|_| ScopedStatsBuilder::default())
+                    .collect(),
+            )
+        });
+        *num_queries += 1;
+
+        for ((mapper_index, outcome), member_stats) in outcomes.iter().zip(member_stats) {
+            stats_if_mapped[*mapper_index].add(outcome);
+            member_stats.add(outcome);
+        }
+    }
+
+    let mut intersections: Vec<_> = intersections
+        .into_iter()
+        .map(
+            |(members, (num_queries, member_stats))| MapperIntersection {
+                mappers: members
+                    .iter()
+                    .map(|mapper_index| mappers[*mapper_index].clone())
+                    .collect(),
+                num_queries,
+                stats: member_stats
+                    .into_iter()
+                    .map(ScopedStatsBuilder::build)
+                    .collect(),
+            },
+        )
+        .collect();
+    intersections.sort_by(|a, b| {
+        b.num_queries
+            .cmp(&a.num_queries)
+            .then_with(|| a.mappers.cmp(&b.mappers))
+    });
+
+    let mut paired_tests = Vec::new();
+    for first in 0..mappers.len() {
+        for second in first + 1..mappers.len() {
+            paired_tests.push(paired_read_tests(
+                [first, second],
+                &mappers,
+                &outcomes_per_mapper,
+                query_indices.len(),
+            ));
+        }
+    }
+
+    let number_of_queries = query_indices.len() as i64;
+    let mut exclusively_mapped_queries = vec![Vec::new(); mappers.len()];
+    for (query_name, query_index) in query_indices {
+        if let Some(mapper_index) = exclusively_mapped.get(&query_index) {
+            exclusively_mapped_queries[*mapper_index].push(query_name);
+        }
+    }
+    for query_names in exclusively_mapped_queries.iter_mut() {
+        query_names.sort_unstable();
+    }
+
+    Ok(MapperComparison {
+        mappers,
+        mapped_reads_paths,
+        number_of_queries,
+        stats_if_mapped: stats_if_mapped
+            .into_iter()
+            .map(ScopedStatsBuilder::build)
+            .collect(),
+        intersections,
+        paired_tests,
+        exclusively_mapped_queries,
+        alignments_per_mapper,
+    })
+}
+
+// a query that a mapper has no outcome for counts as unmapped, the edit distances are only
+// compared for the queries that both mappers mapped (with known edit distances)
+fn paired_read_tests(
+    [first, second]: [usize; 2],
+    mappers: &[String],
+    outcomes_per_mapper: &[Vec<Option<QueryOutcome>>],
+    num_queries: usize,
+) -> PairedReadTests {
+    let outcome = |mapper_index: usize, query_index: usize| {
+        outcomes_per_mapper[mapper_index]
+            .get(query_index)
+            .copied()
+            .flatten()
+    };
+
+    let mut num_only_first = 0;
+    let mut num_only_second = 0;
+    let mut edit_distance_pairs = Vec::new();
+
+    for query_index in 0..num_queries {
+        match (outcome(first, query_index), outcome(second, query_index)) {
+            (Some(first_outcome), Some(second_outcome)) => {
+                if let (Some(first_edit_distance), Some(second_edit_distance)) =
+                    (first_outcome.edit_distance, second_outcome.edit_distance)
+                {
+                    edit_distance_pairs
+                        .push((first_edit_distance as f64, second_edit_distance as f64));
+                }
+            }
+            (Some(_), None) => num_only_first += 1,
+            (None, Some(_)) => num_only_second += 1,
+            (None, None) => {}
+        }
+    }
+
+    PairedReadTests {
+        first_mapper: mappers[first].clone(),
+        second_mapper: mappers[second].clone(),
+        mapped: statistics::mcnemar_test(num_only_first, num_only_second),
+        edit_distance: statistics::wilcoxon_signed_rank_test(edit_distance_pairs),
+    }
+}
+
+// the part of the alignments of one query that the comparison needs
+#[derive(Debug, Clone, Copy)]
+struct QueryOutcome {
+    category: AlignmentCategory,
+    multiple_mapping: bool,
+    longest_indel: usize,
+    error_rate: f64,
+    edit_distance: Option<usize>,
+}
+
+impl QueryOutcome {
+    // None if the query is unmapped
+    fn from_alignments(query_alignments: &[AlignmentSummary], error_rate: f64) -> Option<Self> {
+        let (best, category) = categorize_best_alignment(query_alignments, error_rate)?;
+
+        let num_linear_alignments = query_alignments
+            .iter()
+            .filter(|alignment| alignment.kind != AlignmentKind::Supplementary)
+            .count();
+
+        Some(Self {
+            category,
+            multiple_mapping: num_linear_alignments > 1,
+            longest_indel: best.longest_indel,
+            error_rate: best.edit_distance.unwrap_or(0) as f64
+                / best.aligned_query_length().max(1) as f64,
+            edit_distance: best.edit_distance,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlignmentCategory {
+    ChimericOrInversion,
+    SignificantlyClipped,
+    HighEditDistance,
+    Basic,
+}
+
+// categorizes the best (primary) alignment of a query, the first matching category wins.
+// A query is chimeric or has an inversion, if it has supplementary alignments.
+pub fn categorize_best_alignment(
+    query_alignments: &[AlignmentSummary],
+    error_rate: f64,
+) -> Option<(&AlignmentSummary, AlignmentCategory)> {
+    let best = query_alignments
+        .iter()
+        .find(|alignment| alignment.kind == AlignmentKind::Primary)
+        .or_else(|| query_alignments.first())?;
+
+    let category = if query_alignments
+        .iter()
+        .any(|alignment| alignment.kind == AlignmentKind::Supplementary)
+    {
+        AlignmentCategory::ChimericOrInversion
+    } else if best.clipped_bases() as f64 > best.query_length as f64 * SIGNIFICANT_CLIPPING_FRACTION
+    {
+        AlignmentCategory::SignificantlyClipped
+    } else if best.edit_distance.is_some_and(|edit_distance| {
+        edit_distance as f64 > best.aligned_query_length() as f64 * error_rate
+    }) {
+        AlignmentCategory::HighEditDistance
+    } else {
+        AlignmentCategory::Basic
+    };
+
+    Some((best, category))
+}
+
+#[derive(Debug, Serialize)]
+pub struct MapperComparison {
+    pub mappers: Vec<String>,
+    pub mapped_reads_paths: Vec<PathBuf>,
+    pub number_of_queries: i64,
+    // in the order of the mappers
+    pub stats_if_mapped: Vec<ScopedStats>,
+    // UpSet-style: disjoint sets of the queries that were mapped by exactly the given
+    // mappers (the empty set contains the queries no mapper mapped), largest first
+    pub intersections: Vec<MapperIntersection>,
+    // for every pair of mappers, whether the differences per read are significant
+    pub paired_tests: Vec<PairedReadTests>,
+    // names of the queries that only one mapper mapped, in the order of the mappers
+    #[serde(skip)]
+    pub exclusively_mapped_queries: Vec<Vec<String>>,
+    // the alignments of every mapper, in the order of the mappers, shared with the run results so
+    // that the comparison analyses don't have to read the mapper outputs again
+    #[serde(skip)]
+    pub alignments_per_mapper: Vec<Rc<AlignmentsPerQuery>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PairedReadTests {
+    pub first_mapper: String,
+    pub second_mapper: String,
+    // mapped or unmapped per query
+    pub mapped: McNemarTest,
+    // of the best alignments of the queries that both mappers mapped
+    pub edit_distance: WilcoxonTest,
+}
+
+impl MapperComparison {
+    pub fn intersection(&self, mappers: &[&str]) -> Option<&MapperIntersection> {
+        self.intersections
+            .iter()
+            .find(|intersection| intersection.mappers == mappers)
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct MapperIntersection {
+    pub mappers: Vec<String>,
+    pub num_queries: i64,
+    // stats of the alignments of the queries of this set, in the order of the mappers of this set
+    pub stats: Vec<ScopedStats>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DetailedMappedReadsComparison {
+    pub general_stats: FullStats,
+    pub floxer_stats_if_floxer_mapped: ScopedStats,
+    pub minimap_stats_if_minimap_mapped: ScopedStats,
+    pub minimap_stats_if_both_mapped: ScopedStats,
+    pub minimap_stats_if_only_minimap_mapped: ScopedStats,
+}
+
+impl DetailedMappedReadsComparison {
+    fn from_two_way(comparison: &MapperComparison) -> Result<Self> {
+        let [floxer, minimap] = comparison.mappers.as_slice() else {
+            bail!("the detailed comparison needs exactly two mappers");
+        };
+
+        let intersection_size = |mappers: &[&str]| {
+            comparison
+                .intersection(mappers)
+                .map_or(0, |intersection| intersection.num_queries)
+        };
+        let minimap_stats_in = |mappers: &[&str]| {
+            comparison
+                .intersection(mappers)
+                .and_then(|intersection| {
+                    let minimap_index = mappers.iter().position(|mapper| mapper == minimap)?;
+                    intersection.stats.get(minimap_index).cloned()
+                })
+                .unwrap_or_default()
+        };
+
+        let number_of_queries = comparison.number_of_queries;
+        let floxer_mapped = comparison.stats_if_mapped[0].num_queries;
+        let minimap_mapped = comparison.stats_if_mapped[1].num_queries;
+
+        Ok(Self {
+            general_stats: FullStats {
+                number_of_queries,
+                both_mapped: intersection_size(&[floxer, minimap]),
+                both_unmapped: intersection_size(&[]),
+                floxer_mapped,
+                floxer_unmapped: number_of_queries - floxer_mapped,
+                minimap_mapped,
+                minimap_unmapped: number_of_queries - minimap_mapped,
+                floxer_unmapped_and_minimap_mapped: intersection_size(&[minimap]),
+                minimap_unmapped_and_floxer_mapped: intersection_size(&[floxer]),
+            },
+            floxer_stats_if_floxer_mapped: comparison.stats_if_mapped[0].clone(),
+            minimap_stats_if_minimap_mapped: comparison.stats_if_mapped[1].clone(),
+            minimap_stats_if_both_mapped: minimap_stats_in(&[floxer, minimap]),
+            minimap_stats_if_only_minimap_mapped: minimap_stats_in(&[minimap]),
+        })
+    }
+}
+
+// all i64 to allow simple use of the charming API
+#[derive(Debug, Serialize, Deserialize)]
+#[allow(unused)]
+pub struct FullStats {
+    pub number_of_queries: i64,
+    pub both_mapped: i64,
+    pub both_unmapped: i64,
+    pub floxer_mapped: i64,
+    pub floxer_unmapped: i64,
+    pub minimap_mapped: i64,
+    pub minimap_unmapped: i64,
+    pub floxer_unmapped_and_minimap_mapped: i64,
+    pub minimap_unmapped_and_floxer_mapped: i64,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct ScopedStats {
+    pub num_queries: i64,
+    pub num_best_chimeric_or_inversion: i64,
+    pub num_best_significantly_clipped: i64,
+    pub num_best_high_edit_distance: i64,
+    pub num_basic: i64,
+    pub multiple_mapping: i64,
+    pub basic_average_longest_indel: f64,
+    pub basic_alignments_average_error_rate: f64,
+}
+
+#[derive(Default)]
+struct ScopedStatsBuilder {
+    stats: ScopedStats,
+    basic_longest_indel_sum: usize,
+    basic_error_rate_sum: f64,
+}
+
+impl ScopedStatsBuilder {
+    fn add(&mut self, outcome: &QueryOutcome) {
+        self.stats.num_queries += 1;
+
+        if outcome.multiple_mapping {
+            self.stats.multiple_mapping += 1;
+        }
+
+        match outcome.category {
+            AlignmentCategory::ChimericOrInversion => {
+                self.stats.num_best_chimeric_or_inversion += 1
+            }
+            AlignmentCategory::SignificantlyClipped => {
+                self.stats.num_best_significantly_clipped += 1
+            }
+            AlignmentCategory::HighEditDistance => self.stats.num_best_high_edit_distance += 1,
+            AlignmentCategory::Basic => {
+                self.stats.num_basic += 1;
+                self.basic_longest_indel_sum += outcome.longest_indel;
+                self.basic_error_rate_sum += outcome.error_rate;
+            }
+        }
+    }
+
+    fn build(mut self) -> ScopedStats {
+        if self.stats.num_basic > 0 {
+            let num_basic = self.stats.num_basic as f64;
+            self.stats.basic_average_longest_indel =
+                self.basic_longest_indel_sum as f64 / num_basic;
+            self.stats.basic_alignments_average_error_rate = self.basic_error_rate_sum / num_basic;
+        }
+
+        self.stats
+    }
+}
+
+// ----- for simulate dataset -----
+
+const ERROR_RATE_BUCKET_WIDTH: f64 = 0.02;
+const NUM_ERROR_RATE_BUCKETS: usize = 10;
+
+// where the true origin of the reads of a simulated dataset is known from
+pub enum SimulatedTruth<'a> {
+    // read names or sidecar file of the native simulator, restricted to the reads of the
+    // queries (which may be derived from the simulated queries)
+    Native {
+        dataset: &'a SimulatedDataset,
+        queries_path: &'a Path,
+    },
+    // only known to the simulated_dataset_binary
+    External,
+}
+
+pub fn verify_simulated_dataset(
+    mapped_reads_path: &Path,
+    truth: SimulatedTruth,
+    position_tolerance: u64,
+    suite_config: &BenchmarkSuiteConfig,
+) -> Result<SimulatedDatasetVerificationSummary> {
+    match truth {
+        SimulatedTruth::Native {
+            dataset,
+            queries_path,
+        } => {
+            let mut truth = simulator::load_truth(dataset)?;
+
+            if queries_path != dataset.queries_path {
+                let read_ids: HashSet<String> =
+                    datasets::fastx::FastxReader::from_path(queries_path)?
+                        .map(|record| Ok(record?.id().to_owned()))
+                        .collect::<Result<_>>()?;
+                truth.retain(|read_id, _| read_ids.contains(read_id));
+            }
+
+            verify_with_truth(mapped_reads_path, &truth, position_tolerance)
+        }
+        SimulatedTruth::External => {
+            verify_with_external_binary(mapped_reads_path, position_tolerance, suite_config)
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct ReadAlignmentHits {
+    mapped: bool,
+    primary_mapq: Option<u8>,
+    primary_hit: bool,
+    secondary_hits: usize,
+}
+
+// an alignment hits the truth, if it is on the right contig and strand and its start
+// position differs by at most the tolerance from the true start
+fn verify_with_truth(
+    mapped_reads_path: &Path,
+    truth: &HashMap<String, ReadTruth>,
+    position_tolerance: u64,
+) -> Result<SimulatedDatasetVerificationSummary> {
+    let mut bam = bam::Reader::from_path(mapped_reads_path)?;
+    let contig_names: Vec<_> = bam
+        .header()
+        .target_names()
+        .into_iter()
+        .map(|name| String::from_utf8_lossy(name).into_owned())
+        .collect();
+
+    let mut hits_per_read: HashMap<&str, ReadAlignmentHits> = HashMap::new();
+
+    for record in bam.records() {
+        let record = record?;
+        let read_name = str::from_utf8(record.qname())?;
+
+        let Some((read_id, read_truth)) = truth.get_key_value(read_name) else {
+            bail!("read {read_name} is not part of the simulated dataset");
+        };
+
+        let hits = hits_per_read.entry(read_id).or_default();
+
+        if record.is_unmapped() {
+            continue;
+        }
+
+        hits.mapped = true;
+
+        if !record.is_secondary() && !record.is_supplementary() {
+            hits.primary_mapq = Some(record.mapq());
+        }
+
+        let hits_truth = contig_names.get(record.tid() as usize) == Some(&read_truth.contig)
+            && record.is_reverse() == read_truth.reverse_strand
+            && record.pos().abs_diff(read_truth.start as i64) <= position_tolerance;
+
+        if !hits_truth {
+            continue;
+        }
+
+        if record.is_secondary() || record.is_supplementary() {
+            hits.secondary_hits += 1;
+        } else {
+            hits.primary_hit = true;
+        }
+    }
+
+    let mut summary = SimulatedDatasetVerificationSummary::new(position_tolerance, true);
+    let mut primary_alignments = Vec::new();
+
+    for (read_id, read_truth) in truth {
+        let hits = hits_per_read
+            .get(read_id.as_str())
+            .copied()
+            .unwrap_or_default();
+
+        let status = if hits.primary_hit {
+            MappingStatus::FoundByPrimary
+        } else if hits.secondary_hits > 0 {
+            MappingStatus::FoundOnlyBySecondary
+        } else if hits.mapped {
+            MappingStatus::MappedElsewhere
+        } else {
+            MappingStatus::Unmapped
+        };
+
+        summary.add_read(read_id, Some(read_truth), status, hits.secondary_hits);
+
+        if let Some(primary_mapq) = hits.primary_mapq {
+            primary_alignments.push((primary_mapq, hits.primary_hit));
+        }
+    }
+
+    summary.mapq_calibration = Some(MapqCalibration::new(truth.len(), primary_alignments));
+
+    Ok(summary)
+}
+
+fn verify_with_external_binary(
+    mapped_reads_path: &Path,
+    position_tolerance: u64,
+    suite_config: &BenchmarkSuiteConfig,
+) -> Result<SimulatedDatasetVerificationSummary> {
+    let output = Command::new(&suite_config.simulated_dataset_binary)
+        .arg("verify")
+        .arg("--alignments")
+        .arg(mapped_reads_path)
+        .arg("--allowed-pos-diff")
+        .arg(position_tolerance.to_string())
+        .output()?;
+
+    if !output.status.success() {
+        bail!(
+            "Failed to verify mapped reads of simulated data set with stderr: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    let str_data = String::from_utf8(output.stdout)?;
+    let data: VerifiedSimulatedDataset = toml::from_str(&str_data)?;
+
+    // the external binary doesn't report strands, error rates and secondary alignments
+    let mut summary = SimulatedDatasetVerificationSummary::new(position_tolerance, false);
+    let primary_mapqs = read_primary_mapqs(mapped_reads_path)?;
+    let mut primary_alignments = Vec::new();
+    let num_reads = data.queries.len();
+
+    for query in data.queries {
+        let status = match query.status {
+            ExternalMappingStatus::FoundOptimal => MappingStatus::FoundByPrimary,
+            ExternalMappingStatus::FoundSuboptimal { .. } => MappingStatus::FoundSuboptimal,
+            ExternalMappingStatus::NotFound => MappingStatus::Unmapped,
+        };
+
+        summary.add_read(&query.id, None, status, 0);
+
+        if let Some(&primary_mapq) = primary_mapqs.get(&query.id) {
+            primary_alignments.push((primary_mapq, status == MappingStatus::FoundByPrimary));
+        }
+    }
+
+    summary.mapq_calibration = Some(MapqCalibration::new(num_reads, primary_alignments));
+
+    Ok(summary)
+}
+
+fn read_primary_mapqs(mapped_reads_path: &Path) -> Result<HashMap<String, u8>> {
+    let mut bam = bam::Reader::from_path(mapped_reads_path)?;
+    let mut primary_mapqs = HashMap::new();
+
+    for record in bam.records() {
+        let record = record?;
+
+        if record.is_unmapped() || record.is_secondary() || record.is_supplementary() {
+            continue;
+        }
+
+        primary_mapqs.insert(str::from_utf8(record.qname())?.to_owned(), record.mapq());
+    }
+
+    Ok(primary_mapqs)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display)]
+#[strum(serialize_all = "snake_case")]
+pub enum MappingStatus {
+    FoundByPrimary,
+    FoundOnlyBySecondary,
+    // only reported by the external verification, the read was found with more errors than expected
+    FoundSuboptimal,
+    MappedElsewhere,
+    Unmapped,
+}
+
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
+pub struct VerificationCounts {
+    pub num_reads: usize,
+    pub found_by_primary: usize,
+    pub found_only_by_secondary: usize,
+    // older results don't distinguish them from the reads that were mapped elsewhere
+    #[serde(default)]
+    pub found_suboptimal: usize,
+    pub mapped_elsewhere: usize,
+    pub unmapped: usize,
+    pub secondary_alignments_hitting_truth: usize,
+}
+
+impl VerificationCounts {
+    fn add(&mut self, status: MappingStatus, secondary_hits: usize) {
+        self.num_reads += 1;
+        self.secondary_alignments_hitting_truth += secondary_hits;
+
+        match status {
+            MappingStatus::FoundByPrimary => self.found_by_primary += 1,
+            MappingStatus::FoundOnlyBySecondary => self.found_only_by_secondary += 1,
+            MappingStatus::FoundSuboptimal => self.found_suboptimal += 1,
+            MappingStatus::MappedElsewhere => self.mapped_elsewhere += 1,
+            MappingStatus::Unmapped => self.unmapped += 1,
+        }
+    }
+}
+
+// writes the verification counts of all instances of a benchmark on a simulated dataset to the benchmark folder
+pub fn analyze_simulated_verification_counts<'a>(
+    counts_and_names_of_runs: impl IntoIterator<Item = (&'a VerificationCounts, &'a str)>,
+    benchmark_folder: &BenchmarkFolder,
+) -> Result<SimulatedVerificationResults> {
+    let results = SimulatedVerificationResults {
+        instances: counts_and_names_of_runs
+            .into_iter()
+            .map(|(counts, name)| InstanceVerificationCounts {
+                instance_name: name.to_owned(),
+                counts: *counts,
+            })
+            .collect(),
+    };
+
+    let mut result_file_path = benchmark_folder.get().to_owned();
+    result_file_path.push("simulated_verification_results.toml");
+
+    fs::write(result_file_path, toml::to_string(&results)?)?;
+
+    Ok(results)
+}
+
+#[derive(Debug, Serialize)]
+pub struct SimulatedVerificationResults {
+    pub instances: Vec<InstanceVerificationCounts>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct InstanceVerificationCounts {
+    pub instance_name: String,
+    #[serde(flatten)]
+    pub counts: VerificationCounts,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ErrorRateBucket {
+    pub min_error_rate: f64,
+    // the last bucket has no upper bound
+    pub max_error_rate: Option<f64>,
+    #[serde(flatten)]
+    pub counts: VerificationCounts,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SimulatedDatasetVerificationSummary {
+    pub position_tolerance: u64,
+    pub all_reads: VerificationCounts,
+    // only available if the truth of the reads is known to this program
+    pub forward_strand: Option<VerificationCounts>,
+    pub reverse_strand: Option<VerificationCounts>,
+    pub error_rate_buckets: Vec<ErrorRateBucket>,
+    pub mapq_calibration: Option<MapqCalibration>,
+    #[serde(skip)]
+    missed_reads: Vec<(String, MappingStatus)>,
+}
+
+impl SimulatedDatasetVerificationSummary {
+    fn new(position_tolerance: u64, with_truth_details: bool) -> Self {
+        let error_rate_buckets = if with_truth_details {
+            (0..NUM_ERROR_RATE_BUCKETS)
+                .map(|index| ErrorRateBucket {
+                    min_error_rate: index as f64 * ERROR_RATE_BUCKET_WIDTH,
+                    max_error_rate: (index + 1 < NUM_ERROR_RATE_BUCKETS)
+                        .then(|| (index + 1) as f64 * ERROR_RATE_BUCKET_WIDTH),
+                    counts: Default::default(),
+                })
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        Self {
+            position_tolerance,
+            all_reads: Default::default(),
+            forward_strand: with_truth_details.then(Default::default),
+            reverse_strand: with_truth_details.then(Default::default),
+            error_rate_buckets,
+            mapq_calibration: None,
+            missed_reads: Vec::new(),
+        }
+    }
+
+    fn add_read(
+        &mut self,
+        read_id: &str,
+        truth: Option<&ReadTruth>,
+        status: MappingStatus,
+        secondary_hits: usize,
+    ) {
+        self.all_reads.add(status, secondary_hits);
+
+        if status != MappingStatus::FoundByPrimary {
+            self.missed_reads.push((read_id.to_owned(), status));
+        }
+
+        let Some(truth) = truth else {
+            return;
+        };
+
+        let strand_counts = if truth.reverse_strand {
+            &mut self.reverse_strand
+        } else {
+            &mut self.forward_strand
+        };
+
+        if let Some(strand_counts) = strand_counts {
+            strand_counts.add(status, secondary_hits);
+        }
+
+        let bucket_index = ((truth.error_rate() / ERROR_RATE_BUCKET_WIDTH) as usize)
+            .min(NUM_ERROR_RATE_BUCKETS - 1);
+        if let Some(bucket) = self.error_rate_buckets.get_mut(bucket_index) {
+            bucket.counts.add(status, secondary_hits);
+        }
+    }
+
+    pub fn print(&self) {
+        for (read_id, status) in &self.missed_reads {
+            println!("Query {read_id}: {status}");
+        }
+
+        println!(
+            "- Simulated reads (position tolerance {}): {}",
+            self.position_tolerance,
+            self.all_reads.summary_line()
+        );
+
+        if let (Some(forward_strand), Some(reverse_strand)) =
+            (&self.forward_strand, &self.reverse_strand)
+        {
+            println!("  - forward strand: {}", forward_strand.summary_line());
+            println!("  - reverse strand: {}", reverse_strand.summary_line());
+        }
+
+        for bucket in &self.error_rate_buckets {
+            if bucket.counts.num_reads == 0 {
+                continue;
+            }
+
+            let upper = bucket
+                .max_error_rate
+                .map_or(String::from("inf"), |max| format!("{max:.2}"));
+            println!(
+                "  - error rate [{:.2}, {upper}): {}",
+                bucket.min_error_rate,
+                bucket.counts.summary_line()
+            );
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        fs::write(path, toml::to_string(self)?)?;
+
+        Ok(())
+    }
+}
+
+impl VerificationCounts {
+    fn summary_line(&self) -> String {
+        format!(
+            "{} reads, {} found by primary, {} only by secondary, {} suboptimal, \
+            {} mapped elsewhere, {} unmapped, {} secondary alignments hit the truth",
+            self.num_reads,
+            self.found_by_primary,
+            self.found_only_by_secondary,
+            self.found_suboptimal,
+            self.mapped_elsewhere,
+            self.unmapped,
+            self.secondary_alignments_hitting_truth
+        )
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct VerifiedSimulatedDataset {
+    queries: Vec<VerifiedSimulatedQuery>,
+}
+
+#[derive(Debug, Deserialize)]
+struct VerifiedSimulatedQuery {
+    id: String,
+    status: ExternalMappingStatus,
+}
+
+#[derive(Debug, Deserialize)]
+enum ExternalMappingStatus {
+    NotFound,
+    FoundOptimal,
+    #[allow(unused)]
+    FoundSuboptimal {
+        pos_diff_expected_num_errors: u64,
+        pos_diff_higher_num_errors: u64,
+    },
+}
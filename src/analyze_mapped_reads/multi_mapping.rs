@@ -0,0 +1,253 @@
+use std::{collections::HashSet, fs};
+
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::{
+    alignment_files::{AlignmentKind, AlignmentSummary, AlignmentsPerQuery},
+    folder_structure::BenchmarkFolder,
+    readmappers::floxer::HistogramData,
+};
+
+use super::MapperComparison;
+
+const ALIGNMENTS_PER_READ_THRESHOLDS: [usize; 10] = [1, 2, 3, 4, 5, 10, 20, 50, 100, 1000];
+const EDIT_DISTANCE_GAP_THRESHOLDS: [usize; 10] = [0, 1, 2, 5, 10, 20, 50, 100, 200, 500];
+
+// secondary alignments on the contig of the primary alignment that start at most this far from it
+// are counted as a tandem cluster, all others as interspersed
+const TANDEM_CLUSTER_DISTANCE: usize = 10_000;
+
+// floxer reports all alignments within the error bound, while minimap reports only a few secondary
+// alignments, so the multi-mapping behavior is compared for every pair of mappers
+pub fn compare_multi_mapping(comparison: &MapperComparison) -> MultiMappingComparison {
+    let alignments_per_mapper = &comparison.alignments_per_mapper;
+
+    let stats_per_mapper = alignments_per_mapper
+        .iter()
+        .map(|alignments_per_query| multi_mapping_stats(alignments_per_query))
+        .collect();
+
+    let mut overlaps = Vec::new();
+    for first in 0..alignments_per_mapper.len() {
+        for second in first + 1..alignments_per_mapper.len() {
+            overlaps.push(alternative_loci_overlap(
+                (&comparison.mappers[first], &alignments_per_mapper[first]),
+                (&comparison.mappers[second], &alignments_per_mapper[second]),
+            ));
+        }
+    }
+
+    MultiMappingComparison {
+        mappers: comparison.mappers.clone(),
+        stats_per_mapper,
+        overlaps,
+    }
+}
+
+// writes the multi-mapping comparison to the benchmark folder
+pub fn analyze_multi_mapping_comparison(
+    comparison: &MapperComparison,
+    benchmark_folder: &BenchmarkFolder,
+) -> Result<MultiMappingComparison> {
+    let multi_mapping_comparison = compare_multi_mapping(comparison);
+
+    let mut result_file_path = benchmark_folder.get().to_owned();
+    result_file_path.push("multi_mapping_comparison.toml");
+
+    fs::write(
+        result_file_path,
+        toml::to_string(&multi_mapping_comparison)?,
+    )?;
+
+    Ok(multi_mapping_comparison)
+}
+
+// supplementary alignments are parts of a chimeric alignment and not alternative loci of the read
+fn multi_mapping_stats(alignments_per_query: &AlignmentsPerQuery) -> MultiMappingStats {
+    let mut num_mapped = 0;
+    let mut num_multi_mapped = 0;
+    let mut alignments_per_read = Vec::new();
+    let mut edit_distance_gaps = Vec::new();
+    let mut loci = LocusDistribution::default();
+
+    for alignments in alignments_per_query.values() {
+        let linear_alignments: Vec<_> = alignments
+            .iter()
+            .filter(|alignment| alignment.kind != AlignmentKind::Supplementary)
+            .collect();
+
+        let Some(primary) = linear_alignments
+            .iter()
+            .find(|alignment| alignment.kind == AlignmentKind::Primary)
+        else {
+            continue;
+        };
+
+        num_mapped += 1;
+        alignments_per_read.push(linear_alignments.len());
+
+        if linear_alignments.len() < 2 {
+            continue;
+        }
+
+        num_multi_mapped += 1;
+
+        let mut edit_distances: Vec<_> = linear_alignments
+            .iter()
+            .filter_map(|alignment| alignment.edit_distance)
+            .collect();
+        edit_distances.sort_unstable();
+        if let [best, second_best, ..] = edit_distances[..] {
+            edit_distance_gaps.push(second_best - best);
+        }
+
+        let num_secondary = linear_alignments.len() as i64 - 1;
+        let num_clustered = linear_alignments
+            .iter()
+            .filter(|alignment| {
+                alignment.kind == AlignmentKind::Secondary
+                    && alignment.reference_name == primary.reference_name
+                    && alignment.reference_start.abs_diff(primary.reference_start)
+                        <= TANDEM_CLUSTER_DISTANCE
+            })
+            .count() as i64;
+
+        loci.num_clustered_loci += num_clustered;
+        loci.num_interspersed_loci += num_secondary - num_clustered;
+
+        if num_clustered == num_secondary {
+            loci.num_clustered_reads += 1;
+        } else if num_clustered == 0 {
+            loci.num_interspersed_reads += 1;
+        } else {
+            loci.num_mixed_reads += 1;
+        }
+    }
+
+    MultiMappingStats {
+        num_mapped,
+        num_multi_mapped,
+        alignments_per_read: HistogramData::from_values(
+            alignments_per_read,
+            ALIGNMENTS_PER_READ_THRESHOLDS.to_vec(),
+        ),
+        edit_distance_gap: HistogramData::from_values(
+            edit_distance_gaps,
+            EDIT_DISTANCE_GAP_THRESHOLDS.to_vec(),
+        ),
+        loci,
+    }
+}
+
+// secondary and supplementary alignments
+fn alternative_alignments<'a>(
+    alignments_per_query: &'a AlignmentsPerQuery,
+    query_name: &str,
+) -> impl Iterator<Item = &'a AlignmentSummary> {
+    alignments_per_query
+        .get(query_name)
+        .into_iter()
+        .flatten()
+        .filter(|alignment| alignment.kind != AlignmentKind::Primary)
+}
+
+fn overlaps(a: &AlignmentSummary, b: &AlignmentSummary) -> bool {
+    a.reference_name == b.reference_name
+        && a.reverse_strand == b.reverse_strand
+        && a.reference_start < b.reference_start + b.reference_span.max(1)
+        && b.reference_start < a.reference_start + a.reference_span.max(1)
+}
+
+// an alternative locus of one mapper is found by the other mapper if one of the other mapper's
+// alternative alignments of the same read overlaps it on the same strand
+fn alternative_loci_overlap(
+    (first_mapper, first): (&str, &AlignmentsPerQuery),
+    (second_mapper, second): (&str, &AlignmentsPerQuery),
+) -> AlternativeLociOverlap {
+    let mut overlap = AlternativeLociOverlap {
+        first_mapper: first_mapper.to_owned(),
+        second_mapper: second_mapper.to_owned(),
+        ..Default::default()
+    };
+
+    let query_names: HashSet<_> = first.keys().chain(second.keys()).collect();
+
+    for query_name in query_names {
+        let first_alternatives: Vec<_> = alternative_alignments(first, query_name).collect();
+        let second_alternatives: Vec<_> = alternative_alignments(second, query_name).collect();
+
+        match (
+            first_alternatives.is_empty(),
+            second_alternatives.is_empty(),
+        ) {
+            (false, false) => overlap.num_reads_with_alternatives_of_both += 1,
+            (false, true) => overlap.num_reads_with_alternatives_only_of_first += 1,
+            (true, false) => overlap.num_reads_with_alternatives_only_of_second += 1,
+            (true, true) => {}
+        }
+
+        for alignment in &first_alternatives {
+            overlap.num_alternative_loci_of_first += 1;
+            overlap.num_alternative_loci_of_first_found_by_second += second_alternatives
+                .iter()
+                .any(|other| overlaps(alignment, other))
+                as i64;
+        }
+
+        for alignment in &second_alternatives {
+            overlap.num_alternative_loci_of_second += 1;
+            overlap.num_alternative_loci_of_second_found_by_first += first_alternatives
+                .iter()
+                .any(|other| overlaps(alignment, other))
+                as i64;
+        }
+    }
+
+    overlap
+}
+
+#[derive(Debug, Serialize)]
+pub struct MultiMappingComparison {
+    pub mappers: Vec<String>,
+    // in the order of the mappers
+    pub stats_per_mapper: Vec<MultiMappingStats>,
+    // for every pair of mappers
+    pub overlaps: Vec<AlternativeLociOverlap>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MultiMappingStats {
+    pub num_mapped: i64,
+    // with at least one secondary alignment
+    pub num_multi_mapped: i64,
+    // primary and secondary alignments of the mapped reads
+    pub alignments_per_read: HistogramData,
+    // edit distance of the second best minus the best alignment of the multi-mapped reads
+    pub edit_distance_gap: HistogramData,
+    pub loci: LocusDistribution,
+}
+
+// reads are clustered if all of their secondary alignments are near the primary alignment (tandem
+// repeats), interspersed if none of them are and mixed otherwise
+#[derive(Debug, Default, Serialize)]
+pub struct LocusDistribution {
+    pub num_clustered_reads: i64,
+    pub num_interspersed_reads: i64,
+    pub num_mixed_reads: i64,
+    pub num_clustered_loci: i64,
+    pub num_interspersed_loci: i64,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct AlternativeLociOverlap {
+    pub first_mapper: String,
+    pub second_mapper: String,
+    pub num_reads_with_alternatives_of_both: i64,
+    pub num_reads_with_alternatives_only_of_first: i64,
+    pub num_reads_with_alternatives_only_of_second: i64,
+    pub num_alternative_loci_of_first: i64,
+    pub num_alternative_loci_of_first_found_by_second: i64,
+    pub num_alternative_loci_of_second: i64,
+    pub num_alternative_loci_of_second_found_by_first: i64,
+}
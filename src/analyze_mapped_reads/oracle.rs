@@ -0,0 +1,341 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::{Path, PathBuf},
+    thread,
+};
+
+use anyhow::{Context, Result};
+use rust_htslib::bam::{self, Read};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    alignment_files,
+    config::BenchmarkSuiteConfig,
+    datasets::{fastx::FastxReader, simulator},
+    edit_distance,
+    readmappers::{floxer::QueryErrors, Queries, Reference},
+};
+
+// all misses and false positives are counted, but only this many are listed
+const MAX_LISTED_EXAMPLES: usize = 100;
+
+// all locations where a query can be aligned with at most the allowed number of errors,
+// found by searching every query in the whole reference (both strands)
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct OracleLocations {
+    pub locations_per_query: BTreeMap<String, Vec<OracleLocation>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OracleLocation {
+    pub reference_name: String,
+    pub reverse_strand: bool,
+    // 0-based, end exclusive
+    pub start: usize,
+    pub end: usize,
+    pub edit_distance: usize,
+}
+
+// the search is quadratic, so it only runs for the small references and is skipped otherwise. The
+// result is stored in the oracle folder and reused by later runs with the same datasets and errors.
+pub fn exhaustive_oracle(
+    reference: &Reference,
+    queries: &Queries,
+    query_errors: QueryErrors,
+    suite_config: &BenchmarkSuiteConfig,
+) -> Result<Option<OracleLocations>> {
+    if matches!(
+        reference,
+        Reference::HumanGenomeHg38 | Reference::MaskedHumanGenomeHg38
+    ) {
+        println!(
+            "- Warning: skipped the exhaustive oracle, it is only available for the debug and simulated references"
+        );
+        return Ok(None);
+    }
+
+    let oracle_path = oracle_path(reference, queries, query_errors, suite_config);
+
+    if oracle_path.exists() {
+        let oracle_str = fs::read_to_string(&oracle_path)?;
+        return toml::from_str(&oracle_str)
+            .map(Some)
+            .with_context(|| format!("parsing oracle {}", oracle_path.display()));
+    }
+
+    println!(
+        "- Searching all queries {queries} exhaustively in the reference {reference} (this may take a while)"
+    );
+
+    let contigs: Vec<_> = FastxReader::from_path(reference.path(suite_config)?)?
+        .map(|record| {
+            let record = record?;
+            Ok((record.id().to_owned(), record.seq))
+        })
+        .collect::<Result<_>>()?;
+    let query_records: Vec<_> =
+        FastxReader::from_path(queries.path(suite_config)?)?.collect::<Result<_>>()?;
+
+    let num_threads = thread::available_parallelism().map_or(1, |num| num.get());
+
+    let locations_per_query = thread::scope(|scope| {
+        let handles: Vec<_> = query_records
+            .chunks(query_records.len().div_ceil(num_threads).max(1))
+            .map(|records| {
+                scope.spawn(|| {
+                    records
+                        .iter()
+                        .map(|record| {
+                            let max_errors = query_errors.max_errors(record.seq.len());
+                            (
+                                record.id().to_owned(),
+                                search_query(&record.seq, &contigs, max_errors),
+                            )
+                        })
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().expect("oracle thread panicked"))
+            .collect()
+    });
+
+    let oracle = OracleLocations {
+        locations_per_query,
+    };
+    fs::write(&oracle_path, toml::to_string(&oracle)?)?;
+
+    Ok(Some(oracle))
+}
+
+pub fn oracle_path(
+    reference: &Reference,
+    queries: &Queries,
+    query_errors: QueryErrors,
+    suite_config: &BenchmarkSuiteConfig,
+) -> PathBuf {
+    let errors = match query_errors {
+        QueryErrors::Exact(num_errors) => format!("{num_errors}_errors"),
+        QueryErrors::Rate(error_rate) => format!("error_rate_{error_rate}"),
+    };
+
+    let mut oracle_path = suite_config.oracle_folder();
+    oracle_path.push(format!("oracle-{reference}-{queries}-{errors}.toml"));
+    oracle_path
+}
+
+fn search_query(
+    query: &[u8],
+    contigs: &[(String, Vec<u8>)],
+    max_errors: usize,
+) -> Vec<OracleLocation> {
+    let forward_query = query.to_ascii_uppercase();
+    let reverse_query = simulator::reverse_complement(&forward_query);
+
+    let mut locations = Vec::new();
+
+    for (reference_name, contig) in contigs {
+        for (reverse_strand, query) in [(false, &forward_query), (true, &reverse_query)] {
+            locations.extend(
+                edit_distance::semi_global_occurrences(query, contig, max_errors)
+                    .into_iter()
+                    .map(|occurrence| OracleLocation {
+                        reference_name: reference_name.clone(),
+                        reverse_strand,
+                        start: occurrence.start,
+                        end: occurrence.end,
+                        edit_distance: occurrence.edit_distance,
+                    }),
+            );
+        }
+    }
+
+    locations
+}
+
+// an alignment is correct if it overlaps an oracle location of its query on the same contig and strand,
+// an oracle location is found if at least one primary or secondary alignment overlaps it
+pub fn compare_with_oracle(
+    mapped_reads_path: &Path,
+    oracle: &OracleLocations,
+) -> Result<OracleComparison> {
+    let mut bam = bam::Reader::from_path(mapped_reads_path)
+        .with_context(|| format!("opening mapped reads {}", mapped_reads_path.display()))?;
+    let reference_names = alignment_files::reference_names(bam.header());
+
+    let mut comparison = OracleComparison {
+        num_queries: oracle.locations_per_query.len(),
+        num_oracle_locations: oracle.locations_per_query.values().map(Vec::len).sum(),
+        ..Default::default()
+    };
+
+    let mut found: HashMap<&str, Vec<bool>> = oracle
+        .locations_per_query
+        .iter()
+        .map(|(query_name, locations)| (query_name.as_str(), vec![false; locations.len()]))
+        .collect();
+
+    for record in bam.records() {
+        let record = record?;
+
+        if record.is_unmapped() || record.is_supplementary() {
+            continue;
+        }
+
+        let query_name = String::from_utf8_lossy(record.qname()).into_owned();
+        let reference_name = usize::try_from(record.tid())
+            .ok()
+            .and_then(|tid| reference_names.get(tid))
+            .context("mapped record without reference")?;
+
+        // without a CIGAR string, the alignment is assumed to span the length of the query
+        let start = record.pos() as usize;
+        let cigar = record.cigar();
+        let end = if cigar.is_empty() {
+            start + record.seq_len()
+        } else {
+            cigar.end_pos() as usize
+        };
+
+        let locations = oracle
+            .locations_per_query
+            .get(&query_name)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+
+        let mut is_correct = false;
+        for (location, location_found) in locations
+            .iter()
+            .zip(found.get_mut(query_name.as_str()).into_iter().flatten())
+        {
+            if location.reference_name == *reference_name
+                && location.reverse_strand == record.is_reverse()
+                && location.start < end
+                && start < location.end
+            {
+                is_correct = true;
+                *location_found = true;
+            }
+        }
+
+        let counts = if record.is_secondary() {
+            &mut comparison.secondary
+        } else {
+            &mut comparison.primary
+        };
+        counts.num_alignments += 1;
+
+        if is_correct {
+            counts.num_correct += 1;
+        } else if comparison.incorrect_alignments.len() < MAX_LISTED_EXAMPLES {
+            comparison.incorrect_alignments.push(IncorrectAlignment {
+                query_name,
+                reference_name: reference_name.clone(),
+                position: start as u64 + 1,
+                reverse_strand: record.is_reverse(),
+                secondary: record.is_secondary(),
+            });
+        }
+    }
+
+    for (query_name, locations) in &oracle.locations_per_query {
+        for (location, location_found) in locations.iter().zip(&found[query_name.as_str()]) {
+            if *location_found {
+                comparison.num_found_locations += 1;
+            } else if comparison.missed_locations.len() < MAX_LISTED_EXAMPLES {
+                comparison.missed_locations.push(MissedLocation {
+                    query_name: query_name.clone(),
+                    location: location.clone(),
+                });
+            }
+        }
+    }
+
+    comparison.recall = ratio(
+        comparison.num_found_locations,
+        comparison.num_oracle_locations,
+    );
+    comparison.primary.precision = ratio(
+        comparison.primary.num_correct,
+        comparison.primary.num_alignments,
+    );
+    comparison.secondary.precision = ratio(
+        comparison.secondary.num_correct,
+        comparison.secondary.num_alignments,
+    );
+    comparison.precision = ratio(
+        comparison.primary.num_correct + comparison.secondary.num_correct,
+        comparison.primary.num_alignments + comparison.secondary.num_alignments,
+    );
+
+    Ok(comparison)
+}
+
+// 1.0 if there is nothing to count
+fn ratio(numerator: usize, denominator: usize) -> f64 {
+    if denominator == 0 {
+        1.0
+    } else {
+        numerator as f64 / denominator as f64
+    }
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct OracleComparison {
+    pub num_queries: usize,
+    pub num_oracle_locations: usize,
+    pub num_found_locations: usize,
+    pub recall: f64,
+    pub precision: f64,
+    pub primary: OracleAlignmentCounts,
+    pub secondary: OracleAlignmentCounts,
+    pub missed_locations: Vec<MissedLocation>,
+    pub incorrect_alignments: Vec<IncorrectAlignment>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct OracleAlignmentCounts {
+    pub num_alignments: usize,
+    pub num_correct: usize,
+    pub precision: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MissedLocation {
+    pub query_name: String,
+    pub location: OracleLocation,
+}
+
+#[derive(Debug, Serialize)]
+pub struct IncorrectAlignment {
+    pub query_name: String,
+    pub reference_name: String,
+    // 1-based
+    pub position: u64,
+    pub reverse_strand: bool,
+    pub secondary: bool,
+}
+
+impl OracleComparison {
+    pub fn print(&self) {
+        println!(
+            "- Exhaustive oracle: found {} of {} locations (recall {:.4}), precision {:.4} (primary {:.4}, secondary {:.4})",
+            self.num_found_locations,
+            self.num_oracle_locations,
+            self.recall,
+            self.precision,
+            self.primary.precision,
+            self.secondary.precision
+        );
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        fs::write(path, toml::to_string(self)?)?;
+
+        Ok(())
+    }
+}
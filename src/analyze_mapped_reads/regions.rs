@@ -0,0 +1,366 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io::{BufRead, BufReader},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Context, Result};
+use rust_htslib::bgzf;
+use serde::Serialize;
+
+use crate::{
+    alignment_files::AlignmentKind, folder_structure::BenchmarkFolder,
+    readmappers::floxer::HistogramData,
+};
+
+use super::{MapperComparison, EDIT_DISTANCE_THRESHOLDS};
+
+// queries whose location does not overlap any of the annotations
+const UNANNOTATED_REGION_CLASS: &str = "unannotated";
+
+// one class per BED file, e.g. repeats, centromeres or segmental duplications
+pub struct RegionAnnotations {
+    classes: Vec<RegionClass>,
+}
+
+struct RegionClass {
+    label: String,
+    // sorted and merged, 0-based and end exclusive
+    intervals_per_contig: HashMap<String, Vec<(usize, usize)>>,
+}
+
+impl RegionAnnotations {
+    // plain or (b)gzip compressed BED files, only the first three columns are used
+    pub fn load(labelled_bed_paths: &[(String, PathBuf)]) -> Result<Self> {
+        let mut classes = Vec::new();
+
+        for (label, path) in labelled_bed_paths {
+            if label == UNANNOTATED_REGION_CLASS
+                || classes
+                    .iter()
+                    .any(|class: &RegionClass| &class.label == label)
+            {
+                bail!("region class label {label} is used more than once or reserved");
+            }
+
+            classes.push(RegionClass {
+                label: label.clone(),
+                intervals_per_contig: read_bed(path)?,
+            });
+        }
+
+        Ok(Self { classes })
+    }
+
+    // labels of all classes that overlap the interval (they may overlap each other)
+    fn classify(&self, contig: &str, start: usize, end: usize) -> Vec<&str> {
+        let labels: Vec<_> = self
+            .classes
+            .iter()
+            .filter(|class| {
+                class
+                    .intervals_per_contig
+                    .get(contig)
+                    .is_some_and(|intervals| {
+                        let index =
+                            intervals.partition_point(|&(_, interval_end)| interval_end <= start);
+                        intervals
+                            .get(index)
+                            .is_some_and(|&(interval_start, _)| interval_start < end.max(start + 1))
+                    })
+            })
+            .map(|class| class.label.as_str())
+            .collect();
+
+        if labels.is_empty() {
+            vec![UNANNOTATED_REGION_CLASS]
+        } else {
+            labels
+        }
+    }
+
+    fn labels(&self) -> impl Iterator<Item = &str> {
+        self.classes
+            .iter()
+            .map(|class| class.label.as_str())
+            .chain([UNANNOTATED_REGION_CLASS])
+    }
+}
+
+fn read_bed(path: &Path) -> Result<HashMap<String, Vec<(usize, usize)>>> {
+    let reader = BufReader::new(
+        bgzf::Reader::from_path(path)
+            .with_context(|| format!("opening BED file {}", path.display()))?,
+    );
+    let mut intervals_per_contig: HashMap<String, Vec<(usize, usize)>> = HashMap::new();
+
+    for (line_index, line) in reader.lines().enumerate() {
+        let line = line?;
+
+        if line.trim().is_empty()
+            || line.starts_with('#')
+            || line.starts_with("track")
+            || line.starts_with("browser")
+        {
+            continue;
+        }
+
+        let mut fields = line.split_whitespace();
+        let (Some(contig), Some(start), Some(end)) = (fields.next(), fields.next(), fields.next())
+        else {
+            bail!(
+                "BED line {} in {} has less than 3 columns",
+                line_index + 1,
+                path.display()
+            );
+        };
+
+        let parse = |value: &str| {
+            value.parse::<usize>().with_context(|| {
+                format!(
+                    "invalid coordinate in BED line {} in {}",
+                    line_index + 1,
+                    path.display()
+                )
+            })
+        };
+
+        intervals_per_contig
+            .entry(contig.to_owned())
+            .or_default()
+            .push((parse(start)?, parse(end)?));
+    }
+
+    for intervals in intervals_per_contig.values_mut() {
+        intervals.sort_unstable();
+
+        let mut merged: Vec<(usize, usize)> = Vec::with_capacity(intervals.len());
+        for &(start, end) in intervals.iter() {
+            match merged.last_mut() {
+                Some(last) if start <= last.1 => last.1 = last.1.max(end),
+                _ => merged.push((start, end)),
+            }
+        }
+
+        *intervals = merged;
+    }
+
+    Ok(intervals_per_contig)
+}
+
+// the primary alignment of a query, reduced to what the region stratification needs
+struct PrimaryAlignment {
+    reference_name: String,
+    reference_start: usize,
+    reference_end: usize,
+    reverse_strand: bool,
+    edit_distance: Option<usize>,
+    has_secondary_alignments: bool,
+}
+
+// stratifies the mapped reads of a comparison by the region classes. A query is located at the primary
+// alignment of the first mapper (in the order of the comparison) that mapped it, queries that no mapper
+// mapped are not located. Mappers disagree on a query if some of them did not map it or if their
+// primary alignments are on different contigs or strands or further apart than the position tolerance.
+pub fn stratify_by_regions(
+    comparison: &MapperComparison,
+    annotations: &RegionAnnotations,
+    position_tolerance: u64,
+) -> Result<RegionStratifiedComparison> {
+    let mut primary_alignments_per_mapper = Vec::new();
+
+    for alignments_per_query in &comparison.alignments_per_mapper {
+        let primary_alignments: HashMap<_, _> = alignments_per_query
+            .iter()
+            .filter_map(|(query_name, alignments)| {
+                let primary = alignments
+                    .iter()
+                    .find(|alignment| alignment.kind == AlignmentKind::Primary)?;
+
+                Some((
+                    query_name.as_str(),
+                    PrimaryAlignment {
+                        reference_name: primary.reference_name.clone(),
+                        reference_start: primary.reference_start,
+                        reference_end: primary.reference_start + primary.reference_span,
+                        reverse_strand: primary.reverse_strand,
+                        edit_distance: primary.edit_distance,
+                        has_secondary_alignments: alignments
+                            .iter()
+                            .any(|alignment| alignment.kind == AlignmentKind::Secondary),
+                    },
+                ))
+            })
+            .collect();
+
+        primary_alignments_per_mapper.push(primary_alignments);
+    }
+
+    let mut values_per_class: HashMap<&str, RegionClassValues> = annotations
+        .labels()
+        .map(|label| (label, RegionClassValues::new(comparison.mappers.len())))
+        .collect();
+
+    let mut located_queries: Vec<&str> = primary_alignments_per_mapper
+        .iter()
+        .flat_map(|primary_alignments| primary_alignments.keys().copied())
+        .collect();
+    located_queries.sort_unstable();
+    located_queries.dedup();
+
+    for query_name in &located_queries {
+        let alignments: Vec<_> = primary_alignments_per_mapper
+            .iter()
+            .map(|primary_alignments| primary_alignments.get(*query_name))
+            .collect();
+
+        let location = alignments
+            .iter()
+            .flatten()
+            .next()
+            .expect("located queries are mapped by at least one mapper");
+
+        let disagreement = alignments.iter().any(|alignment| match alignment {
+            None => true,
+            Some(alignment) => {
+                alignment.reference_name != location.reference_name
+                    || alignment.reverse_strand != location.reverse_strand
+                    || alignment.reference_start.abs_diff(location.reference_start) as u64
+                        > position_tolerance
+            }
+        });
+
+        for label in annotations.classify(
+            &location.reference_name,
+            location.reference_start,
+            location.reference_end,
+        ) {
+            let values = values_per_class
+                .get_mut(label)
+                .expect("all labels have values");
+
+            values.num_queries += 1;
+            values.num_disagreements += disagreement as i64;
+
+            for (mapper_values, alignment) in values.per_mapper.iter_mut().zip(&alignments) {
+                match alignment {
+                    None => mapper_values.num_unmapped += 1,
+                    Some(alignment) => {
+                        mapper_values.num_mapped += 1;
+                        mapper_values.num_multi_mapped += alignment.has_secondary_alignments as i64;
+                        mapper_values.edit_distances.extend(alignment.edit_distance);
+                    }
+                }
+            }
+        }
+    }
+
+    let classes = annotations
+        .labels()
+        .map(|label| {
+            let values = values_per_class
+                .remove(label)
+                .expect("all labels have values");
+
+            RegionClassStats {
+                region_class: label.to_owned(),
+                num_queries: values.num_queries,
+                num_disagreements: values.num_disagreements,
+                stats_per_mapper: values
+                    .per_mapper
+                    .into_iter()
+                    .map(|mapper_values| RegionMapperStats {
+                        num_mapped: mapper_values.num_mapped,
+                        num_unmapped: mapper_values.num_unmapped,
+                        num_multi_mapped: mapper_values.num_multi_mapped,
+                        edit_distance: HistogramData::from_values(
+                            mapper_values.edit_distances,
+                            EDIT_DISTANCE_THRESHOLDS.to_vec(),
+                        ),
+                    })
+                    .collect(),
+            }
+        })
+        .collect();
+
+    Ok(RegionStratifiedComparison {
+        mappers: comparison.mappers.clone(),
+        position_tolerance,
+        num_located_queries: located_queries.len() as i64,
+        num_unlocated_queries: comparison.number_of_queries - located_queries.len() as i64,
+        classes,
+    })
+}
+
+// writes the stratified comparison to the benchmark folder
+pub fn analyze_region_stratified_comparison(
+    comparison: &MapperComparison,
+    annotations: &RegionAnnotations,
+    position_tolerance: u64,
+    benchmark_folder: &BenchmarkFolder,
+) -> Result<RegionStratifiedComparison> {
+    let stratified_comparison = stratify_by_regions(comparison, annotations, position_tolerance)?;
+
+    let mut result_file_path = benchmark_folder.get().to_owned();
+    result_file_path.push("region_stratified_comparison.toml");
+
+    fs::write(result_file_path, toml::to_string(&stratified_comparison)?)?;
+
+    Ok(stratified_comparison)
+}
+
+struct RegionClassValues {
+    num_queries: i64,
+    num_disagreements: i64,
+    per_mapper: Vec<RegionMapperValues>,
+}
+
+impl RegionClassValues {
+    fn new(num_mappers: usize) -> Self {
+        Self {
+            num_queries: 0,
+            num_disagreements: 0,
+            per_mapper: (0..num_mappers).map(|_| Default::default()).collect(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct RegionMapperValues {
+    num_mapped: i64,
+    num_unmapped: i64,
+    num_multi_mapped: i64,
+    edit_distances: Vec<usize>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RegionStratifiedComparison {
+    pub mappers: Vec<String>,
+    pub position_tolerance: u64,
+    pub num_located_queries: i64,
+    // queries that no mapper mapped
+    pub num_unlocated_queries: i64,
+    // in the order of the BED files, the unannotated class is last. A query is counted in every
+    // class that overlaps its location
+    pub classes: Vec<RegionClassStats>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RegionClassStats {
+    pub region_class: String,
+    pub num_queries: i64,
+    pub num_disagreements: i64,
+    // in the order of the mappers
+    pub stats_per_mapper: Vec<RegionMapperStats>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RegionMapperStats {
+    pub num_mapped: i64,
+    pub num_unmapped: i64,
+    // with at least one secondary alignment
+    pub num_multi_mapped: i64,
+    // of the primary alignments
+    pub edit_distance: HistogramData,
+}
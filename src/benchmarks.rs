@@ -1,9 +1,17 @@
 use std::collections::HashSet;
+use std::rc::Rc;
 use std::sync::atomic::{AtomicUsize, Ordering};
 
-use crate::analyze_mapped_reads::analyze_alignments_detailed_comparison;
+use crate::analyze_mapped_reads::{
+    analyze_alignments_comparison, analyze_alignments_detailed_comparison,
+    analyze_simulated_verification_counts, export_discrepancies,
+    multi_mapping::analyze_multi_mapping_comparison,
+    regions::{analyze_region_stratified_comparison, RegionAnnotations},
+    MapperComparison, VerificationCounts,
+};
 use crate::cli::BenchmarkConfig;
 use crate::config::BenchmarkSuiteConfig;
+use crate::export::BenchmarkResults;
 use crate::folder_structure::BenchmarkFolder;
 use crate::plots::{self, thesis};
 use crate::readmappers::floxer::{
@@ -11,7 +19,10 @@ use crate::readmappers::floxer::{
     FloxerRunResult, IntervalOptimization, PexTreeConstruction, QueryErrors, VerificationAlgorithm,
 };
 use crate::readmappers::minimap::MinimapConfig;
-use crate::readmappers::{IndexStrategy, Queries, Reference};
+use crate::readmappers::{
+    compare_repeated_resource_metrics, IndexStrategy, Queries, Reference, ResourceMetrics,
+};
+use crate::report;
 
 use anyhow::{bail, Result};
 use clap::ValueEnum;
@@ -105,6 +116,81 @@ impl Benchmark {
             }
         }
     }
+
+    // for the reports
+    pub fn description(&self) -> &'static str {
+        match *self {
+            Benchmark::AnchorGroupOrderAndChoiceStrategy => {
+                "floxer with the combinations of anchor group orders and anchor choice strategies, \
+                with 3 errors per PEX seed."
+            }
+            Benchmark::AnchorsPerVerificationTask => {
+                "floxer with 1000, 3000, 10,000 and unlimited anchors per verification task."
+            }
+            Benchmark::Debug => {
+                "floxer with both PEX tree constructions on the debug reference and queries, \
+                single-threaded and with 2 query errors."
+            }
+            Benchmark::DefaultParams => "floxer with its default parameters.",
+            Benchmark::EraseUselessAnchors => "floxer with and without erasing useless anchors.",
+            Benchmark::ExtraVerificationRatio => {
+                "floxer with extra verification ratios from 0.02 to 0.3."
+            }
+            Benchmark::IndexBuild => {
+                "Index construction of floxer and minimap, the queries are not mapped."
+            }
+            Benchmark::IntervalOptimization => "floxer with and without the interval optimization.",
+            Benchmark::MaxAnchorsHard => {
+                "floxer with hard anchor caps of 50, 200, 1000 and no cap."
+            }
+            Benchmark::Minimap => {
+                "floxer (with CIGAR output) compared to minimap at the default error rate."
+            }
+            Benchmark::MinimapHighErrorRate => {
+                "floxer (with CIGAR output) at the high error rate compared to minimap."
+            }
+            Benchmark::PexSeedErrors => "floxer with 0 to 3 errors per PEX seed.",
+            Benchmark::PexSeedErrorsHighErrorRate => {
+                "floxer with 0 to 3 errors per PEX seed at the high error rate."
+            }
+            Benchmark::PexSeedErrorsNoMaxAnchors => {
+                "floxer with 0 to 3 errors per PEX seed and without anchor caps."
+            }
+            Benchmark::PexSeedErrorsNoMaxAnchorsAndHighErrorRate => {
+                "floxer with 1 to 3 errors per PEX seed, without anchor caps and at the high error \
+                rate."
+            }
+            Benchmark::PexTreeBuilding => {
+                "floxer with top down and bottom up PEX tree construction, with 1 and 2 errors per \
+                PEX seed."
+            }
+            Benchmark::ProblemQuery => {
+                "floxer mapping the problem query 5 times (with profiling) to find \
+                non-deterministic bugs."
+            }
+            Benchmark::Profile => "floxer with its default parameters, profiled with perf.",
+            Benchmark::QueryErrorRate => "floxer with query error rates from 0.05 to 0.15.",
+            Benchmark::SeedSamplingStepSize => "floxer with seed sampling step sizes from 1 to 16.",
+            Benchmark::SoftAnchorCap => {
+                "floxer with different soft anchor caps, for 1 and 2 errors per PEX seed."
+            }
+            Benchmark::Threads => "floxer with 8 to 32 threads.",
+            Benchmark::VerificationAlgorithm => "floxer with every verification algorithm.",
+        }
+    }
+
+    // the benchmark folders are named after the benchmark, with a few exceptions
+    pub fn from_folder_name(folder_name: &str) -> Option<Self> {
+        if folder_name == "default" {
+            return Some(Benchmark::DefaultParams);
+        }
+
+        if folder_name.starts_with("soft_anchor_cap_") {
+            return Some(Benchmark::SoftAnchorCap);
+        }
+
+        Benchmark::from_str(&folder_name.replace('_', "-"), false).ok()
+    }
 }
 
 pub fn run_benchmarks<I: IntoIterator<Item = Benchmark>>(
@@ -122,6 +208,12 @@ pub fn run_benchmarks<I: IntoIterator<Item = Benchmark>>(
         }
     }
 
+    // a failing report should not hide the errors of the runs
+    match report::write_suite_report(suite_config) {
+        Ok(report_path) => println!("- wrote report {}", report_path.display()),
+        Err(err) => println!("- Warning: could not write the report: {err:#}"),
+    }
+
     if num_error_runs != 0 {
         bail!("errors occurred in at least {num_error_runs} run(s)")
     }
@@ -251,6 +343,39 @@ impl BenchmarkResult {
             &self.folder,
             suite_config,
         );
+
+        plots::plot_alignment_histograms(
+            self.floxer_results
+                .iter()
+                .map(|run| (&run.mapped_read_stats, run.benchmark_instance_name.as_str())),
+            &self.benchmark_name,
+            &self.folder,
+            suite_config,
+        );
+
+        plots::plot_length_binned_stats(
+            self.floxer_results.iter().map(|run| {
+                (
+                    &run.length_binned_stats,
+                    run.benchmark_instance_name.as_str(),
+                )
+            }),
+            &self.benchmark_name,
+            &self.folder,
+            suite_config,
+        );
+
+        plots::plot_mapq_calibration(
+            self.floxer_results.iter().map(|run| {
+                (
+                    run.mapq_calibration.as_ref(),
+                    run.benchmark_instance_name.as_str(),
+                )
+            }),
+            &self.benchmark_name,
+            &self.folder,
+            suite_config,
+        );
     }
 }
 
@@ -449,6 +574,13 @@ fn index_build(
     }
     .run(&folder, suite_config)?;
 
+    BenchmarkResults {
+        benchmark_name: name,
+        floxer: vec![&floxer_res],
+        minimap: Some(&minimap_res),
+    }
+    .export(&folder)?;
+
     plots::plot_resource_metrics(
         name,
         [
@@ -533,6 +665,13 @@ fn minimap(suite_config: &BenchmarkSuiteConfig, benchmark_config: &BenchmarkConf
 
     let minimap_res = MinimapConfig::from(benchmark_config).run(&folder, suite_config)?;
 
+    BenchmarkResults {
+        benchmark_name: name,
+        floxer: vec![&floxer_res],
+        minimap: Some(&minimap_res),
+    }
+    .export(&folder)?;
+
     plots::plot_resource_metrics(
         name,
         [
@@ -542,23 +681,97 @@ fn minimap(suite_config: &BenchmarkSuiteConfig, benchmark_config: &BenchmarkConf
         &folder,
         suite_config,
     );
+    compare_resource_metrics_if_repeated(
+        [
+            (floxer_res.repeated_resource_metrics.as_slice(), "floxer"),
+            (
+                minimap_res.repeated_map_resource_metrics.as_slice(),
+                "minimap",
+            ),
+        ],
+        name,
+        &folder,
+        suite_config,
+    )?;
 
-    let mut floxer_mapped_reads_path = folder.most_recect_previous_run_folder();
-    floxer_mapped_reads_path.push("floxer");
-    floxer_mapped_reads_path.push("mapped_reads.bam");
-
-    let mut minimap_mapped_reads_path = folder.most_recect_previous_run_folder();
-    minimap_mapped_reads_path.push("minimap");
-    minimap_mapped_reads_path.push("mapped_reads.sam");
-
-    let aligner_comparison = analyze_alignments_detailed_comparison(
-        &floxer_mapped_reads_path,
-        &minimap_mapped_reads_path,
+    let comparison = analyze_alignments_comparison(
+        [
+            (
+                "floxer",
+                &floxer_res.mapped_reads_path,
+                Rc::clone(&floxer_res.alignments_per_query),
+            ),
+            (
+                "minimap",
+                &minimap_res.mapped_reads_path,
+                Rc::clone(&minimap_res.alignments_per_query),
+            ),
+        ],
         floxer::DEFAULT_ERROR_RATE,
         &folder,
+    )?;
+    let aligner_comparison = analyze_alignments_detailed_comparison(&comparison, &folder)?;
+    analyze_regions_if_given(&comparison, benchmark_config, &folder)?;
+    let multi_mapping_comparison = analyze_multi_mapping_comparison(&comparison, &folder)?;
+    export_discrepancies(
+        &comparison,
+        &folder.discrepancies_folder(),
+        None,
         suite_config,
     )?;
 
+    plots::plot_mapped_reads_stats(
+        [
+            &floxer_res.mapped_read_stats,
+            &minimap_res.mapped_read_stats,
+        ],
+        &format!("{name} mapped reads stats"),
+        ["floxer", "minimap"],
+        &folder,
+        suite_config,
+    );
+    plots::plot_alignment_histograms(
+        [
+            (&floxer_res.mapped_read_stats, "floxer"),
+            (&minimap_res.mapped_read_stats, "minimap"),
+        ],
+        name,
+        &folder,
+        suite_config,
+    );
+    plots::plot_length_binned_stats(
+        [
+            (&floxer_res.length_binned_stats, "floxer"),
+            (&minimap_res.length_binned_stats, "minimap"),
+        ],
+        name,
+        &folder,
+        suite_config,
+    );
+    analyze_simulated_verification_if_available(
+        [
+            (floxer_res.simulated_verification_counts.as_ref(), "floxer"),
+            (
+                minimap_res.simulated_verification_counts.as_ref(),
+                "minimap",
+            ),
+        ],
+        name,
+        &folder,
+        suite_config,
+    )?;
+    plots::plot_mapq_calibration(
+        [
+            (floxer_res.mapq_calibration.as_ref(), "floxer"),
+            (minimap_res.mapq_calibration.as_ref(), "minimap"),
+        ],
+        name,
+        &folder,
+        suite_config,
+    );
+    plots::plot_mapper_comparison(&comparison, name, &folder, suite_config);
+    plots::plot_read_significance(&comparison, name, &folder, suite_config);
+    plots::plot_multi_mapping(&multi_mapping_comparison, name, &folder, suite_config);
     plots::create_floxer_vs_minimap_plots(&aligner_comparison, &folder, suite_config);
 
     Ok(())
@@ -589,6 +802,13 @@ fn minimap_high_error_rate(
 
     let minimap_res = MinimapConfig::from(benchmark_config).run(&folder, suite_config)?;
 
+    BenchmarkResults {
+        benchmark_name,
+        floxer: vec![&floxer_res],
+        minimap: Some(&minimap_res),
+    }
+    .export(&folder)?;
+
     plots::plot_resource_metrics(
         benchmark_name,
         [
@@ -598,28 +818,178 @@ fn minimap_high_error_rate(
         &folder,
         suite_config,
     );
+    compare_resource_metrics_if_repeated(
+        [
+            (
+                floxer_res.repeated_resource_metrics.as_slice(),
+                floxer_instance_name.as_str(),
+            ),
+            (
+                minimap_res.repeated_map_resource_metrics.as_slice(),
+                "minimap",
+            ),
+        ],
+        benchmark_name,
+        &folder,
+        suite_config,
+    )?;
 
-    let mut floxer_mapped_reads_path = folder.most_recect_previous_run_folder();
-    floxer_mapped_reads_path.push(floxer_instance_name);
-    floxer_mapped_reads_path.push("mapped_reads.bam");
-
-    let mut minimap_mapped_reads_path = folder.most_recect_previous_run_folder();
-    minimap_mapped_reads_path.push("minimap");
-    minimap_mapped_reads_path.push("mapped_reads.sam");
-
-    let aligner_comparison = analyze_alignments_detailed_comparison(
-        &floxer_mapped_reads_path,
-        &minimap_mapped_reads_path,
+    let comparison = analyze_alignments_comparison(
+        [
+            (
+                floxer_instance_name.as_str(),
+                &floxer_res.mapped_reads_path,
+                Rc::clone(&floxer_res.alignments_per_query),
+            ),
+            (
+                "minimap",
+                &minimap_res.mapped_reads_path,
+                Rc::clone(&minimap_res.alignments_per_query),
+            ),
+        ],
         floxer::HIGH_ERROR_RATE,
         &folder,
+    )?;
+    let aligner_comparison = analyze_alignments_detailed_comparison(&comparison, &folder)?;
+    analyze_regions_if_given(&comparison, benchmark_config, &folder)?;
+    let multi_mapping_comparison = analyze_multi_mapping_comparison(&comparison, &folder)?;
+    export_discrepancies(
+        &comparison,
+        &folder.discrepancies_folder(),
+        None,
         suite_config,
     )?;
 
+    plots::plot_mapped_reads_stats(
+        [
+            &floxer_res.mapped_read_stats,
+            &minimap_res.mapped_read_stats,
+        ],
+        &format!("{benchmark_name} mapped reads stats"),
+        [floxer_instance_name.as_str(), "minimap"],
+        &folder,
+        suite_config,
+    );
+    plots::plot_alignment_histograms(
+        [
+            (&floxer_res.mapped_read_stats, floxer_instance_name.as_str()),
+            (&minimap_res.mapped_read_stats, "minimap"),
+        ],
+        benchmark_name,
+        &folder,
+        suite_config,
+    );
+    plots::plot_length_binned_stats(
+        [
+            (
+                &floxer_res.length_binned_stats,
+                floxer_instance_name.as_str(),
+            ),
+            (&minimap_res.length_binned_stats, "minimap"),
+        ],
+        benchmark_name,
+        &folder,
+        suite_config,
+    );
+    analyze_simulated_verification_if_available(
+        [
+            (
+                floxer_res.simulated_verification_counts.as_ref(),
+                floxer_instance_name.as_str(),
+            ),
+            (
+                minimap_res.simulated_verification_counts.as_ref(),
+                "minimap",
+            ),
+        ],
+        benchmark_name,
+        &folder,
+        suite_config,
+    )?;
+    plots::plot_mapq_calibration(
+        [
+            (
+                floxer_res.mapq_calibration.as_ref(),
+                floxer_instance_name.as_str(),
+            ),
+            (minimap_res.mapq_calibration.as_ref(), "minimap"),
+        ],
+        benchmark_name,
+        &folder,
+        suite_config,
+    );
+    plots::plot_mapper_comparison(&comparison, benchmark_name, &folder, suite_config);
+    plots::plot_read_significance(&comparison, benchmark_name, &folder, suite_config);
+    plots::plot_multi_mapping(
+        &multi_mapping_comparison,
+        benchmark_name,
+        &folder,
+        suite_config,
+    );
     plots::create_floxer_vs_minimap_plots(&aligner_comparison, &folder, suite_config);
 
     Ok(())
 }
 
+// instances without verification counts (not a simulated dataset) are left out
+fn analyze_simulated_verification_if_available<'a>(
+    counts_and_names_of_runs: impl IntoIterator<Item = (Option<&'a VerificationCounts>, &'a str)>,
+    benchmark_name: &str,
+    folder: &BenchmarkFolder,
+    suite_config: &BenchmarkSuiteConfig,
+) -> Result<()> {
+    let counts_and_names: Vec<_> = counts_and_names_of_runs
+        .into_iter()
+        .filter_map(|(counts, name)| counts.map(|counts| (counts, name)))
+        .collect();
+
+    if counts_and_names.is_empty() {
+        return Ok(());
+    }
+
+    let results = analyze_simulated_verification_counts(counts_and_names, folder)?;
+    plots::plot_simulated_verification(&results, benchmark_name, folder, suite_config);
+
+    Ok(())
+}
+
+// only instances that were run repeatedly can be compared
+fn compare_resource_metrics_if_repeated<'a>(
+    metrics_and_names_of_runs: impl IntoIterator<Item = (&'a [ResourceMetrics], &'a str)>,
+    benchmark_name: &str,
+    folder: &BenchmarkFolder,
+    suite_config: &BenchmarkSuiteConfig,
+) -> Result<()> {
+    let Some(comparison) = compare_repeated_resource_metrics(metrics_and_names_of_runs, folder)?
+    else {
+        return Ok(());
+    };
+
+    plots::plot_resource_metrics_significance(&comparison, benchmark_name, folder, suite_config);
+
+    Ok(())
+}
+
+fn analyze_regions_if_given(
+    comparison: &MapperComparison,
+    benchmark_config: &BenchmarkConfig,
+    folder: &BenchmarkFolder,
+) -> Result<()> {
+    if benchmark_config.regions.is_empty() {
+        return Ok(());
+    }
+
+    let annotations = RegionAnnotations::load(&benchmark_config.regions)?;
+    analyze_region_stratified_comparison(
+        comparison,
+        &annotations,
+        benchmark_config.position_tolerance,
+        folder,
+    )?;
+
+    Ok(())
+}
+
 // some code duplication here for the pex seed, but I'll live with it for now.
 fn pex_seed_errors(
     suite_config: &BenchmarkSuiteConfig,
@@ -863,7 +1233,7 @@ fn soft_anchor_cap(
     for pex_seed_errors in [1, 2] {
         let mut values = vec![5, 10, 20, 50];
 
-        values.push(if benchmark_config.reference == Reference::Simulated {
+        values.push(if benchmark_config.reference.is_simulated(suite_config) {
             u64::MAX
         } else {
             100
@@ -993,6 +1363,13 @@ impl FloxerParameterBenchmark {
             instance_names.push(floxer_config.name.clone());
         }
 
+        BenchmarkResults {
+            benchmark_name: &self.benchmark_name,
+            floxer: floxer_results.iter().collect(),
+            minimap: None,
+        }
+        .export(&benchmark_folder)?;
+
         plots::plot_resource_metrics(
             &self.benchmark_name,
             floxer_results
@@ -1001,6 +1378,17 @@ impl FloxerParameterBenchmark {
             &benchmark_folder,
             suite_config,
         );
+        compare_resource_metrics_if_repeated(
+            floxer_results.iter().map(|res| {
+                (
+                    res.repeated_resource_metrics.as_slice(),
+                    res.benchmark_instance_name.as_str(),
+                )
+            }),
+            &self.benchmark_name,
+            &benchmark_folder,
+            suite_config,
+        )?;
 
         let res = BenchmarkResult {
             benchmark_name: self.benchmark_name.to_owned(),
@@ -1011,6 +1399,60 @@ impl FloxerParameterBenchmark {
         res.plot_general_stats(suite_config);
         res.plot_mapped_reads_stats(suite_config);
 
+        analyze_simulated_verification_if_available(
+            res.floxer_results.iter().map(|run| {
+                (
+                    run.simulated_verification_counts.as_ref(),
+                    run.benchmark_instance_name.as_str(),
+                )
+            }),
+            &self.benchmark_name,
+            &res.folder,
+            suite_config,
+        )?;
+
+        if res.floxer_results.len() > 1 {
+            let comparison = analyze_alignments_comparison(
+                res.floxer_results.iter().map(|run| {
+                    (
+                        run.benchmark_instance_name.as_str(),
+                        &run.mapped_reads_path,
+                        Rc::clone(&run.alignments_per_query),
+                    )
+                }),
+                floxer::DEFAULT_ERROR_RATE,
+                &res.folder,
+            )?;
+            analyze_regions_if_given(&comparison, benchmark_config, &res.folder)?;
+            let multi_mapping_comparison =
+                analyze_multi_mapping_comparison(&comparison, &res.folder)?;
+            export_discrepancies(
+                &comparison,
+                &res.folder.discrepancies_folder(),
+                None,
+                suite_config,
+            )?;
+
+            plots::plot_mapper_comparison(
+                &comparison,
+                &self.benchmark_name,
+                &res.folder,
+                suite_config,
+            );
+            plots::plot_read_significance(
+                &comparison,
+                &self.benchmark_name,
+                &res.folder,
+                suite_config,
+            );
+            plots::plot_multi_mapping(
+                &multi_mapping_comparison,
+                &self.benchmark_name,
+                &res.folder,
+                suite_config,
+            );
+        }
+
         Ok(res)
     }
 }
@@ -1,15 +1,21 @@
-use clap::{Args, Parser};
+use clap::{Args, Parser, Subcommand};
+use jiff::{civil::Date, tz::TimeZone, Span, Timestamp, Zoned};
 use std::path::PathBuf;
 
+use crate::analyze_mapped_reads::length_bins::{self, LengthBins};
 use crate::benchmarks::Benchmark;
-use crate::readmappers::floxer::CigarOutput;
+use crate::readmappers::floxer::{self, CigarOutput};
 use crate::readmappers::{Queries, Reference};
+use crate::results_database::ResultsMetric;
 
 #[derive(Parser)]
 pub struct CliArgs {
-    #[arg(short, long, default_value = "benchmark_config.toml")]
+    #[arg(short, long, global = true, default_value = "benchmark_config.toml")]
     pub config_file: PathBuf,
 
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
     /// Give benchmark names that should be run. If none are given, all will be run
     #[arg(value_enum)]
     pub benchmarks: Option<Vec<Benchmark>>,
@@ -28,14 +34,313 @@ pub struct BenchmarkConfig {
     #[arg(short, long)]
     pub tag: Option<String>,
 
-    #[arg(short, long, value_enum, default_value_t = Reference::HumanGenomeHg38)]
+    /// One of human-genome-hg38, masked-human-genome-hg38, debug, simulated or the name of a
+    /// registered simulated dataset
+    #[arg(short, long, default_value_t = Reference::HumanGenomeHg38)]
     pub reference: Reference,
 
-    #[arg(short, long, value_enum, default_value_t = Queries::HumanWgsNanopore)]
+    /// One of human-wgs-nanopore, human-wgs-nanopore-small, debug, problem-query, simulated,
+    /// simulated-small or the name of a registered derived or simulated dataset
+    #[arg(short, long, default_value_t = Queries::HumanWgsNanopore)]
     pub queries: Queries,
 
     #[arg(long, value_enum, default_value_t = CigarOutput::Off)]
     pub cigar_output: CigarOutput,
+
+    /// Maximum distance between the mapped and the true start position of a simulated read
+    /// for it to count as found
+    #[arg(long, default_value_t = 100)]
+    pub position_tolerance: u64,
+
+    /// Recompute the edit distances of the mapped reads (from CIGAR and MD tag or the reference)
+    /// instead of trusting the NM tags, mismatches are reported
+    #[arg(long)]
+    pub recompute_edit_distances: bool,
+
+    /// Never load the whole reference into memory, e.g. for large references. The alignments of
+    /// floxer are then not validated and edit distances are only recomputed from CIGAR and MD tag
+    #[arg(long)]
+    pub skip_reference_loading: bool,
+
+    /// Search every query exhaustively in the reference and report recall and precision of floxer
+    /// against these locations (only for the debug and simulated references)
+    #[arg(long)]
+    pub exhaustive_oracle: bool,
+
+    /// BED annotations (e.g. repeats or centromeres) as <label>=<path> or just <path>. Mapper comparisons
+    /// are additionally reported per region class
+    #[arg(long, value_parser = parse_labelled_path)]
+    pub regions: Vec<(String, PathBuf)>,
+
+    /// Run every read mapper this many times. The resource usage of the instances of a benchmark is
+    /// then compared with Welch's t-test, the mapped reads of the last repetition are analyzed
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u16).range(1..))]
+    pub repetitions: u16,
+
+    /// Query length bins for the mapped reads analysis, either comma separated upper bounds or
+    /// log:<bins per decade> for log-spaced bins from 100 to 1,000,000
+    #[arg(long, default_value = length_bins::DEFAULT_LENGTH_BINS)]
+    pub length_bins: LengthBins,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Create a new query dataset from an existing one and register it under the given name
+    DeriveQueries(DeriveQueriesArgs),
+    /// Generate a simulated reference and queries with the parameters from the config file
+    SimulateDataset(SimulateDatasetArgs),
+    /// Compare the mapped reads (SAM, BAM or PAF) of any number of read mappers or configurations
+    CompareMappedReads(CompareMappedReadsArgs),
+    /// Query the results of all completed benchmark instances, which are recorded in the results
+    /// database in the output folder
+    Results(ResultsArgs),
+    /// Store recorded runs as a named baseline and check later runs against it for performance
+    /// regressions
+    Baseline(BaselineArgs),
+    /// Write self-contained HTML reports of the most recent benchmark runs and an index page to
+    /// the output folder
+    Report(ReportArgs),
+}
+
+#[derive(Args)]
+pub struct DeriveQueriesArgs {
+    /// Name of the new query dataset, can afterwards be used with --queries
+    pub name: String,
+
+    /// The query dataset to derive from
+    #[arg(short, long)]
+    pub source: Queries,
+
+    #[command(subcommand)]
+    pub derivation: QueryDerivationArgs,
+}
+
+#[derive(Args)]
+pub struct SimulateDatasetArgs {
+    /// Name of a [simulated_datasets.<name>] table in the config file
+    pub name: String,
+
+    /// Generate the dataset again with the parameters and seed recorded in the dataset registry
+    #[arg(long)]
+    pub rebuild: bool,
+}
+
+#[derive(Args)]
+pub struct CompareMappedReadsArgs {
+    /// Mapped reads as <label>=<path> or just <path>, then the file name is the label
+    #[arg(required = true, value_parser = parse_labelled_path)]
+    pub mapped_reads: Vec<(String, PathBuf)>,
+
+    /// Best alignments with a higher error rate count as high edit distance alignments
+    #[arg(long, default_value_t = floxer::DEFAULT_ERROR_RATE)]
+    pub error_rate: f64,
+
+    /// Write the comparison (TOML) to this file instead of stdout
+    #[arg(long)]
+    pub output: Option<PathBuf>,
+
+    /// Write a TSV report of the reads that only one mapper mapped (for every mapper) into this folder
+    #[arg(long)]
+    pub discrepancies: Option<PathBuf>,
+
+    /// The queries that were mapped. With --discrepancies, the reads that only one mapper mapped
+    /// are also written as query datasets and registered as <folder name>_only_<label>_mapped
+    #[arg(short, long, requires = "discrepancies")]
+    pub queries: Option<Queries>,
+
+    /// BED annotations (e.g. repeats or centromeres) as <label>=<path> or just <path>. The comparison
+    /// is additionally reported per region class
+    #[arg(long, value_parser = parse_labelled_path, requires = "regions_output")]
+    pub regions: Vec<(String, PathBuf)>,
+
+    /// Write the comparison per region class (TOML) to this file
+    #[arg(long, requires = "regions")]
+    pub regions_output: Option<PathBuf>,
+
+    /// Write the multi-mapping comparison (alignments per read, edit distance gaps, tandem vs
+    /// interspersed loci and the overlap of the alternative loci of the mappers) (TOML) to this file
+    #[arg(long)]
+    pub multi_mapping_output: Option<PathBuf>,
+
+    /// Maximum distance between the primary alignments of two mappers for them to agree on a query
+    #[arg(long, default_value_t = 100)]
+    pub position_tolerance: u64,
+}
+
+#[derive(Args)]
+pub struct ResultsArgs {
+    #[command(subcommand)]
+    pub query: ResultsQueryArgs,
+}
+
+#[derive(Subcommand)]
+pub enum ResultsQueryArgs {
+    /// Every matching instance with its inputs, version, host, resource usage and mapped reads
+    List(ResultsFilterArgs),
+    /// Number of runs, mean, min and max of a metric per benchmark instance and inputs
+    Summarize {
+        #[command(flatten)]
+        filter: ResultsFilterArgs,
+
+        #[arg(long, value_enum, default_value_t = ResultsMetric::WallClockSeconds)]
+        metric: ResultsMetric,
+    },
+}
+
+#[derive(Args)]
+pub struct ResultsFilterArgs {
+    #[arg(long)]
+    pub benchmark: Option<String>,
+
+    #[arg(long)]
+    pub instance: Option<String>,
+
+    /// floxer or minimap
+    #[arg(long)]
+    pub mapper: Option<String>,
+
+    #[arg(short, long)]
+    pub reference: Option<Reference>,
+
+    #[arg(short, long)]
+    pub queries: Option<Queries>,
+
+    #[arg(short, long)]
+    pub tag: Option<String>,
+
+    #[arg(long)]
+    pub host: Option<String>,
+
+    /// Only instances that completed after this date (e.g. 2024-10-01) or in this ISO 8601
+    /// duration before now (e.g. P3M for the last 3 months)
+    #[arg(long, value_parser = parse_since)]
+    pub since: Option<Timestamp>,
+}
+
+#[derive(Args)]
+pub struct ReportArgs {
+    /// Only write the report of this benchmark run folder (<output>/<benchmark>/<inputs>/<run>)
+    #[arg(short, long)]
+    pub benchmark_folder: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct BaselineArgs {
+    #[command(subcommand)]
+    pub command: BaselineCommandArgs,
+}
+
+#[derive(Subcommand)]
+pub enum BaselineCommandArgs {
+    /// Store the most recent run of every matching benchmark instance as the baseline with this
+    /// name, an existing baseline with the same name is replaced
+    Promote {
+        name: String,
+
+        #[command(flatten)]
+        filter: ResultsFilterArgs,
+    },
+    /// All baselines with the time of their promotion and their number of instances
+    List,
+    /// Compare the most recent matching run of every instance of the baseline against it. Fails
+    /// with a nonzero exit code if any instance got worse beyond the thresholds or has no matching
+    /// run other than the baseline run
+    Check {
+        name: String,
+
+        #[command(flatten)]
+        filter: ResultsFilterArgs,
+
+        #[command(flatten)]
+        thresholds: RegressionThresholds,
+    },
+}
+
+// all thresholds are relative to the baseline
+#[derive(Args)]
+pub struct RegressionThresholds {
+    /// For example 0.1 to allow up to 10% more wall clock time than the baseline
+    #[arg(long, default_value_t = 0.1)]
+    pub max_wall_clock_increase: f64,
+
+    /// Of user and system CPU time
+    #[arg(long, default_value_t = 0.1)]
+    pub max_cpu_increase: f64,
+
+    /// Of peak memory
+    #[arg(long, default_value_t = 0.1)]
+    pub max_memory_increase: f64,
+
+    /// For example 0.01 to allow 1% fewer mapped reads than the baseline
+    #[arg(long, default_value_t = 0.0)]
+    pub max_mapped_decrease: f64,
+
+    /// Of simulated reads whose true origin is found by the primary alignment
+    #[arg(long, default_value_t = 0.0)]
+    pub max_simulated_found_decrease: f64,
+}
+
+fn parse_since(value: &str) -> Result<Timestamp, String> {
+    if let Ok(date) = value.parse::<Date>() {
+        return date
+            .to_zoned(TimeZone::system())
+            .map(|start_of_day| start_of_day.timestamp())
+            .map_err(|error| error.to_string());
+    }
+
+    let span: Span = value
+        .parse()
+        .map_err(|_| format!("{value} is neither a date nor an ISO 8601 duration"))?;
+
+    Zoned::now()
+        .checked_sub(span)
+        .map(|since| since.timestamp())
+        .map_err(|error| error.to_string())
+}
+
+fn parse_labelled_path(value: &str) -> Result<(String, PathBuf), String> {
+    if let Some((label, path)) = value.split_once('=') {
+        if label.is_empty() {
+            return Err(String::from("label must not be empty"));
+        }
+
+        return Ok((label.to_owned(), PathBuf::from(path)));
+    }
+
+    let path = PathBuf::from(value);
+    let label = path
+        .file_stem()
+        .ok_or_else(|| format!("no file name in {value}"))?
+        .to_string_lossy()
+        .into_owned();
+
+    Ok((label, path))
+}
+
+#[derive(Subcommand)]
+pub enum QueryDerivationArgs {
+    /// Random subsample of the reads, which only depends on the seed
+    Subsample {
+        #[arg(short, long, conflicts_with = "fraction")]
+        num_reads: Option<usize>,
+
+        /// For example 0.01 to run benchmarks at 1% scale
+        #[arg(short, long)]
+        fraction: Option<f64>,
+
+        #[arg(long, default_value_t = 42)]
+        seed: u64,
+    },
+    /// All reads whose id is listed in the given file (one id per line)
+    ReadIds { read_id_file: PathBuf },
+    /// All reads with a length in the given (inclusive) window
+    LengthWindow {
+        #[arg(long)]
+        min_length: Option<usize>,
+
+        #[arg(long)]
+        max_length: Option<usize>,
+    },
 }
 
 impl BenchmarkConfig {
@@ -1,6 +1,8 @@
 use anyhow::Result;
 use serde::Deserialize;
-use std::{fs, path::PathBuf};
+use std::{collections::BTreeMap, fs, path::PathBuf};
+
+use crate::datasets::{simulated::SimulationParameters, DatasetRegistry};
 
 // config that is read from a file.
 // these are parameters of this program that don't change for every benchmark
@@ -8,11 +10,15 @@ use std::{fs, path::PathBuf};
 #[derive(Deserialize)]
 pub struct BenchmarkSuiteConfig {
     pub output_folder: PathBuf,
-    pub compare_aligner_outputs_binary: PathBuf,
     pub simulated_dataset_binary: PathBuf,
     pub readmapper_binaries: ReadmapperBinaries,
     pub reference_paths: ReferencePaths,
     pub query_paths: QueryPaths,
+    #[serde(default)]
+    pub simulated_datasets: BTreeMap<String, SimulationParameters>,
+    // loaded from the output folder during setup
+    #[serde(skip)]
+    pub datasets: DatasetRegistry,
 }
 
 #[derive(Deserialize)]
@@ -52,17 +58,69 @@ impl BenchmarkSuiteConfig {
         base_output_folder
     }
 
-    pub fn setup(&self) -> Result<()> {
+    pub fn oracle_folder(&self) -> PathBuf {
+        let mut base_output_folder = self.output_folder.clone();
+        base_output_folder.push("oracles");
+        base_output_folder
+    }
+
+    pub fn datasets_folder(&self) -> PathBuf {
+        let mut base_output_folder = self.output_folder.clone();
+        base_output_folder.push("datasets");
+        base_output_folder
+    }
+
+    pub fn derived_queries_folder(&self) -> PathBuf {
+        let mut datasets_folder = self.datasets_folder();
+        datasets_folder.push("derived_queries");
+        datasets_folder
+    }
+
+    pub fn simulated_datasets_folder(&self) -> PathBuf {
+        let mut datasets_folder = self.datasets_folder();
+        datasets_folder.push("simulated");
+        datasets_folder
+    }
+
+    pub fn results_database_path(&self) -> PathBuf {
+        let mut base_output_folder = self.output_folder.clone();
+        base_output_folder.push("results.sqlite");
+        base_output_folder
+    }
+
+    pub fn dataset_registry_path(&self) -> PathBuf {
+        let mut datasets_folder = self.datasets_folder();
+        datasets_folder.push("registry.toml");
+        datasets_folder
+    }
+
+    pub fn save_dataset_registry(&self) -> Result<()> {
+        self.datasets.save(&self.dataset_registry_path())
+    }
+
+    pub fn setup(&mut self) -> Result<()> {
         let index_folder = self.index_folder();
         if !index_folder.exists() {
             fs::create_dir_all(index_folder)?;
         }
 
+        let oracle_folder = self.oracle_folder();
+        if !oracle_folder.exists() {
+            fs::create_dir_all(oracle_folder)?;
+        }
+
         let all_plots_dir = self.all_plots_folder();
         if !all_plots_dir.exists() {
             fs::create_dir_all(all_plots_dir)?;
         }
 
+        let datasets_folder = self.datasets_folder();
+        if !datasets_folder.exists() {
+            fs::create_dir_all(datasets_folder)?;
+        }
+
+        self.datasets = DatasetRegistry::load(&self.dataset_registry_path())?;
+
         Ok(())
     }
 }
@@ -0,0 +1,317 @@
+use std::{
+    collections::HashSet,
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Result};
+use jiff::Zoned;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    fastx::{self, FastxReader, FastxRecord},
+    seed_as_string, DatasetKind, DatasetRegistry,
+};
+use crate::{
+    cli::{DeriveQueriesArgs, QueryDerivationArgs},
+    config::BenchmarkSuiteConfig,
+    readmappers::Queries,
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DerivedQueries {
+    pub name: String,
+    pub source: Queries,
+    pub path: PathBuf,
+    pub num_reads: usize,
+    pub created: String,
+    pub derivation: QueryDerivation,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum QueryDerivation {
+    RandomSubsample {
+        num_reads: usize,
+        fraction: Option<f64>,
+        #[serde(with = "seed_as_string")]
+        seed: u64,
+    },
+    ReadIds {
+        read_id_file: PathBuf,
+    },
+    LengthWindow {
+        min_length: Option<usize>,
+        max_length: Option<usize>,
+    },
+    // the reads that only one of the compared read mappers mapped
+    OnlyMappedBy {
+        mapper: String,
+        mapped_reads_path: PathBuf,
+    },
+}
+
+pub fn derive_queries(
+    args: &DeriveQueriesArgs,
+    suite_config: &mut BenchmarkSuiteConfig,
+) -> Result<()> {
+    suite_config
+        .datasets
+        .check_new_name(&args.name, DatasetKind::DerivedQueries)?;
+
+    let source_path = args.source.path(suite_config)?.to_owned();
+    let format = FastxReader::from_path(&source_path)?.format();
+
+    let mut output_path = suite_config.derived_queries_folder();
+    output_path.push(&args.name);
+    output_path.set_extension(format.file_extension());
+
+    let (derivation, num_reads) = match &args.derivation {
+        QueryDerivationArgs::Subsample {
+            num_reads,
+            fraction,
+            seed,
+        } => {
+            let num_source_reads = count_reads(&source_path)?;
+            let num_reads = match (num_reads, fraction) {
+                (Some(num_reads), _) => *num_reads,
+                (None, Some(fraction)) => {
+                    if !(*fraction > 0.0 && *fraction <= 1.0) {
+                        bail!("subsample fraction must be in (0,1], but is {fraction}");
+                    }
+                    ((num_source_reads as f64 * fraction).ceil() as usize).max(1)
+                }
+                (None, None) => bail!("either the number of reads or a fraction is needed"),
+            };
+
+            let selected = sample_read_indices(num_source_reads, num_reads, *seed);
+            let written = write_filtered(&source_path, &output_path, |index, _| {
+                selected.contains(&index)
+            })?;
+
+            (
+                QueryDerivation::RandomSubsample {
+                    num_reads,
+                    fraction: *fraction,
+                    seed: *seed,
+                },
+                written,
+            )
+        }
+        QueryDerivationArgs::ReadIds { read_id_file } => {
+            let read_ids = read_id_list(read_id_file)?;
+            let written = write_filtered(&source_path, &output_path, |_, record| {
+                read_ids.contains(record.id())
+            })?;
+
+            if written != read_ids.len() {
+                println!(
+                    "Warning: only {written} of {} read ids were found in {}",
+                    read_ids.len(),
+                    args.source
+                );
+            }
+
+            (
+                QueryDerivation::ReadIds {
+                    read_id_file: fs::canonicalize(read_id_file)?,
+                },
+                written,
+            )
+        }
+        QueryDerivationArgs::LengthWindow {
+            min_length,
+            max_length,
+        } => {
+            let min = min_length.unwrap_or(0);
+            let max = max_length.unwrap_or(usize::MAX);
+
+            if min > max {
+                bail!("minimum length {min} is larger than maximum length {max}");
+            }
+
+            let written = write_filtered(&source_path, &output_path, |_, record| {
+                (min..=max).contains(&record.seq.len())
+            })?;
+
+            (
+                QueryDerivation::LengthWindow {
+                    min_length: *min_length,
+                    max_length: *max_length,
+                },
+                written,
+            )
+        }
+    };
+
+    if num_reads == 0 {
+        bail!("the derived query dataset {} would be empty", args.name);
+    }
+
+    suite_config
+        .datasets
+        .register_derived_queries(DerivedQueries {
+            name: args.name.clone(),
+            source: args.source.clone(),
+            path: output_path.clone(),
+            num_reads,
+            created: Zoned::now().to_string(),
+            derivation,
+        });
+    suite_config.save_dataset_registry()?;
+
+    println!(
+        "- Derived {num_reads} reads from {} into {}, use it with --queries {}",
+        args.source,
+        output_path.display(),
+        args.name
+    );
+
+    Ok(())
+}
+
+// writes the given reads of the source into the output folder and registers them, such that the
+// reads can be used like any other query dataset. The file is named like the dataset.
+pub fn derive_only_mapped_by_queries(
+    name: &str,
+    source: &Queries,
+    read_ids: &HashSet<&str>,
+    mapper: &str,
+    mapped_reads_path: &Path,
+    output_folder: &Path,
+    suite_config: &BenchmarkSuiteConfig,
+) -> Result<PathBuf> {
+    // the benchmarks only get a shared config, so the registry is updated on disk
+    let mut datasets = DatasetRegistry::load(&suite_config.dataset_registry_path())?;
+    datasets.check_new_name(name, DatasetKind::DerivedQueries)?;
+
+    let source_path = source.path(suite_config)?.to_owned();
+    let format = FastxReader::from_path(&source_path)?.format();
+
+    let mut output_path = output_folder.to_owned();
+    output_path.push(name);
+    output_path.set_extension(format.file_extension());
+
+    let num_reads = write_filtered(&source_path, &output_path, |_, record| {
+        read_ids.contains(record.id())
+    })?;
+
+    if num_reads == 0 {
+        bail!("the derived query dataset {name} would be empty");
+    }
+
+    if num_reads != read_ids.len() {
+        println!(
+            "Warning: only {num_reads} of {} read ids were found in {source}",
+            read_ids.len(),
+        );
+    }
+
+    datasets.register_derived_queries(DerivedQueries {
+        name: name.to_owned(),
+        source: source.clone(),
+        path: fs::canonicalize(&output_path)?,
+        num_reads,
+        created: Zoned::now().to_string(),
+        derivation: QueryDerivation::OnlyMappedBy {
+            mapper: mapper.to_owned(),
+            mapped_reads_path: fs::canonicalize(mapped_reads_path)?,
+        },
+    });
+    datasets.save(&suite_config.dataset_registry_path())?;
+
+    Ok(output_path)
+}
+
+fn count_reads(path: &Path) -> Result<usize> {
+    let mut count = 0;
+    for record in FastxReader::from_path(path)? {
+        record?;
+        count += 1;
+    }
+
+    Ok(count)
+}
+
+// reservoir sampling, such that the result only depends on the seed and the number of reads
+fn sample_read_indices(num_source_reads: usize, num_reads: usize, seed: u64) -> HashSet<usize> {
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    let mut reservoir: Vec<_> = (0..num_reads.min(num_source_reads)).collect();
+
+    for index in reservoir.len()..num_source_reads {
+        let replace_index = rng.gen_range(0..=index);
+        if replace_index < reservoir.len() {
+            reservoir[replace_index] = index;
+        }
+    }
+
+    reservoir.into_iter().collect()
+}
+
+fn read_id_list(path: &Path) -> Result<HashSet<String>> {
+    Ok(fs::read_to_string(path)?
+        .lines()
+        .map(|line| line.trim().trim_start_matches(['@', '>']))
+        .filter(|id| !id.is_empty())
+        .map(ToOwned::to_owned)
+        .collect())
+}
+
+// writes all records of the source for which the predicate holds, keeping their order
+fn write_filtered(
+    source_path: &Path,
+    output_path: &Path,
+    mut predicate: impl FnMut(usize, &FastxRecord) -> bool,
+) -> Result<usize> {
+    let mut writer = fastx::create_writer(output_path)?;
+    let mut num_written = 0;
+
+    for (index, record) in FastxReader::from_path(source_path)?.enumerate() {
+        let record = record?;
+
+        if predicate(index, &record) {
+            record.write_to(&mut writer)?;
+            num_written += 1;
+        }
+    }
+
+    writer.flush()?;
+
+    Ok(num_written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn large_seeds_survive_the_registry() {
+        let derivation = QueryDerivation::RandomSubsample {
+            num_reads: 10,
+            fraction: None,
+            seed: u64::MAX,
+        };
+
+        let serialized = toml::to_string(&derivation).unwrap();
+        let QueryDerivation::RandomSubsample { seed, .. } = toml::from_str(&serialized).unwrap()
+        else {
+            panic!("wrong derivation kind");
+        };
+
+        assert_eq!(seed, u64::MAX);
+    }
+
+    #[test]
+    fn integer_seeds_can_still_be_read() {
+        let derivation: QueryDerivation =
+            toml::from_str("kind = \"random_subsample\"\nnum_reads = 10\nseed = 42\n").unwrap();
+
+        assert!(matches!(
+            derivation,
+            QueryDerivation::RandomSubsample { seed: 42, .. }
+        ));
+    }
+}
@@ -0,0 +1,179 @@
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::Path,
+};
+
+use anyhow::{bail, Context, Result};
+use rust_htslib::bgzf;
+use strum::Display;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Display)]
+#[strum(serialize_all = "snake_case")]
+pub enum FastxFormat {
+    Fasta,
+    Fastq,
+}
+
+impl FastxFormat {
+    pub fn file_extension(&self) -> &'static str {
+        match self {
+            FastxFormat::Fasta => "fasta",
+            FastxFormat::Fastq => "fastq",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FastxRecord {
+    // the full header line without the leading '>' or '@'
+    pub header: String,
+    pub seq: Vec<u8>,
+    pub qual: Option<Vec<u8>>,
+}
+
+impl FastxRecord {
+    pub fn id(&self) -> &str {
+        self.header.split_whitespace().next().unwrap_or("")
+    }
+
+    pub fn write_to(&self, writer: &mut impl Write) -> Result<()> {
+        match &self.qual {
+            Some(qual) => {
+                writeln!(writer, "@{}", self.header)?;
+                writer.write_all(&self.seq)?;
+                writer.write_all(b"\n+\n")?;
+                writer.write_all(qual)?;
+                writer.write_all(b"\n")?;
+            }
+            None => {
+                writeln!(writer, ">{}", self.header)?;
+                writer.write_all(&self.seq)?;
+                writer.write_all(b"\n")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// reads plain, gzip and bgzip compressed FASTA/FASTQ files (multi-line FASTA is supported)
+pub struct FastxReader {
+    reader: Box<dyn BufRead>,
+    format: FastxFormat,
+    next_header: Option<String>,
+    line: String,
+}
+
+impl FastxReader {
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let bgzf_reader = bgzf::Reader::from_path(path)
+            .with_context(|| format!("opening sequence file {}", path.display()))?;
+        let mut reader: Box<dyn BufRead> = Box::new(BufReader::new(bgzf_reader));
+
+        let mut first_line = String::new();
+        reader.read_line(&mut first_line)?;
+
+        let format = match first_line.as_bytes().first() {
+            Some(b'>') => FastxFormat::Fasta,
+            Some(b'@') => FastxFormat::Fastq,
+            _ => bail!(
+                "{} does not look like a FASTA or FASTQ file",
+                path.display()
+            ),
+        };
+
+        Ok(Self {
+            reader,
+            format,
+            next_header: Some(first_line[1..].trim_end().to_owned()),
+            line: String::new(),
+        })
+    }
+
+    pub fn format(&self) -> FastxFormat {
+        self.format
+    }
+
+    fn read_line(&mut self) -> Result<bool> {
+        self.line.clear();
+        Ok(self.reader.read_line(&mut self.line)? != 0)
+    }
+
+    fn read_fasta_record(&mut self, header: String) -> Result<FastxRecord> {
+        let mut seq = Vec::new();
+
+        while self.read_line()? {
+            if let Some(next_header) = self.line.strip_prefix('>') {
+                self.next_header = Some(next_header.trim_end().to_owned());
+                break;
+            }
+
+            seq.extend_from_slice(self.line.trim_end().as_bytes());
+        }
+
+        Ok(FastxRecord {
+            header,
+            seq,
+            qual: None,
+        })
+    }
+
+    fn read_fastq_record(&mut self, header: String) -> Result<FastxRecord> {
+        if !self.read_line()? {
+            bail!("truncated FASTQ record {header}");
+        }
+        let seq = self.line.trim_end().as_bytes().to_vec();
+
+        if !self.read_line()? || !self.line.starts_with('+') {
+            bail!("FASTQ record {header} is missing the '+' separator line");
+        }
+
+        if !self.read_line()? {
+            bail!("FASTQ record {header} is missing its quality line");
+        }
+        let qual = self.line.trim_end().as_bytes().to_vec();
+
+        if qual.len() != seq.len() {
+            bail!("FASTQ record {header} has different sequence and quality lengths");
+        }
+
+        if self.read_line()? {
+            match self.line.strip_prefix('@') {
+                Some(next_header) => self.next_header = Some(next_header.trim_end().to_owned()),
+                None if self.line.trim().is_empty() => {}
+                None => bail!("expected a FASTQ header after record {header}"),
+            }
+        }
+
+        Ok(FastxRecord {
+            header,
+            seq,
+            qual: Some(qual),
+        })
+    }
+}
+
+impl Iterator for FastxReader {
+    type Item = Result<FastxRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let header = self.next_header.take()?;
+
+        Some(match self.format {
+            FastxFormat::Fasta => self.read_fasta_record(header),
+            FastxFormat::Fastq => self.read_fastq_record(header),
+        })
+    }
+}
+
+pub fn create_writer(path: &Path) -> Result<BufWriter<File>> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let file =
+        File::create(path).with_context(|| format!("creating sequence file {}", path.display()))?;
+
+    Ok(BufWriter::new(file))
+}
@@ -0,0 +1,165 @@
+pub mod derived;
+pub mod fastx;
+pub mod simulated;
+pub mod simulator;
+
+use std::{fs, path::Path};
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::readmappers::{Queries, Reference};
+use derived::DerivedQueries;
+use simulated::SimulatedDataset;
+
+// TOML integers are signed 64 bit, so large seeds are stored as strings. Registries and config
+// files with integer seeds can still be read.
+pub(super) mod seed_as_string {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(seed: &u64, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&seed.to_string())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Seed {
+            Integer(u64),
+            String(String),
+        }
+
+        match Seed::deserialize(deserializer)? {
+            Seed::Integer(seed) => Ok(seed),
+            Seed::String(seed) => seed.parse().map_err(serde::de::Error::custom),
+        }
+    }
+
+    // for seeds that are chosen randomly if they are not given
+    pub mod optional {
+        use serde::{Deserialize, Deserializer, Serializer};
+
+        pub fn serialize<S: Serializer>(
+            seed: &Option<u64>,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            match seed {
+                Some(seed) => super::serialize(seed, serializer),
+                None => serializer.serialize_none(),
+            }
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<Option<u64>, D::Error> {
+            #[derive(Deserialize)]
+            struct Seed(#[serde(with = "super")] u64);
+
+            Ok(Option::<Seed>::deserialize(deserializer)?.map(|Seed(seed)| seed))
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DatasetKind {
+    DerivedQueries,
+    Simulated,
+}
+
+// keeps track of all datasets that were created by this program, such that they can be
+// used like the datasets from the config file. Stored as TOML in the datasets folder.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct DatasetRegistry {
+    #[serde(default)]
+    pub derived_queries: Vec<DerivedQueries>,
+    #[serde(default)]
+    pub simulated_datasets: Vec<SimulatedDataset>,
+}
+
+impl DatasetRegistry {
+    pub fn load(registry_path: &Path) -> Result<Self> {
+        if !registry_path.exists() {
+            return Ok(Self::default());
+        }
+
+        let registry_str = fs::read_to_string(registry_path)?;
+
+        toml::from_str(&registry_str)
+            .with_context(|| format!("parsing dataset registry {}", registry_path.display()))
+    }
+
+    pub fn save(&self, registry_path: &Path) -> Result<()> {
+        fs::write(registry_path, toml::to_string(self)?)?;
+
+        Ok(())
+    }
+
+    // datasets of the same kind may be replaced, but names must be unique across kinds
+    pub fn check_new_name(&self, name: &str, kind: DatasetKind) -> Result<()> {
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            bail!("dataset names may only contain ASCII letters, digits and '_'");
+        }
+
+        let is_builtin = Queries::BUILTIN.iter().any(|q| q.to_string() == name)
+            || Reference::BUILTIN.iter().any(|r| r.to_string() == name);
+        if is_builtin {
+            bail!("{name} is already the name of a configured dataset");
+        }
+
+        let is_derived_queries = self.derived_queries.iter().any(|d| d.name == name);
+        let is_simulated = self.simulated_datasets.iter().any(|s| s.name == name);
+        if (is_derived_queries && kind != DatasetKind::DerivedQueries)
+            || (is_simulated && kind != DatasetKind::Simulated)
+        {
+            bail!("{name} is already registered as a different kind of dataset");
+        }
+
+        Ok(())
+    }
+
+    pub fn queries_path(&self, name: &str) -> Result<&Path> {
+        if let Some(derived) = self.derived_queries.iter().find(|d| d.name == name) {
+            return Ok(&derived.path);
+        }
+
+        Ok(&self
+            .simulated_dataset(name)
+            .with_context(|| format!("no query dataset named {name} is configured or registered"))?
+            .queries_path)
+    }
+
+    pub fn reference_path(&self, name: &str) -> Result<&Path> {
+        Ok(&self
+            .simulated_dataset(name)
+            .with_context(|| format!("no reference named {name} is configured or registered"))?
+            .reference_path)
+    }
+
+    pub fn derived_queries(&self, name: &str) -> Result<&DerivedQueries> {
+        self.derived_queries
+            .iter()
+            .find(|derived| derived.name == name)
+            .with_context(|| format!("no derived query dataset named {name} is registered"))
+    }
+
+    pub fn simulated_dataset(&self, name: &str) -> Result<&SimulatedDataset> {
+        self.simulated_datasets
+            .iter()
+            .find(|simulated| simulated.name == name)
+            .with_context(|| format!("no simulated dataset named {name} is registered"))
+    }
+
+    // replaces an earlier dataset of the same name
+    pub fn register_derived_queries(&mut self, derived_queries: DerivedQueries) {
+        self.derived_queries
+            .retain(|derived| derived.name != derived_queries.name);
+        self.derived_queries.push(derived_queries);
+    }
+
+    // replaces an earlier dataset of the same name
+    pub fn register_simulated_dataset(&mut self, simulated_dataset: SimulatedDataset) {
+        self.simulated_datasets
+            .retain(|simulated| simulated.name != simulated_dataset.name);
+        self.simulated_datasets.push(simulated_dataset);
+    }
+}
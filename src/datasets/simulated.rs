@@ -0,0 +1,360 @@
+use std::{fs, path::PathBuf, process::Command};
+
+use anyhow::{bail, Context, Result};
+use jiff::Zoned;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use strum::Display;
+
+use super::{seed_as_string, simulator, DatasetKind};
+use crate::{
+    cli::SimulateDatasetArgs,
+    config::BenchmarkSuiteConfig,
+    readmappers::{self, Reference},
+};
+
+// parameters of a simulated dataset, given in the [simulated_datasets.<name>] tables of the config file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SimulationParameters {
+    #[serde(default)]
+    pub simulator: Simulator,
+    // if no seed is given, a random one is chosen and recorded in the dataset registry
+    #[serde(
+        default,
+        with = "seed_as_string::optional",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub seed: Option<u64>,
+    // either a random reference of the given length is generated
+    // or the reads are sampled from an existing reference
+    pub reference_length: Option<usize>,
+    pub source_reference: Option<Reference>,
+    pub num_reads: usize,
+    pub read_lengths: ReadLengthDistribution,
+    pub error_rate: f64,
+    pub error_profile: ErrorProfile,
+    #[serde(default)]
+    pub truth: TruthEncoding,
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize, Display)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum Simulator {
+    #[default]
+    Native,
+    // the simulated_dataset_binary from the config file
+    External,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ReadLengthDistribution {
+    Fixed { length: usize },
+    Uniform { min: usize, max: usize },
+    Normal { mean: f64, std_dev: f64, min: usize },
+    // fitted from the query_lengths histogram of the stats file of a floxer run on real data
+    FloxerStats { stats_file: PathBuf },
+}
+
+// relative frequencies of the error types, don't need to sum up to 1
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ErrorProfile {
+    pub substitutions: f64,
+    pub insertions: f64,
+    pub deletions: f64,
+    #[serde(default)]
+    pub homopolymer_indels: Option<HomopolymerIndels>,
+}
+
+// nanopore-like errors: in homopolymer runs of at least min_run_length bases, insertions and
+// deletions are rate_multiplier times as likely and insertions repeat the homopolymer base
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HomopolymerIndels {
+    pub min_run_length: usize,
+    pub rate_multiplier: f64,
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TruthEncoding {
+    // the origin of a read is encoded in its name
+    #[default]
+    ReadNames,
+    // the origin of the reads is written to truth.tsv next to the reads
+    Sidecar,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SimulatedDataset {
+    pub name: String,
+    pub reference_path: PathBuf,
+    pub queries_path: PathBuf,
+    pub truth_path: Option<PathBuf>,
+    pub generator: String,
+    pub created: String,
+    pub parameters: SimulationParameters,
+}
+
+impl SimulationParameters {
+    fn validate(&self) -> Result<()> {
+        if !(self.error_rate >= 0.0 && self.error_rate < 1.0) {
+            bail!(
+                "simulation error rate must be in [0,1), but is {}",
+                self.error_rate
+            );
+        }
+
+        let profile = &self.error_profile;
+        let ratios = [profile.substitutions, profile.insertions, profile.deletions];
+        if ratios
+            .iter()
+            .any(|ratio| !(ratio.is_finite() && *ratio >= 0.0))
+            || ratios.iter().sum::<f64>() <= 0.0
+        {
+            bail!("error profile ratios must be non-negative and not all zero");
+        }
+
+        if let Some(homopolymer_indels) = &profile.homopolymer_indels {
+            if homopolymer_indels.min_run_length < 2
+                || !(homopolymer_indels.rate_multiplier.is_finite()
+                    && homopolymer_indels.rate_multiplier >= 0.0)
+            {
+                bail!("homopolymer runs need at least 2 bases and a non-negative rate multiplier");
+            }
+        }
+
+        if self.num_reads == 0 {
+            bail!("simulated datasets need at least one read");
+        }
+
+        if self.reference_length.is_some() == self.source_reference.is_some() {
+            bail!("simulated datasets need either a reference_length or a source_reference");
+        }
+
+        if self.reference_length == Some(0) {
+            bail!("the simulated reference must not be empty");
+        }
+
+        if self.simulator == Simulator::External
+            && (self.source_reference.is_some()
+                || profile.homopolymer_indels.is_some()
+                || self.truth != TruthEncoding::ReadNames
+                || matches!(
+                    self.read_lengths,
+                    ReadLengthDistribution::FloxerStats { .. }
+                ))
+        {
+            bail!(
+                "the external simulator only supports random references, \
+                fixed, uniform and normal read lengths and no homopolymer indels"
+            );
+        }
+
+        // reads from the long tail of the normal distribution are cut by the generator
+        let (min_read_length, max_read_length) = match &self.read_lengths {
+            ReadLengthDistribution::Fixed { length } => (*length, *length),
+            ReadLengthDistribution::Uniform { min, max } => {
+                if min > max {
+                    bail!("minimum read length {min} is larger than maximum read length {max}");
+                }
+                (*min, *max)
+            }
+            ReadLengthDistribution::Normal { mean, std_dev, min } => {
+                if !(*mean > 0.0 && *std_dev >= 0.0) {
+                    bail!("invalid normal read length distribution");
+                }
+                (*min, *mean as usize)
+            }
+            // checked when the histogram is loaded
+            ReadLengthDistribution::FloxerStats { .. } => (1, 1),
+        };
+
+        if min_read_length == 0 {
+            bail!("read lengths must be positive");
+        }
+
+        if let Some(reference_length) = self.reference_length {
+            if max_read_length > reference_length {
+                bail!("read lengths must not exceed the reference length");
+            }
+        }
+
+        Ok(())
+    }
+
+    fn add_generator_args(&self, command: &mut Command, seed: u64) -> Result<()> {
+        let Some(reference_length) = self.reference_length else {
+            bail!("the external simulator needs a reference_length");
+        };
+
+        command
+            .arg("--seed")
+            .arg(seed.to_string())
+            .arg("--reference-length")
+            .arg(reference_length.to_string())
+            .arg("--num-reads")
+            .arg(self.num_reads.to_string())
+            .arg("--error-rate")
+            .arg(self.error_rate.to_string())
+            .arg("--substitution-ratio")
+            .arg(self.error_profile.substitutions.to_string())
+            .arg("--insertion-ratio")
+            .arg(self.error_profile.insertions.to_string())
+            .arg("--deletion-ratio")
+            .arg(self.error_profile.deletions.to_string());
+
+        match &self.read_lengths {
+            ReadLengthDistribution::Fixed { length } => {
+                command
+                    .args(["--min-read-length", &length.to_string()])
+                    .args(["--max-read-length", &length.to_string()]);
+            }
+            ReadLengthDistribution::Uniform { min, max } => {
+                command
+                    .args(["--min-read-length", &min.to_string()])
+                    .args(["--max-read-length", &max.to_string()]);
+            }
+            ReadLengthDistribution::Normal { mean, std_dev, min } => {
+                command
+                    .args(["--mean-read-length", &mean.to_string()])
+                    .args(["--read-length-std-dev", &std_dev.to_string()])
+                    .args(["--min-read-length", &min.to_string()]);
+            }
+            ReadLengthDistribution::FloxerStats { .. } => {
+                bail!("the external simulator does not support fitted read lengths")
+            }
+        }
+
+        Ok(())
+    }
+}
+
+pub fn simulate_dataset(
+    args: &SimulateDatasetArgs,
+    suite_config: &mut BenchmarkSuiteConfig,
+) -> Result<()> {
+    // rebuilding uses the recorded parameters (including the seed) instead of the config file
+    let parameters = if args.rebuild {
+        suite_config
+            .datasets
+            .simulated_dataset(&args.name)?
+            .parameters
+            .clone()
+    } else {
+        suite_config
+            .simulated_datasets
+            .get(&args.name)
+            .with_context(|| {
+                format!(
+                    "no parameters for the simulated dataset {} in the config file",
+                    args.name
+                )
+            })?
+            .clone()
+    };
+
+    parameters.validate()?;
+    suite_config
+        .datasets
+        .check_new_name(&args.name, DatasetKind::Simulated)?;
+
+    let seed = parameters.seed.unwrap_or_else(|| rand::thread_rng().gen());
+
+    let mut dataset_folder = suite_config.simulated_datasets_folder();
+    dataset_folder.push(&args.name);
+    fs::create_dir_all(&dataset_folder)?;
+
+    let reference_path = match &parameters.source_reference {
+        Some(source_reference) => {
+            if *source_reference == Reference::Registered(args.name.clone()) {
+                bail!("a simulated dataset can't use its own reference as source");
+            }
+            fs::canonicalize(source_reference.path(suite_config)?)?
+        }
+        None => {
+            let mut reference_path = dataset_folder.clone();
+            reference_path.push("reference.fasta");
+            reference_path
+        }
+    };
+
+    let mut queries_path = dataset_folder.clone();
+    queries_path.push("queries.fastq");
+
+    let truth_path = match parameters.truth {
+        TruthEncoding::ReadNames => None,
+        TruthEncoding::Sidecar => {
+            let mut truth_path = dataset_folder;
+            truth_path.push("truth.tsv");
+            Some(truth_path)
+        }
+    };
+
+    println!(
+        "- Generating the simulated dataset {} (seed {seed}, {} simulator)",
+        args.name, parameters.simulator
+    );
+
+    let generator = match parameters.simulator {
+        Simulator::Native => {
+            simulator::simulate(
+                &parameters,
+                seed,
+                &reference_path,
+                &queries_path,
+                truth_path.as_deref(),
+            )?;
+
+            format!(
+                "{} {} (native)",
+                env!("CARGO_PKG_NAME"),
+                env!("CARGO_PKG_VERSION")
+            )
+        }
+        Simulator::External => {
+            let mut command = Command::new(&suite_config.simulated_dataset_binary);
+            command
+                .arg("generate")
+                .arg("--reference-output")
+                .arg(&reference_path)
+                .arg("--queries-output")
+                .arg(&queries_path);
+            parameters.add_generator_args(&mut command, seed)?;
+
+            let output = command.output()?;
+
+            if !output.status.success() {
+                bail!(
+                    "Failed to generate simulated data set with stderr: {}",
+                    String::from_utf8_lossy(&output.stderr)
+                );
+            }
+
+            suite_config.simulated_dataset_binary.display().to_string()
+        }
+    };
+
+    // indices of an earlier dataset with the same name would not match the new reference
+    readmappers::remove_stored_indices(&Reference::Registered(args.name.clone()), suite_config)?;
+
+    suite_config
+        .datasets
+        .register_simulated_dataset(SimulatedDataset {
+            name: args.name.clone(),
+            reference_path,
+            queries_path,
+            truth_path,
+            generator,
+            created: Zoned::now().to_string(),
+            parameters: SimulationParameters {
+                seed: Some(seed),
+                ..parameters
+            },
+        });
+    suite_config.save_dataset_registry()?;
+
+    println!("- Use it with --reference {0} --queries {0}", args.name);
+
+    Ok(())
+}
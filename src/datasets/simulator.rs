@@ -0,0 +1,500 @@
+use std::{collections::HashMap, fs, io::Write, path::Path};
+
+use anyhow::{bail, Context, Result};
+use rand::{
+    distributions::{Distribution, WeightedIndex},
+    Rng, SeedableRng,
+};
+use rand_chacha::ChaCha8Rng;
+use rand_distr::Normal;
+use serde::Deserialize;
+
+use super::{
+    fastx::{self, FastxReader, FastxRecord},
+    simulated::{
+        ErrorProfile, HomopolymerIndels, ReadLengthDistribution, SimulatedDataset,
+        SimulationParameters, TruthEncoding,
+    },
+};
+use crate::readmappers::floxer::HistogramData;
+
+const BASES: [u8; 4] = *b"ACGT";
+
+// reads that would overlap a non-ACGT base or a contig end are sampled again
+const MAX_SAMPLING_ATTEMPTS: usize = 10_000;
+
+const FASTA_LINE_WIDTH: usize = 80;
+
+struct Contig {
+    name: String,
+    seq: Vec<u8>,
+}
+
+// where a simulated read comes from. Positions are 0-based, the end is exclusive
+// and both refer to the forward strand of the reference.
+#[derive(Debug, Clone)]
+pub struct ReadTruth {
+    pub contig: String,
+    pub start: usize,
+    pub end: usize,
+    pub reverse_strand: bool,
+    pub num_errors: usize,
+}
+
+impl ReadTruth {
+    // relative to the length of the reference region
+    pub fn error_rate(&self) -> f64 {
+        self.num_errors as f64 / (self.end - self.start) as f64
+    }
+
+    fn strand_char(&self) -> char {
+        if self.reverse_strand {
+            '-'
+        } else {
+            '+'
+        }
+    }
+
+    // sim<index>_<strand>_<start>_<end>_<num errors>_<contig>,
+    // the contig comes last, because its name may contain '_'
+    fn encode_in_read_name(&self, index: usize) -> String {
+        format!(
+            "sim{index}_{}_{}_{}_{}_{}",
+            self.strand_char(),
+            self.start,
+            self.end,
+            self.num_errors,
+            self.contig
+        )
+    }
+
+    fn parse_read_name(read_name: &str) -> Result<Self> {
+        let fields: Vec<_> = read_name.splitn(6, '_').collect();
+        let [_, strand, start, end, num_errors, contig] = fields[..] else {
+            bail!("read name {read_name} does not contain the origin of a simulated read");
+        };
+
+        Self::from_fields(contig, start, end, strand, num_errors)
+            .with_context(|| format!("parsing simulated read name {read_name}"))
+    }
+
+    fn write_sidecar_line(&self, writer: &mut impl Write, read_id: &str) -> Result<()> {
+        writeln!(
+            writer,
+            "{read_id}\t{}\t{}\t{}\t{}\t{}",
+            self.contig,
+            self.start,
+            self.end,
+            self.strand_char(),
+            self.num_errors
+        )?;
+
+        Ok(())
+    }
+
+    fn parse_sidecar_line(line: &str) -> Result<(String, Self)> {
+        let fields: Vec<_> = line.split('\t').collect();
+        let [read_id, contig, start, end, strand, num_errors] = fields[..] else {
+            bail!("truth line {line} does not have 6 fields");
+        };
+
+        let truth = Self::from_fields(contig, start, end, strand, num_errors)
+            .with_context(|| format!("parsing truth line {line}"))?;
+
+        Ok((read_id.to_owned(), truth))
+    }
+
+    fn from_fields(
+        contig: &str,
+        start: &str,
+        end: &str,
+        strand: &str,
+        num_errors: &str,
+    ) -> Result<Self> {
+        let reverse_strand = match strand {
+            "+" => false,
+            "-" => true,
+            _ => bail!("invalid strand {strand}"),
+        };
+
+        Ok(Self {
+            contig: contig.to_owned(),
+            start: start.parse()?,
+            end: end.parse()?,
+            reverse_strand,
+            num_errors: num_errors.parse()?,
+        })
+    }
+}
+
+// the truth of all reads of a dataset created by the native simulator, by read id
+pub fn load_truth(dataset: &SimulatedDataset) -> Result<HashMap<String, ReadTruth>> {
+    match &dataset.truth_path {
+        Some(truth_path) => fs::read_to_string(truth_path)
+            .with_context(|| format!("reading truth file {}", truth_path.display()))?
+            .lines()
+            .skip(1)
+            .filter(|line| !line.trim().is_empty())
+            .map(ReadTruth::parse_sidecar_line)
+            .collect(),
+        None => FastxReader::from_path(&dataset.queries_path)?
+            .map(|record| {
+                let record = record?;
+                let read_id = record.id().to_owned();
+                let truth = ReadTruth::parse_read_name(&read_id)?;
+
+                Ok((read_id, truth))
+            })
+            .collect(),
+    }
+}
+
+// Samples reads from the reference at reference_path. If the parameters contain a reference
+// length, a random reference is generated and written to reference_path first.
+pub fn simulate(
+    parameters: &SimulationParameters,
+    seed: u64,
+    reference_path: &Path,
+    queries_path: &Path,
+    truth_path: Option<&Path>,
+) -> Result<()> {
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+
+    let contigs = match parameters.reference_length {
+        Some(reference_length) => {
+            let contig = Contig {
+                name: String::from("simulated"),
+                seq: (0..reference_length)
+                    .map(|_| BASES[rng.gen_range(0..4)])
+                    .collect(),
+            };
+            write_reference(&contig, reference_path)?;
+            vec![contig]
+        }
+        None => read_contigs(reference_path)?,
+    };
+
+    // start offsets of the contigs in the concatenated reference
+    let contig_offsets: Vec<_> = contigs
+        .iter()
+        .scan(0, |offset, contig| {
+            let contig_offset = *offset;
+            *offset += contig.seq.len();
+            Some(contig_offset)
+        })
+        .collect();
+    let total_length: usize = contigs.iter().map(|contig| contig.seq.len()).sum();
+
+    if total_length == 0 {
+        bail!("reference {} is empty", reference_path.display());
+    }
+
+    let length_sampler = LengthSampler::new(&parameters.read_lengths)?;
+    let error_model = ErrorModel::new(parameters.error_rate, &parameters.error_profile);
+    let quality_char = quality_char(parameters.error_rate);
+
+    let mut queries_writer = fastx::create_writer(queries_path)?;
+    let mut truth_writer = truth_path.map(fastx::create_writer).transpose()?;
+
+    if let Some(truth_writer) = &mut truth_writer {
+        writeln!(
+            truth_writer,
+            "read_id\tcontig\tstart\tend\tstrand\tnum_errors"
+        )?;
+    }
+
+    for index in 0..parameters.num_reads {
+        let read_length = length_sampler.sample(&mut rng);
+
+        let mut attempt = 0;
+        let (contig, start) = loop {
+            attempt += 1;
+            if attempt > MAX_SAMPLING_ATTEMPTS {
+                bail!("no region of length {read_length} without ambiguous bases was found");
+            }
+
+            let global_start = rng.gen_range(0..total_length);
+            let contig_index = contig_offsets.partition_point(|offset| *offset <= global_start) - 1;
+            let contig = &contigs[contig_index];
+            let start = global_start - contig_offsets[contig_index];
+
+            let Some(template) = contig.seq.get(start..start + read_length) else {
+                continue;
+            };
+
+            if template.iter().all(|base| BASES.contains(base)) {
+                break (contig, start);
+            }
+        };
+
+        let end = start + read_length;
+        let reverse_strand = rng.gen_bool(0.5);
+
+        let template = if reverse_strand {
+            reverse_complement(&contig.seq[start..end])
+        } else {
+            contig.seq[start..end].to_vec()
+        };
+
+        let (seq, num_errors) = error_model.apply(&template, &mut rng);
+
+        let truth = ReadTruth {
+            contig: contig.name.clone(),
+            start,
+            end,
+            reverse_strand,
+            num_errors,
+        };
+
+        let header = match parameters.truth {
+            TruthEncoding::ReadNames => truth.encode_in_read_name(index),
+            TruthEncoding::Sidecar => format!("sim{index}"),
+        };
+
+        if let Some(truth_writer) = &mut truth_writer {
+            truth.write_sidecar_line(truth_writer, &header)?;
+        }
+
+        let qual = vec![quality_char; seq.len()];
+        FastxRecord {
+            header,
+            seq,
+            qual: Some(qual),
+        }
+        .write_to(&mut queries_writer)?;
+    }
+
+    queries_writer.flush()?;
+    if let Some(truth_writer) = &mut truth_writer {
+        truth_writer.flush()?;
+    }
+
+    Ok(())
+}
+
+fn read_contigs(reference_path: &Path) -> Result<Vec<Contig>> {
+    FastxReader::from_path(reference_path)?
+        .map(|record| {
+            let mut record = record?;
+            record.seq.make_ascii_uppercase();
+
+            Ok(Contig {
+                name: record.id().to_owned(),
+                seq: record.seq,
+            })
+        })
+        .collect()
+}
+
+fn write_reference(contig: &Contig, reference_path: &Path) -> Result<()> {
+    let mut writer = fastx::create_writer(reference_path)?;
+
+    writeln!(writer, ">{}", contig.name)?;
+    for line in contig.seq.chunks(FASTA_LINE_WIDTH) {
+        writer.write_all(line)?;
+        writer.write_all(b"\n")?;
+    }
+
+    writer.flush()?;
+
+    Ok(())
+}
+
+pub fn reverse_complement(seq: &[u8]) -> Vec<u8> {
+    seq.iter()
+        .rev()
+        .map(|base| match base {
+            b'A' => b'T',
+            b'C' => b'G',
+            b'G' => b'C',
+            b'T' => b'A',
+            other => *other,
+        })
+        .collect()
+}
+
+// constant phred quality that matches the error rate
+fn quality_char(error_rate: f64) -> u8 {
+    let phred = if error_rate > 0.0 {
+        (-10.0 * error_rate.log10()).round().clamp(1.0, 60.0)
+    } else {
+        60.0
+    };
+
+    phred as u8 + 33
+}
+
+enum LengthSampler {
+    Fixed(usize),
+    Uniform(usize, usize),
+    Normal {
+        distribution: Normal<f64>,
+        min: usize,
+    },
+    Histogram {
+        // inclusive length ranges of the histogram buckets
+        buckets: Vec<(usize, usize)>,
+        bucket_distribution: WeightedIndex<usize>,
+    },
+}
+
+#[derive(Debug, Deserialize)]
+struct QueryLengthStats {
+    query_lengths: HistogramData,
+}
+
+impl LengthSampler {
+    fn new(read_lengths: &ReadLengthDistribution) -> Result<Self> {
+        Ok(match read_lengths {
+            ReadLengthDistribution::Fixed { length } => Self::Fixed(*length),
+            ReadLengthDistribution::Uniform { min, max } => Self::Uniform(*min, *max),
+            ReadLengthDistribution::Normal { mean, std_dev, min } => Self::Normal {
+                distribution: Normal::new(*mean, *std_dev)?,
+                min: *min,
+            },
+            ReadLengthDistribution::FloxerStats { stats_file } => {
+                let stats_str = fs::read_to_string(stats_file)
+                    .with_context(|| format!("reading floxer stats {}", stats_file.display()))?;
+                let stats: QueryLengthStats = toml::from_str(&stats_str)?;
+
+                Self::fit_histogram(&stats.query_lengths)?
+            }
+        })
+    }
+
+    // the lengths are uniformly distributed within each bucket. The last bucket and the
+    // first bucket are bounded by the maximum and minimum length, if they are known.
+    fn fit_histogram(histogram: &HistogramData) -> Result<Self> {
+        let Some(&last_threshold) = histogram.thresholds.last() else {
+            bail!("query length histogram has no thresholds");
+        };
+
+        let (min_length, max_length) = match &histogram.descriptive_stats {
+            Some(stats) => (stats.min_value.max(1), stats.max_value.max(1)),
+            None => (1, last_threshold * 2),
+        };
+
+        let buckets: Vec<_> = (0..histogram.occurrences.len())
+            .map(|index| {
+                let lower = match index {
+                    0 => min_length,
+                    _ => (histogram.thresholds[index - 1] + 1).max(min_length),
+                };
+                let upper = histogram
+                    .thresholds
+                    .get(index)
+                    .copied()
+                    .unwrap_or(max_length)
+                    .min(max_length);
+
+                (lower, upper.max(lower))
+            })
+            .collect();
+
+        let bucket_distribution = WeightedIndex::new(&histogram.occurrences)
+            .context("query length histogram is empty")?;
+
+        Ok(Self::Histogram {
+            buckets,
+            bucket_distribution,
+        })
+    }
+
+    fn sample(&self, rng: &mut impl Rng) -> usize {
+        match self {
+            Self::Fixed(length) => *length,
+            Self::Uniform(min, max) => rng.gen_range(*min..=*max),
+            Self::Normal { distribution, min } => {
+                (distribution.sample(rng).round() as usize).max(*min)
+            }
+            Self::Histogram {
+                buckets,
+                bucket_distribution,
+            } => {
+                let (lower, upper) = buckets[bucket_distribution.sample(rng)];
+                rng.gen_range(lower..=upper)
+            }
+        }
+    }
+}
+
+struct ErrorModel<'a> {
+    error_rate: f64,
+    substitutions: f64,
+    insertions: f64,
+    deletions: f64,
+    homopolymer_indels: Option<&'a HomopolymerIndels>,
+}
+
+impl<'a> ErrorModel<'a> {
+    fn new(error_rate: f64, profile: &'a ErrorProfile) -> Self {
+        Self {
+            error_rate,
+            substitutions: profile.substitutions,
+            insertions: profile.insertions,
+            deletions: profile.deletions,
+            homopolymer_indels: profile.homopolymer_indels.as_ref(),
+        }
+    }
+
+    // returns the read and the number of introduced errors. The number of errors is an upper
+    // bound for the edit distance, because adjacent errors might cancel each other out.
+    fn apply(&self, template: &[u8], rng: &mut impl Rng) -> (Vec<u8>, usize) {
+        let run_lengths = homopolymer_run_lengths(template);
+        let base_weight_sum = self.substitutions + self.insertions + self.deletions;
+
+        let mut read = Vec::with_capacity(template.len() + template.len() / 10);
+        let mut num_errors = 0;
+
+        for (&base, &run_length) in template.iter().zip(&run_lengths) {
+            let in_homopolymer = self
+                .homopolymer_indels
+                .filter(|homopolymer| run_length >= homopolymer.min_run_length);
+            let indel_multiplier =
+                in_homopolymer.map_or(1.0, |homopolymer| homopolymer.rate_multiplier);
+
+            let insertions = self.insertions * indel_multiplier;
+            let deletions = self.deletions * indel_multiplier;
+            let weight_sum = self.substitutions + insertions + deletions;
+
+            let error_probability = (self.error_rate * weight_sum / base_weight_sum).min(1.0);
+            if !rng.gen_bool(error_probability) {
+                read.push(base);
+                continue;
+            }
+
+            num_errors += 1;
+            let error_kind = rng.gen_range(0.0..weight_sum);
+
+            if error_kind < self.substitutions {
+                read.push(random_other_base(base, rng));
+            } else if error_kind < self.substitutions + insertions {
+                let inserted = match in_homopolymer {
+                    Some(_) => base,
+                    None => BASES[rng.gen_range(0..4)],
+                };
+                read.push(inserted);
+                read.push(base);
+            }
+            // otherwise, the base is deleted
+        }
+
+        (read, num_errors)
+    }
+}
+
+fn homopolymer_run_lengths(seq: &[u8]) -> Vec<usize> {
+    let mut run_lengths = Vec::with_capacity(seq.len());
+
+    for run in seq.chunk_by(|a, b| a == b) {
+        run_lengths.extend(std::iter::repeat_n(run.len(), run.len()));
+    }
+
+    run_lengths
+}
+
+fn random_other_base(base: u8, rng: &mut impl Rng) -> u8 {
+    let others: Vec<_> = BASES.iter().filter(|other| **other != base).collect();
+
+    *others[rng.gen_range(0..others.len())]
+}
@@ -0,0 +1,178 @@
+// simple banded edit distance computations for validating alignments, not meant to be fast
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AlignmentMode {
+    // the whole query against the whole reference
+    Global,
+    // the whole query against a prefix of the reference (the alignment may end anywhere)
+    FreeReferenceEnd,
+}
+
+// None if the edit distance is larger than max_distance. Bases are compared case-insensitively.
+pub fn banded_edit_distance(
+    query: &[u8],
+    reference: &[u8],
+    max_distance: usize,
+    mode: AlignmentMode,
+) -> Option<usize> {
+    const INF: usize = usize::MAX / 2;
+
+    let n = query.len();
+    let m = reference.len();
+
+    if mode == AlignmentMode::Global && n.abs_diff(m) > max_distance {
+        return None;
+    }
+
+    let band = |i: usize| (i.saturating_sub(max_distance), m.min(i + max_distance));
+
+    let mut previous_row = vec![INF; m + 1];
+    let mut current_row = vec![INF; m + 1];
+
+    let (_, first_hi) = band(0);
+    for (j, cell) in previous_row.iter_mut().enumerate().take(first_hi + 1) {
+        *cell = j;
+    }
+
+    for i in 1..=n {
+        let (previous_lo, previous_hi) = band(i - 1);
+        let (lo, hi) = band(i);
+        let previous = |j: usize| {
+            if (previous_lo..=previous_hi).contains(&j) {
+                previous_row[j]
+            } else {
+                INF
+            }
+        };
+
+        let mut row_min = INF;
+
+        for j in lo..=hi {
+            let value = if j == 0 {
+                i
+            } else {
+                let mismatch = !query[i - 1].eq_ignore_ascii_case(&reference[j - 1]) as usize;
+                let left = if j > lo { current_row[j - 1] } else { INF };
+
+                (previous(j - 1) + mismatch)
+                    .min(previous(j) + 1)
+                    .min(left + 1)
+            };
+
+            current_row[j] = value;
+            row_min = row_min.min(value);
+        }
+
+        if row_min > max_distance {
+            return None;
+        }
+
+        std::mem::swap(&mut previous_row, &mut current_row);
+    }
+
+    let (lo, hi) = band(n);
+    let distance = match mode {
+        AlignmentMode::Global => previous_row[m],
+        AlignmentMode::FreeReferenceEnd => previous_row[lo..=hi].iter().copied().min()?,
+    };
+
+    (distance <= max_distance).then_some(distance)
+}
+
+// an interval of the reference in which the whole query can be aligned with at most max_distance errors
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Occurrence {
+    pub start: usize,
+    pub end: usize,
+    // the smallest edit distance of all alignments in the interval
+    pub edit_distance: usize,
+}
+
+// semi-global search (free begin and end in the reference) with Ukkonen's cutoff.
+// Overlapping hits are merged into a single occurrence. N in the reference never matches.
+pub fn semi_global_occurrences(
+    query: &[u8],
+    reference: &[u8],
+    max_distance: usize,
+) -> Vec<Occurrence> {
+    let m = query.len();
+    let mut occurrences: Vec<Occurrence> = Vec::new();
+
+    if m == 0 {
+        return occurrences;
+    }
+
+    // cost and start of the best alignment of the query prefix of length i ending at the current column
+    let mut previous_costs: Vec<usize> = (0..=m).collect();
+    let mut previous_starts = vec![0; m + 1];
+    let mut current_costs = vec![0; m + 1];
+    let mut current_starts = vec![0; m + 1];
+
+    // all cells below the last active row have a cost larger than max_distance
+    let mut last_active_row = max_distance.min(m);
+
+    for (j, reference_base) in reference.iter().enumerate() {
+        let previous_cost = |i: usize, costs: &[usize]| {
+            if i <= last_active_row {
+                costs[i]
+            } else {
+                max_distance + 1
+            }
+        };
+
+        current_costs[0] = 0;
+        current_starts[0] = j + 1;
+
+        let last_row = (last_active_row + 1).min(m);
+
+        for i in 1..=last_row {
+            let mismatch = (!query[i - 1].eq_ignore_ascii_case(reference_base)
+                || reference_base.eq_ignore_ascii_case(&b'N')) as usize;
+
+            let mut cost = previous_cost(i - 1, &previous_costs) + mismatch;
+            let mut start = previous_starts[i - 1];
+
+            let deletion = previous_cost(i, &previous_costs) + 1;
+            if deletion < cost {
+                cost = deletion;
+                start = previous_starts[i];
+            }
+
+            let insertion = current_costs[i - 1] + 1;
+            if insertion < cost {
+                cost = insertion;
+                start = current_starts[i - 1];
+            }
+
+            current_costs[i] = cost;
+            current_starts[i] = start;
+        }
+
+        last_active_row = last_row;
+        while last_active_row > 0 && current_costs[last_active_row] > max_distance {
+            last_active_row -= 1;
+        }
+
+        if last_active_row == m {
+            let hit = Occurrence {
+                start: current_starts[m],
+                end: j + 1,
+                edit_distance: current_costs[m],
+            };
+
+            match occurrences.last_mut() {
+                Some(last) if hit.start < last.end => {
+                    last.start = last.start.min(hit.start);
+                    last.end = hit.end;
+                    last.edit_distance = last.edit_distance.min(hit.edit_distance);
+                }
+                _ => occurrences.push(hit),
+            }
+        }
+
+        std::mem::swap(&mut previous_costs, &mut current_costs);
+        std::mem::swap(&mut previous_starts, &mut current_starts);
+    }
+
+    occurrences
+}
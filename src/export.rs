@@ -0,0 +1,188 @@
+use std::fs;
+
+use anyhow::Result;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::{
+    folder_structure::BenchmarkFolder,
+    readmappers::{floxer::FloxerRunResult, minimap::MinimapRunResult},
+};
+
+static MINIMAP_INSTANCE_NAME: &str = "minimap";
+
+// lists with one entry per read or alignment, they are only exported to the JSON file
+const PER_READ_FIELDS: [&str; 2] = ["chimeric_reads", "mismatches"];
+
+// everything that a benchmark computed, such that it can be analyzed without parsing the TOML and
+// alignment files of the benchmark instances
+#[derive(Debug, Serialize)]
+pub struct BenchmarkResults<'a> {
+    pub benchmark_name: &'a str,
+    pub floxer: Vec<&'a FloxerRunResult>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub minimap: Option<&'a MinimapRunResult>,
+}
+
+impl BenchmarkResults<'_> {
+    // writes results.json with all results and results.csv in long format, with one row per instance
+    // and (numeric) metric
+    pub fn export(&self, benchmark_folder: &BenchmarkFolder) -> Result<()> {
+        let mut json_path = benchmark_folder.get().to_owned();
+        json_path.push("results.json");
+        fs::write(json_path, serde_json::to_string_pretty(self)?)?;
+
+        let mut rows = Vec::new();
+        for floxer_result in &self.floxer {
+            self.add_rows(
+                &floxer_result.benchmark_instance_name,
+                "floxer",
+                serde_json::to_value(floxer_result)?,
+                &mut rows,
+            );
+        }
+        if let Some(minimap_result) = self.minimap {
+            self.add_rows(
+                MINIMAP_INSTANCE_NAME,
+                "minimap",
+                serde_json::to_value(minimap_result)?,
+                &mut rows,
+            );
+        }
+
+        let mut csv_path = benchmark_folder.get().to_owned();
+        csv_path.push("results.csv");
+        fs::write(csv_path, rows.join(""))?;
+
+        Ok(())
+    }
+
+    fn add_rows(&self, instance_name: &str, mapper: &str, result: Value, rows: &mut Vec<String>) {
+        if rows.is_empty() {
+            rows.push(String::from("benchmark,instance,mapper,metric,value\n"));
+        }
+
+        let mut metrics = Vec::new();
+        flatten_metrics(String::new(), result, &mut metrics);
+
+        for (metric, value) in metrics {
+            let fields = [self.benchmark_name, instance_name, mapper, &metric, &value];
+            let row: Vec<_> = fields.iter().map(|field| csv_field(field)).collect();
+            rows.push(format!("{}\n", row.join(",")));
+        }
+    }
+}
+
+// nested fields are joined with dots and list entries by their index, e.g.
+// mapq_calibration.points.3.num_wrong. Histogram bins are labelled by their threshold instead
+// (e.g. stats.query_lengths.occurrences.1000, the last bin is inf). Strings (like paths), missing
+// values and per read lists are not metrics.
+fn flatten_metrics(prefix: String, value: Value, metrics: &mut Vec<(String, String)>) {
+    let join = |key: &str| {
+        if prefix.is_empty() {
+            key.to_owned()
+        } else {
+            format!("{prefix}.{key}")
+        }
+    };
+
+    match value {
+        Value::Object(mut fields) => {
+            if let Some(Value::Array(thresholds)) = fields.remove("thresholds") {
+                if let Some(Value::Array(occurrences)) = fields.remove("occurrences") {
+                    let occurrences_prefix = join("occurrences");
+                    let labels = thresholds
+                        .iter()
+                        .map(ToString::to_string)
+                        .chain([String::from("inf")]);
+
+                    for (label, occurrence) in labels.zip(occurrences) {
+                        flatten_metrics(
+                            format!("{occurrences_prefix}.{label}"),
+                            occurrence,
+                            metrics,
+                        );
+                    }
+                }
+            }
+
+            for (key, field) in fields {
+                if PER_READ_FIELDS.contains(&key.as_str()) {
+                    continue;
+                }
+
+                flatten_metrics(join(&key), field, metrics);
+            }
+        }
+        Value::Array(entries) => {
+            for (index, entry) in entries.into_iter().enumerate() {
+                flatten_metrics(join(&index.to_string()), entry, metrics);
+            }
+        }
+        Value::Number(number) => metrics.push((prefix, number.to_string())),
+        Value::Bool(flag) => metrics.push((prefix, (flag as u8).to_string())),
+        Value::String(_) | Value::Null => {}
+    }
+}
+
+// quoted if it contains a separator, quote or line break (RFC 4180)
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn csv_fields_are_quoted_if_needed() {
+        assert_eq!(csv_field("floxer_1"), "floxer_1");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_field("two\nlines"), "\"two\nlines\"");
+    }
+
+    #[test]
+    fn histograms_are_labelled_by_thresholds_and_per_read_lists_are_skipped() {
+        let result = json!({
+            "stats": {
+                "query_lengths": {
+                    "num_values": 3,
+                    "thresholds": [100, 1000],
+                    "occurrences": [1, 0, 2],
+                    "mean": 1500.5,
+                },
+            },
+            "mapped_read_stats": {
+                "num_chimeric": 1,
+                "chimeric_reads": [{ "query_name": "read_1", "num_segments": 2 }],
+            },
+            "mapped_reads_path": "out/mapped_reads.bam",
+            "points": [{ "mapq": 60 }],
+        });
+
+        let mut metrics = Vec::new();
+        flatten_metrics(String::new(), result, &mut metrics);
+        metrics.sort();
+
+        let expected = [
+            ("mapped_read_stats.num_chimeric", "1"),
+            ("points.0.mapq", "60"),
+            ("stats.query_lengths.mean", "1500.5"),
+            ("stats.query_lengths.num_values", "3"),
+            ("stats.query_lengths.occurrences.100", "1"),
+            ("stats.query_lengths.occurrences.1000", "0"),
+            ("stats.query_lengths.occurrences.inf", "2"),
+        ];
+        assert_eq!(
+            metrics,
+            expected.map(|(metric, value)| (metric.to_owned(), value.to_owned()))
+        );
+    }
+}
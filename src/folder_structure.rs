@@ -12,6 +12,8 @@ use crate::cli::BenchmarkConfig;
 #[derive(Debug, Clone)]
 pub struct BenchmarkFolder {
     folder: PathBuf,
+    benchmark_name: String,
+    tag: Option<String>,
 }
 
 impl BenchmarkFolder {
@@ -35,19 +37,37 @@ impl BenchmarkFolder {
         }
         folder.push(&subfolder_name);
 
-        Self { folder }
+        Self {
+            folder,
+            benchmark_name: benchmark_name.to_owned(),
+            tag: config.tag.clone(),
+        }
     }
 
     pub fn get(&self) -> &Path {
         &self.folder
     }
 
+    pub fn benchmark_name(&self) -> &str {
+        &self.benchmark_name
+    }
+
+    pub fn tag(&self) -> Option<&str> {
+        self.tag.as_deref()
+    }
+
     pub fn plot_folder(&self) -> PathBuf {
         let mut folder = self.folder.clone();
         folder.push("plots");
         folder
     }
 
+    pub fn discrepancies_folder(&self) -> PathBuf {
+        let mut folder = self.folder.clone();
+        folder.push("discrepancies");
+        folder
+    }
+
     pub fn most_recect_previous_run_folder(&self) -> PathBuf {
         let mut most_recent_link = self.folder.clone();
         most_recent_link.pop();
@@ -76,8 +96,17 @@ pub struct BenchmarkInstanceFolder {
     pub timing_path: PathBuf,
     pub index_timing_path: PathBuf,
     pub stats_path: PathBuf,
+    pub query_stats_path: PathBuf,
+    pub simulated_verification_path: PathBuf,
+    pub mapped_reads_stats_path: PathBuf,
+    pub length_binned_stats_path: PathBuf,
+    pub alignment_validation_path: PathBuf,
+    pub conformance_report_path: PathBuf,
+    pub oracle_comparison_path: PathBuf,
     pub perf_data_path: PathBuf,
     pub flamegraph_path: PathBuf,
+    pub manifest_path: PathBuf,
+    pub binary_version_path: PathBuf,
 }
 
 impl BenchmarkInstanceFolder {
@@ -121,12 +150,39 @@ impl BenchmarkInstanceFolder {
         let mut stats_path = base_folder.clone();
         stats_path.push("stats.toml");
 
+        let mut query_stats_path = base_folder.clone();
+        query_stats_path.push("query_stats.tsv");
+
+        let mut simulated_verification_path = base_folder.clone();
+        simulated_verification_path.push("simulated_verification.toml");
+
+        let mut mapped_reads_stats_path = base_folder.clone();
+        mapped_reads_stats_path.push("mapped_reads_stats.toml");
+
+        let mut length_binned_stats_path = base_folder.clone();
+        length_binned_stats_path.push("length_binned_stats.toml");
+
+        let mut alignment_validation_path = base_folder.clone();
+        alignment_validation_path.push("alignment_validation.toml");
+
+        let mut conformance_report_path = base_folder.clone();
+        conformance_report_path.push("sam_conformance.toml");
+
+        let mut oracle_comparison_path = base_folder.clone();
+        oracle_comparison_path.push("oracle_comparison.toml");
+
         let mut perf_data_path = base_folder.clone();
         perf_data_path.push("perf.data");
 
         let mut flamegraph_path = base_folder.clone();
         flamegraph_path.push(format!("flamegraph_{}.svg", instance_name));
 
+        let mut manifest_path = base_folder.clone();
+        manifest_path.push("manifest.toml");
+
+        let mut binary_version_path = base_folder.clone();
+        binary_version_path.push("binary_version.txt");
+
         Ok(Self {
             mapped_reads_sam_path,
             mapped_reads_bam_path,
@@ -134,8 +190,33 @@ impl BenchmarkInstanceFolder {
             timing_path,
             index_timing_path,
             stats_path,
+            query_stats_path,
+            simulated_verification_path,
+            mapped_reads_stats_path,
+            length_binned_stats_path,
+            alignment_validation_path,
+            conformance_report_path,
+            oracle_comparison_path,
             perf_data_path,
             flamegraph_path,
+            manifest_path,
+            binary_version_path,
         })
     }
+
+    // resource usage of the additional repetitions of a run, starting at 1
+    pub fn repetition_timing_path(&self, repetition: u16) -> PathBuf {
+        self.timing_path
+            .with_file_name(format!("timing_repetition_{repetition}.toml"))
+    }
+
+    // resolves the most_recent link, such that reruns of the analysis refer to the same folder
+    pub fn canonical_base_folder(&self) -> Result<PathBuf> {
+        let base_folder = self
+            .timing_path
+            .parent()
+            .expect("timing file is in the instance folder");
+
+        Ok(fs::canonicalize(base_folder)?)
+    }
 }
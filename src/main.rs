@@ -1,12 +1,21 @@
+mod alignment_files;
+mod alignment_validation;
 mod analyze_mapped_reads;
 mod benchmarks;
 mod cli;
 mod config;
+mod datasets;
+mod edit_distance;
+mod export;
 mod folder_structure;
 mod plots;
 mod readmappers;
+mod regression_gate;
+mod report;
+mod results_database;
+mod statistics;
 
-use std::{error::Error, fs};
+use std::{error::Error, fs, rc::Rc};
 
 use clap::Parser;
 use config::BenchmarkSuiteConfig;
@@ -15,10 +24,104 @@ fn main() -> Result<(), Box<dyn Error>> {
     let args = cli::CliArgs::parse();
 
     let config_file_str = fs::read_to_string(&args.config_file)?;
-    let suite_config: BenchmarkSuiteConfig = toml::from_str(&config_file_str)?;
+    let mut suite_config: BenchmarkSuiteConfig = toml::from_str(&config_file_str)?;
 
     suite_config.setup()?;
 
+    if let Some(command) = args.command {
+        match command {
+            cli::Command::DeriveQueries(derive_args) => {
+                datasets::derived::derive_queries(&derive_args, &mut suite_config)?
+            }
+            cli::Command::SimulateDataset(simulate_args) => {
+                datasets::simulated::simulate_dataset(&simulate_args, &mut suite_config)?
+            }
+            cli::Command::CompareMappedReads(compare_args) => {
+                let labelled_alignments = compare_args
+                    .mapped_reads
+                    .into_iter()
+                    .map(|(label, path)| {
+                        let alignments = alignment_files::read_alignments_per_query(&path, None)?;
+                        Ok((label, path, Rc::new(alignments)))
+                    })
+                    .collect::<anyhow::Result<Vec<_>>>()?;
+                let comparison = analyze_mapped_reads::compare_mappers(
+                    labelled_alignments,
+                    compare_args.error_rate,
+                )?;
+                let comparison_str = toml::to_string(&comparison)?;
+
+                match compare_args.output {
+                    Some(output_path) => fs::write(output_path, comparison_str)?,
+                    None => print!("{comparison_str}"),
+                }
+
+                if let Some(discrepancies_folder) = compare_args.discrepancies {
+                    let dataset_name_prefix = discrepancies_folder
+                        .file_name()
+                        .unwrap_or_default()
+                        .to_string_lossy()
+                        .into_owned();
+
+                    analyze_mapped_reads::export_discrepancies(
+                        &comparison,
+                        &discrepancies_folder,
+                        compare_args
+                            .queries
+                            .as_ref()
+                            .map(|queries| (queries, dataset_name_prefix.as_str())),
+                        &suite_config,
+                    )?;
+                }
+
+                if let Some(regions_output) = compare_args.regions_output {
+                    let annotations = analyze_mapped_reads::regions::RegionAnnotations::load(
+                        &compare_args.regions,
+                    )?;
+                    let stratified_comparison = analyze_mapped_reads::regions::stratify_by_regions(
+                        &comparison,
+                        &annotations,
+                        compare_args.position_tolerance,
+                    )?;
+
+                    fs::write(regions_output, toml::to_string(&stratified_comparison)?)?;
+                }
+
+                if let Some(multi_mapping_output) = compare_args.multi_mapping_output {
+                    let multi_mapping_comparison =
+                        analyze_mapped_reads::multi_mapping::compare_multi_mapping(&comparison);
+
+                    fs::write(
+                        multi_mapping_output,
+                        toml::to_string(&multi_mapping_comparison)?,
+                    )?;
+                }
+            }
+            cli::Command::Results(results_args) => {
+                results_database::query_results(&results_args, &suite_config)?
+            }
+            cli::Command::Baseline(baseline_args) => {
+                regression_gate::run_baseline_command(&baseline_args, &suite_config)?
+            }
+            cli::Command::Report(report_args) => {
+                let report_path = match report_args.benchmark_folder {
+                    Some(benchmark_folder) => {
+                        report::write_benchmark_report(&benchmark_folder)?.report_path
+                    }
+                    None => report::write_suite_report(&suite_config)?,
+                };
+
+                println!("- wrote report {}", report_path.display());
+            }
+        }
+
+        return Ok(());
+    }
+
+    // fail early if a registered dataset does not exist
+    args.benchmark_config.queries.path(&suite_config)?;
+    args.benchmark_config.reference.path(&suite_config)?;
+
     if let Some(benchmarks) = args.benchmarks {
         benchmarks::run_benchmarks(benchmarks, &suite_config, &args.benchmark_config)?;
     } else {
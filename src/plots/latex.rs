@@ -0,0 +1,340 @@
+use std::{fmt::Write, fs};
+
+use anyhow::Result;
+
+use crate::{config::BenchmarkSuiteConfig, folder_structure::BenchmarkFolder};
+
+// the figures are typeset by LaTeX with the fonts of the thesis, the data files are read relative
+// to this path, e.g. \newcommand{\benchmarkdatapath}{figures/} before \input of a figure
+static DATA_PATH_COMMAND: &str = "\\benchmarkdatapath";
+
+// bar charts in one or more axes side by side, like the thesis plots
+pub struct BarFigure {
+    name: String,
+    panels: Vec<BarPanel>,
+}
+
+pub struct BarPanel {
+    value_label: String,
+    category_label: Option<String>,
+    // empty categories have no tick label
+    categories: Vec<String>,
+    // name and one value per category
+    series: Vec<(String, Vec<f64>)>,
+    stacked: bool,
+    // the categories on the y axis
+    horizontal: bool,
+}
+
+impl BarPanel {
+    pub fn new<S: AsRef<str>>(value_label: &str, categories: impl IntoIterator<Item = S>) -> Self {
+        Self {
+            value_label: value_label.to_owned(),
+            category_label: None,
+            categories: categories
+                .into_iter()
+                .map(|category| category.as_ref().to_owned())
+                .collect(),
+            series: Vec::new(),
+            stacked: false,
+            horizontal: false,
+        }
+    }
+
+    // one bar per instance without a category, the layout of most thesis plots
+    pub fn per_instance<S: AsRef<str>>(
+        value_label: &str,
+        names_and_values: impl IntoIterator<Item = (S, f64)>,
+    ) -> Self {
+        names_and_values
+            .into_iter()
+            .fold(Self::new(value_label, [""]), |panel, (name, value)| {
+                panel.series(name.as_ref(), [value])
+            })
+    }
+
+    pub fn category_label(mut self, category_label: &str) -> Self {
+        self.category_label = Some(category_label.to_owned());
+        self
+    }
+
+    pub fn series(mut self, name: &str, values: impl IntoIterator<Item = f64>) -> Self {
+        self.series
+            .push((name.to_owned(), values.into_iter().collect()));
+        self
+    }
+
+    pub fn stacked(mut self) -> Self {
+        self.stacked = true;
+        self
+    }
+
+    pub fn horizontal(mut self) -> Self {
+        self.horizontal = true;
+        self
+    }
+
+    fn series_names(&self) -> Vec<&str> {
+        self.series.iter().map(|(name, _)| name.as_str()).collect()
+    }
+
+    fn to_csv(&self) -> String {
+        let mut csv = String::from("coordinate,category");
+        for (name, _) in &self.series {
+            csv.push(',');
+            csv.push_str(&name.replace(',', " "));
+        }
+        csv.push('\n');
+
+        for (category_index, category) in self.categories.iter().enumerate() {
+            csv.push_str(&format!("c{category_index},{}", category.replace(',', " ")));
+            for (_, values) in &self.series {
+                csv.push(',');
+                if let Some(value) = values.get(category_index) {
+                    csv.push_str(&value.to_string());
+                }
+            }
+            csv.push('\n');
+        }
+
+        csv
+    }
+}
+
+impl BarFigure {
+    pub fn new(name: String) -> Self {
+        Self {
+            name,
+            panels: Vec::new(),
+        }
+    }
+
+    pub fn panel(mut self, panel: BarPanel) -> Self {
+        self.panels.push(panel);
+        self
+    }
+
+    fn data_file_name(&self, panel_index: usize) -> String {
+        if self.panels.len() == 1 {
+            format!("{}.csv", self.name)
+        } else {
+            format!("{}_{panel_index}.csv", self.name)
+        }
+    }
+
+    fn to_pgfplots(&self) -> Result<String> {
+        let mut tex = String::new();
+
+        writeln!(
+            tex,
+            "% {}, requires \\usepackage{{pgfplots}} and \\usepgfplotslibrary{{groupplots}}",
+            self.name
+        )?;
+        writeln!(tex, "\\providecommand{{{DATA_PATH_COMMAND}}}{{}}")?;
+        writeln!(tex, "\\begin{{tikzpicture}}")?;
+        writeln!(
+            tex,
+            "\\begin{{groupplot}}[\n    group style={{group size={} by 1, horizontal sep=1.8cm}},\n    \
+            width={:.2}\\linewidth,\n    height=6cm,\n    scaled ticks=false,\n    \
+            /pgf/number format/fixed,\n    legend style={{at={{(0,1.03)}}, anchor=south west, \
+            legend columns=-1}},\n]",
+            self.panels.len(),
+            0.85 / self.panels.len().max(1) as f64
+        )?;
+
+        for (panel_index, panel) in self.panels.iter().enumerate() {
+            let coordinates: Vec<_> = (0..panel.categories.len())
+                .map(|category_index| format!("c{category_index}"))
+                .collect();
+            let tick_labels: Vec<_> = panel
+                .categories
+                .iter()
+                .map(|category| format!("{{{}}}", escape(category)))
+                .collect();
+
+            let (category_axis, value_axis) = if panel.horizontal {
+                ("y", "x")
+            } else {
+                ("x", "y")
+            };
+
+            let mut options = vec![
+                format!(
+                    "{value_axis}bar{}",
+                    if panel.stacked { " stacked" } else { "" }
+                ),
+                format!("{value_axis}min=0"),
+                format!("{value_axis}label={{{}}}", escape(&panel.value_label)),
+                format!(
+                    "symbolic {category_axis} coords={{{}}}",
+                    coordinates.join(",")
+                ),
+                format!("{category_axis}tick=data"),
+                format!("{category_axis}ticklabels={{{}}}", tick_labels.join(",")),
+                // a single category would otherwise be squeezed to the border
+                format!(
+                    "enlarge {category_axis} limits={}",
+                    if panel.categories.len() > 1 {
+                        0.15
+                    } else {
+                        0.5
+                    }
+                ),
+            ];
+            if let Some(category_label) = &panel.category_label {
+                options.push(format!(
+                    "{category_axis}label={{{}}}",
+                    escape(category_label)
+                ));
+            }
+            if !panel.stacked {
+                options.push(String::from("nodes near coords"));
+                options.push(String::from(
+                    "every node near coord/.append style={font=\\footnotesize}",
+                ));
+            }
+
+            writeln!(tex, "\\nextgroupplot[{}]", options.join(", "))?;
+
+            // usually the panels show the same series, then one legend is enough
+            let show_legend = panel.series.len() > 1
+                && (panel_index == 0 || panel.series_names() != self.panels[0].series_names());
+
+            for (series_index, (name, _)) in panel.series.iter().enumerate() {
+                writeln!(
+                    tex,
+                    "\\addplot table[{category_axis}=coordinate, {value_axis} index={}, \
+                    col sep=comma] {{{DATA_PATH_COMMAND} {}}};",
+                    series_index + 2,
+                    self.data_file_name(panel_index)
+                )?;
+
+                if show_legend {
+                    writeln!(tex, "\\addlegendentry{{{}}}", escape(name))?;
+                }
+            }
+        }
+
+        writeln!(tex, "\\end{{groupplot}}\n\\end{{tikzpicture}}")?;
+
+        Ok(tex)
+    }
+
+    // next to the SVG of the same plot
+    pub fn save(
+        &self,
+        benchmark_folder: &BenchmarkFolder,
+        suite_config: &BenchmarkSuiteConfig,
+    ) -> Result<()> {
+        let mut files = vec![(format!("{}.tex", self.name), self.to_pgfplots()?)];
+        for (panel_index, panel) in self.panels.iter().enumerate() {
+            files.push((self.data_file_name(panel_index), panel.to_csv()));
+        }
+
+        save_files(&files, benchmark_folder, suite_config)
+    }
+}
+
+// a booktabs table, meant to be put into a table environment with a caption by the thesis
+pub struct Table {
+    name: String,
+    // the first column is left aligned, the others are right aligned
+    header: Vec<String>,
+    rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    pub fn new<S: AsRef<str>>(name: String, header: impl IntoIterator<Item = S>) -> Self {
+        Self {
+            name,
+            header: header
+                .into_iter()
+                .map(|column| column.as_ref().to_owned())
+                .collect(),
+            rows: Vec::new(),
+        }
+    }
+
+    pub fn row(mut self, row: Vec<String>) -> Self {
+        self.rows.push(row);
+        self
+    }
+
+    fn to_booktabs(&self) -> Result<String> {
+        let escape_row = |row: &[String]| -> String {
+            row.iter()
+                .map(|cell| escape(cell))
+                .collect::<Vec<_>>()
+                .join(" & ")
+        };
+
+        let mut tex = String::new();
+
+        writeln!(
+            tex,
+            "% {}, requires \\usepackage{{booktabs}}\n\\begin{{tabular}}{{l{}}}\n\\toprule",
+            self.name,
+            "r".repeat(self.header.len().saturating_sub(1))
+        )?;
+        writeln!(tex, "{} \\\\\n\\midrule", escape_row(&self.header))?;
+        for row in &self.rows {
+            writeln!(tex, "{} \\\\", escape_row(row))?;
+        }
+        writeln!(tex, "\\bottomrule\n\\end{{tabular}}")?;
+
+        Ok(tex)
+    }
+
+    pub fn save(
+        &self,
+        benchmark_folder: &BenchmarkFolder,
+        suite_config: &BenchmarkSuiteConfig,
+    ) -> Result<()> {
+        save_files(
+            &[(format!("{}.tex", self.name), self.to_booktabs()?)],
+            benchmark_folder,
+            suite_config,
+        )
+    }
+}
+
+// like the SVGs, into the plot folder of the benchmark and the all plots folder
+fn save_files(
+    files: &[(String, String)],
+    benchmark_folder: &BenchmarkFolder,
+    suite_config: &BenchmarkSuiteConfig,
+) -> Result<()> {
+    let plot_folder = benchmark_folder.plot_folder();
+    if !plot_folder.exists() {
+        fs::create_dir_all(&plot_folder)?;
+    }
+
+    for folder in [plot_folder, suite_config.all_plots_folder()] {
+        for (file_name, content) in files {
+            let mut path = folder.clone();
+            path.push(file_name);
+            fs::write(path, content)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\textbackslash{}"),
+            '~' => escaped.push_str("\\textasciitilde{}"),
+            '^' => escaped.push_str("\\textasciicircum{}"),
+            '#' | '$' | '%' | '&' | '_' | '{' | '}' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            _ => escaped.push(c),
+        }
+    }
+
+    escaped
+}
@@ -1,18 +1,31 @@
+pub mod latex;
 pub mod thesis;
 
-use std::fs;
+use std::{fs, iter::zip};
+
+use latex::Table;
 
 use crate::{
-    analyze_mapped_reads::{DetailedMappedReadsComparison, SimpleMappedReadsStats},
+    analyze_mapped_reads::{
+        length_bins::{LengthBinStats, LengthBinnedStats},
+        mapq_calibration::MapqCalibration,
+        multi_mapping::{MultiMappingComparison, MultiMappingStats},
+        DetailedMappedReadsComparison, MapperComparison, ScopedStats, SimpleMappedReadsStats,
+        SimulatedVerificationResults, VerificationCounts,
+    },
     config::BenchmarkSuiteConfig,
     folder_structure::BenchmarkFolder,
-    readmappers::{floxer::HistogramData, ResourceMetrics},
+    readmappers::{
+        floxer::HistogramData, ResourceMetrics, ResourceMetricsComparison, ResourceMetricsTests,
+    },
 };
 
 use charming::{
     component::{Axis, Grid, Legend, Title},
-    element::{AxisLabel, Formatter, Label, LabelPosition, NameLocation, TextStyle},
-    series::Bar,
+    element::{
+        AxisLabel, AxisType, Formatter, ItemStyle, Label, LabelPosition, NameLocation, TextStyle,
+    },
+    series::{Bar, Line, Scatter},
     Chart, ImageRenderer,
 };
 
@@ -22,6 +35,7 @@ static SUBTITLE_FONT_SIZE: u8 = 20;
 static LABEL_FONT_SIZE: u8 = 15;
 static GRID_OUTERMOST_OFFSET: usize = 6;
 static JS_FLOAT_FORMATTER: &str = "function (param) { return param.data.toFixed(1); }";
+static JS_FLOAT_FORMATTER_2: &str = "function (param) { return param.data.toFixed(2); }";
 static JS_FLOAT_FORMATTER_0: &str = "function (param) { return param.data.toFixed(0); }";
 
 pub fn plot_resource_metrics<'a>(
@@ -30,6 +44,34 @@ pub fn plot_resource_metrics<'a>(
     benchmark_folder: &BenchmarkFolder,
     suite_config: &BenchmarkSuiteConfig,
 ) {
+    let metrics_and_names_of_runs: Vec<_> = metrics_and_names_of_runs.into_iter().collect();
+
+    metrics_and_names_of_runs
+        .iter()
+        .fold(
+            Table::new(
+                format!("{benchmark_name}_resource_metrics"),
+                [
+                    "Run",
+                    "Wall clock time [s]",
+                    "User CPU time [s]",
+                    "System CPU time [s]",
+                    "Peak memory [GB]",
+                ],
+            ),
+            |table, (metrics, name)| {
+                table.row(vec![
+                    name.replace("_", " "),
+                    format!("{:.1}", metrics.wall_clock_seconds),
+                    format!("{:.1}", metrics.user_cpu_seconds),
+                    format!("{:.1}", metrics.system_cpu_seconds),
+                    format!("{:.2}", metrics.peak_memory_kilobytes as f64 / 1_000_000.0),
+                ])
+            },
+        )
+        .save(benchmark_folder, suite_config)
+        .expect("failed to save the resource metrics table");
+
     let mut chart = Chart::new()
         .legend(
             Legend::new()
@@ -68,7 +110,7 @@ pub fn plot_resource_metrics<'a>(
                 .axis_label(AxisLabel::new().font_size(AXIS_TEXT_SIZE).color("black")),
         );
 
-    for (metrics, name) in metrics_and_names_of_runs.into_iter() {
+    for (metrics, name) in metrics_and_names_of_runs {
         chart = chart
             .series(
                 Bar::new()
@@ -133,9 +175,15 @@ pub fn plot_mapped_reads_stats<'a, S>(
         .map(|s| s.as_ref().to_owned())
         .collect();
 
-    let num_mapped_per_instance: Vec<_> = iter.into_iter().map(|stats| stats.num_mapped).collect();
+    let stats: Vec<_> = iter.into_iter().collect();
+    let num_linear_per_instance: Vec<_> = stats
+        .iter()
+        .map(|stats| stats.num_mapped - stats.num_chimeric)
+        .collect();
+    let num_chimeric_per_instance: Vec<_> = stats.iter().map(|stats| stats.num_chimeric).collect();
 
     let chart = Chart::new()
+        .legend(Legend::new().right("10%"))
         .background_color("white")
         .x_axis(
             Axis::new()
@@ -150,7 +198,18 @@ pub fn plot_mapped_reads_stats<'a, S>(
         )
         .series(
             Bar::new()
-                .data(num_mapped_per_instance)
+                .stack("all")
+                .name("linear")
+                .data(num_linear_per_instance)
+                .x_axis_index(0)
+                .y_axis_index(0)
+                .label(Label::new().show(true).position(LabelPosition::Inside)),
+        )
+        .series(
+            Bar::new()
+                .stack("all")
+                .name("chimeric")
+                .data(num_chimeric_per_instance)
                 .x_axis_index(0)
                 .y_axis_index(0)
                 .label(Label::new().show(true).position(LabelPosition::Top)),
@@ -168,6 +227,424 @@ pub fn plot_mapped_reads_stats<'a, S>(
     );
 }
 
+// the primary and secondary alignment stats of every run in a grid each
+pub fn plot_alignment_histograms<'a>(
+    stats_and_names_of_runs: impl IntoIterator<Item = (&'a SimpleMappedReadsStats, &'a str)>,
+    benchmark_name: &str,
+    benchmark_folder: &BenchmarkFolder,
+    suite_config: &BenchmarkSuiteConfig,
+) {
+    let (stats, names): (Vec<_>, Vec<_>) = stats_and_names_of_runs.into_iter().unzip();
+
+    let Some(first_stats) = stats.first() else {
+        return;
+    };
+
+    plot_histogram_data_in_grid(
+        stats
+            .iter()
+            .map(|stats| stats.primary_alignment_stats.iter_histograms()),
+        &format!("{benchmark_name} primary alignment stats"),
+        &names,
+        first_stats.primary_alignment_stats.iter_metric_names(),
+        benchmark_folder,
+        suite_config,
+    );
+
+    plot_histogram_data_in_grid(
+        stats
+            .iter()
+            .map(|stats| stats.secondary_alignment_stats.iter_histograms()),
+        &format!("{benchmark_name} secondary alignment stats"),
+        &names,
+        first_stats.secondary_alignment_stats.iter_metric_names(),
+        benchmark_folder,
+        suite_config,
+    );
+}
+
+// mapping rate, mean edit distance rate and mean times per query length bin, one bar per run
+pub fn plot_length_binned_stats<'a>(
+    stats_and_names_of_runs: impl IntoIterator<Item = (&'a LengthBinnedStats, &'a str)>,
+    benchmark_name: &str,
+    benchmark_folder: &BenchmarkFolder,
+    suite_config: &BenchmarkSuiteConfig,
+) {
+    let (stats, names): (Vec<_>, Vec<_>) = stats_and_names_of_runs.into_iter().unzip();
+
+    let Some(first_stats) = stats.first() else {
+        return;
+    };
+
+    let bin_labels: Vec<_> = first_stats
+        .bins
+        .iter()
+        .map(|bin| bin.label.clone())
+        .collect();
+
+    for (metric_name, file_name_suffix, metric, factor) in [
+        (
+            "Mapped queries (%)",
+            "mapping_rate",
+            (|bin: &LengthBinStats| Some(bin.mapping_rate)) as fn(&LengthBinStats) -> Option<f64>,
+            100.0,
+        ),
+        (
+            "Mean edit distance rate (%)",
+            "edit_distance_rate",
+            |bin: &LengthBinStats| bin.mean_edit_distance_rate,
+            100.0,
+        ),
+        (
+            "Mean search time per query (ms)",
+            "search_time",
+            |bin: &LengthBinStats| bin.mean_milliseconds_search_per_read,
+            1.0,
+        ),
+        (
+            "Mean verification time per query (ms)",
+            "verification_time",
+            |bin: &LengthBinStats| bin.mean_milliseconds_verification_per_read,
+            1.0,
+        ),
+    ] {
+        // the timings are only known for floxer builds that write the per query stats
+        if stats
+            .iter()
+            .all(|stats| stats.bins.iter().all(|bin| metric(bin).is_none()))
+        {
+            continue;
+        }
+
+        let mut chart = Chart::new()
+            .legend(
+                Legend::new()
+                    .right("10%")
+                    .text_style(TextStyle::new().font_size(20).color("black")),
+            )
+            .background_color("white")
+            .x_axis(
+                Axis::new()
+                    .name("Query length")
+                    .name_location(NameLocation::Middle)
+                    .name_gap(35)
+                    .data(bin_labels.clone())
+                    .name_text_style(TextStyle::new().font_size(AXIS_TEXT_SIZE).color("black"))
+                    .axis_label(AxisLabel::new().font_size(18).color("black")),
+            )
+            .y_axis(
+                Axis::new()
+                    .name(metric_name)
+                    .name_text_style(TextStyle::new().font_size(AXIS_TEXT_SIZE).color("black"))
+                    .axis_label(AxisLabel::new().font_size(AXIS_TEXT_SIZE).color("black")),
+            );
+
+        for (stats, name) in stats.iter().zip(&names) {
+            chart = chart.series(
+                Bar::new()
+                    .data(
+                        stats
+                            .bins
+                            .iter()
+                            .map(|bin| metric(bin).unwrap_or(0.0) * factor)
+                            .collect::<Vec<_>>(),
+                    )
+                    .name(name.replace("_", " ")),
+            );
+        }
+
+        save_chart(
+            chart,
+            format!("{benchmark_name}_{file_name_suffix}_by_query_length"),
+            1600,
+            800,
+            benchmark_folder,
+            suite_config,
+        );
+    }
+}
+
+pub fn plot_simulated_verification(
+    results: &SimulatedVerificationResults,
+    benchmark_name: &str,
+    benchmark_folder: &BenchmarkFolder,
+    suite_config: &BenchmarkSuiteConfig,
+) {
+    let instance_names: Vec<_> = results
+        .instances
+        .iter()
+        .map(|instance| instance.instance_name.replace("_", " "))
+        .collect();
+
+    let category_counts = |count: fn(&VerificationCounts) -> usize| -> Vec<i64> {
+        results
+            .instances
+            .iter()
+            .map(|instance| count(&instance.counts) as i64)
+            .collect()
+    };
+    let categories = [
+        (
+            "found by primary",
+            category_counts(|counts| counts.found_by_primary),
+        ),
+        (
+            "found only by secondary",
+            category_counts(|counts| counts.found_only_by_secondary),
+        ),
+        (
+            "found suboptimal",
+            category_counts(|counts| counts.found_suboptimal),
+        ),
+        (
+            "mapped elsewhere",
+            category_counts(|counts| counts.mapped_elsewhere),
+        ),
+        ("unmapped", category_counts(|counts| counts.unmapped)),
+    ];
+
+    let mut chart = Chart::new()
+        .title(
+            Title::new()
+                .text(format!("{benchmark_name} simulated reads"))
+                .left("center"),
+        )
+        .legend(Legend::new().top("5%"))
+        .background_color("white")
+        .grid(Grid::new().top("15%").bottom("25%"))
+        .x_axis(
+            Axis::new()
+                .data(instance_names)
+                .axis_label(AxisLabel::new().rotate(45).font_size(14).color("black")),
+        )
+        .y_axis(
+            Axis::new()
+                .name("#Reads")
+                .name_text_style(TextStyle::new().font_size(20).color("black"))
+                .axis_label(AxisLabel::new().font_size(14).color("black")),
+        );
+
+    for (category_name, counts) in categories {
+        chart = chart.series(
+            Bar::new()
+                .stack("all")
+                .name(category_name)
+                .data(counts)
+                .label(Label::new().show(true).position(LabelPosition::Inside)),
+        );
+    }
+
+    save_chart(
+        chart,
+        format!("{benchmark_name}_simulated_verification"),
+        (150 * results.instances.len() as u32).max(800),
+        800,
+        benchmark_folder,
+        suite_config,
+    );
+}
+
+// alignments per read and edit distance gaps in a grid, the locus distribution as stacked bars
+pub fn plot_multi_mapping(
+    comparison: &MultiMappingComparison,
+    benchmark_name: &str,
+    benchmark_folder: &BenchmarkFolder,
+    suite_config: &BenchmarkSuiteConfig,
+) {
+    plot_histogram_data_in_grid(
+        comparison
+            .stats_per_mapper
+            .iter()
+            .map(|stats| [&stats.alignments_per_read, &stats.edit_distance_gap]),
+        &format!("{benchmark_name} multi-mapping stats"),
+        &comparison.mappers,
+        ["Alignments per read", "Edit distance gap to second best"],
+        benchmark_folder,
+        suite_config,
+    );
+
+    let mut chart = Chart::new()
+        .title(
+            Title::new()
+                .text(format!("{benchmark_name} loci of multi-mapped reads"))
+                .left("center"),
+        )
+        .legend(Legend::new().top("5%"))
+        .background_color("white")
+        .grid(Grid::new().top("15%"))
+        .x_axis(
+            Axis::new()
+                .data(comparison.mappers.clone())
+                .axis_label(AxisLabel::new().font_size(14).color("black")),
+        )
+        .y_axis(
+            Axis::new()
+                .name("#Reads")
+                .name_text_style(TextStyle::new().font_size(20).color("black"))
+                .axis_label(AxisLabel::new().font_size(14).color("black")),
+        );
+
+    for (category_name, count) in [
+        (
+            "clustered (tandem)",
+            (|stats: &MultiMappingStats| stats.loci.num_clustered_reads)
+                as fn(&MultiMappingStats) -> i64,
+        ),
+        ("interspersed", |stats: &MultiMappingStats| {
+            stats.loci.num_interspersed_reads
+        }),
+        ("mixed", |stats: &MultiMappingStats| {
+            stats.loci.num_mixed_reads
+        }),
+    ] {
+        chart = chart.series(
+            Bar::new()
+                .stack("all")
+                .name(category_name)
+                .data(comparison.stats_per_mapper.iter().map(count).collect())
+                .label(Label::new().show(true).position(LabelPosition::Inside)),
+        );
+    }
+
+    save_chart(
+        chart,
+        format!("{benchmark_name}_multi_mapping_loci"),
+        (200 * comparison.mappers.len() as u32).max(800),
+        800,
+        benchmark_folder,
+        suite_config,
+    );
+}
+
+// runs without a calibration (not a simulated dataset) are left out
+pub fn plot_mapq_calibration<'a>(
+    calibrations_and_names_of_runs: impl IntoIterator<Item = (Option<&'a MapqCalibration>, &'a str)>,
+    benchmark_name: &str,
+    benchmark_folder: &BenchmarkFolder,
+    suite_config: &BenchmarkSuiteConfig,
+) {
+    let calibrations_and_names: Vec<_> = calibrations_and_names_of_runs
+        .into_iter()
+        .filter_map(|(calibration, name)| calibration.map(|calibration| (calibration, name)))
+        .collect();
+
+    if calibrations_and_names.is_empty() {
+        return;
+    }
+
+    let legend = || {
+        Legend::new()
+            .right("10%")
+            .text_style(TextStyle::new().font_size(20).color("black"))
+    };
+
+    // the error rate is on a log axis, so it gets a pseudo count for the points without wrong alignments
+    let mut curve_chart = Chart::new()
+        .legend(legend())
+        .background_color("white")
+        .x_axis(
+            Axis::new()
+                .type_(AxisType::Log)
+                .name("Error rate of alignments with at least the MAPQ")
+                .name_location(NameLocation::Middle)
+                .name_gap(35)
+                .name_text_style(TextStyle::new().font_size(AXIS_TEXT_SIZE).color("black"))
+                .axis_label(AxisLabel::new().font_size(18).color("black")),
+        )
+        .y_axis(
+            Axis::new()
+                .name("Mapped queries (%)")
+                .name_text_style(TextStyle::new().font_size(AXIS_TEXT_SIZE).color("black"))
+                .axis_label(AxisLabel::new().font_size(AXIS_TEXT_SIZE).color("black")),
+        );
+
+    let mut calibration_chart = Chart::new()
+        .legend(legend())
+        .background_color("white")
+        .x_axis(
+            Axis::new()
+                .name("Reported MAPQ")
+                .name_location(NameLocation::Middle)
+                .name_gap(35)
+                .name_text_style(TextStyle::new().font_size(AXIS_TEXT_SIZE).color("black"))
+                .axis_label(AxisLabel::new().font_size(18).color("black")),
+        )
+        .y_axis(
+            Axis::new()
+                .name("Empirical MAPQ")
+                .name_text_style(TextStyle::new().font_size(AXIS_TEXT_SIZE).color("black"))
+                .axis_label(AxisLabel::new().font_size(AXIS_TEXT_SIZE).color("black")),
+        );
+
+    let mut max_mapq = 0;
+
+    for (calibration, name) in &calibrations_and_names {
+        curve_chart = curve_chart.series(
+            Line::new()
+                .name(name.replace("_", " "))
+                .symbol_size(8)
+                .data(
+                    calibration
+                        .points
+                        .iter()
+                        .map(|point| {
+                            vec![
+                                (point.cumulative_num_wrong as f64 + 1.0)
+                                    / (point.cumulative_num_mapped as f64 + 1.0),
+                                point.cumulative_fraction_mapped * 100.0,
+                            ]
+                        })
+                        .collect(),
+                ),
+        );
+
+        calibration_chart = calibration_chart.series(
+            Scatter::new()
+                .name(name.replace("_", " "))
+                .symbol_size(12)
+                .data(
+                    calibration
+                        .points
+                        .iter()
+                        .map(|point| vec![point.mapq as f64, point.empirical_mapq])
+                        .collect(),
+                ),
+        );
+
+        max_mapq = calibration
+            .points
+            .iter()
+            .map(|point| point.mapq)
+            .fold(max_mapq, u8::max);
+    }
+
+    // perfectly calibrated MAPQ values are on the diagonal
+    calibration_chart = calibration_chart.series(
+        Line::new()
+            .name("calibrated")
+            .show_symbol(false)
+            .item_style(ItemStyle::new().color("gray"))
+            .data(vec![vec![0.0, 0.0], vec![max_mapq as f64, max_mapq as f64]]),
+    );
+
+    save_chart(
+        curve_chart,
+        format!("{benchmark_name}_mapq_error_rate_curve"),
+        1600,
+        800,
+        benchmark_folder,
+        suite_config,
+    );
+    save_chart(
+        calibration_chart,
+        format!("{benchmark_name}_mapq_calibration"),
+        1200,
+        1000,
+        benchmark_folder,
+        suite_config,
+    );
+}
+
 pub fn plot_histogram_data_in_grid<'a, I, S1, S2>(
     iter: impl IntoIterator<Item = I>,
     title: &str,
@@ -506,10 +983,6 @@ pub fn create_floxer_vs_minimap_plots(
                 ),
         );
 
-    // 15478 minimap whre did I lose?
-    // 15513 floxer
-    // TODO what went wrong?
-
     save_chart(
         mapping_status_chart,
         "mapping_status_comparison".into(),
@@ -621,6 +1094,379 @@ pub fn create_floxer_vs_minimap_plots(
     );
 }
 
+// UpSet-style plot of which mappers mapped which queries and the alignment categories
+// of every mapper in every intersection
+pub fn plot_mapper_comparison(
+    comparison: &MapperComparison,
+    benchmark_name: &str,
+    benchmark_folder: &BenchmarkFolder,
+    suite_config: &BenchmarkSuiteConfig,
+) {
+    let num_mappers = comparison.mappers.len();
+    let intersection_ids: Vec<_> = (1..=comparison.intersections.len())
+        .map(|id| id.to_string())
+        .collect();
+
+    let mut member_dots = Vec::new();
+    let mut non_member_dots = Vec::new();
+    for (intersection_index, intersection) in comparison.intersections.iter().enumerate() {
+        for (mapper_index, mapper) in comparison.mappers.iter().enumerate() {
+            let dot = vec![intersection_index as i64, mapper_index as i64];
+            if intersection.mappers.contains(mapper) {
+                member_dots.push(dot);
+            } else {
+                non_member_dots.push(dot);
+            }
+        }
+    }
+
+    let upset_chart = Chart::new()
+        .title(
+            Title::new()
+                .text(format!(
+                    "{benchmark_name} mapped queries per set of mappers"
+                ))
+                .left("center"),
+        )
+        .background_color("white")
+        .grid(Grid::new().left("20%").right("5%").top("10%").bottom("40%"))
+        .grid(Grid::new().left("20%").right("5%").top("65%").bottom("5%"))
+        .x_axis(
+            Axis::new()
+                .grid_index(0)
+                .data(intersection_ids.clone())
+                .axis_label(AxisLabel::new().show(false)),
+        )
+        .y_axis(
+            Axis::new()
+                .grid_index(0)
+                .name("#Reads")
+                .name_text_style(TextStyle::new().font_size(20).color("black"))
+                .axis_label(AxisLabel::new().font_size(14).color("black")),
+        )
+        .x_axis(
+            Axis::new()
+                .grid_index(1)
+                .type_(AxisType::Category)
+                .data(intersection_ids)
+                .axis_label(AxisLabel::new().show(false)),
+        )
+        .y_axis(
+            Axis::new()
+                .grid_index(1)
+                .type_(AxisType::Category)
+                .data(comparison.mappers.clone())
+                .axis_label(AxisLabel::new().font_size(14).color("black")),
+        )
+        .series(
+            Bar::new()
+                .x_axis_index(0)
+                .y_axis_index(0)
+                .data(
+                    comparison
+                        .intersections
+                        .iter()
+                        .map(|intersection| intersection.num_queries)
+                        .collect(),
+                )
+                .label(
+                    Label::new()
+                        .show(true)
+                        .position(LabelPosition::Top)
+                        .font_size(LABEL_FONT_SIZE),
+                ),
+        )
+        .series(
+            Scatter::new()
+                .x_axis_index(1)
+                .y_axis_index(1)
+                .symbol_size(18)
+                .item_style(ItemStyle::new().color("black"))
+                .data(member_dots),
+        )
+        .series(
+            Scatter::new()
+                .x_axis_index(1)
+                .y_axis_index(1)
+                .symbol_size(18)
+                .item_style(ItemStyle::new().color("lightgray"))
+                .data(non_member_dots),
+        );
+
+    save_chart(
+        upset_chart,
+        format!("{benchmark_name}_mapper_intersections"),
+        (100 * comparison.intersections.len() as u32).max(800),
+        600 + 40 * num_mappers as u32,
+        benchmark_folder,
+        suite_config,
+    );
+
+    // one bar for every mapper in every (non-empty) intersection
+    let mut bar_names = Vec::new();
+    let mut bar_stats: Vec<&ScopedStats> = Vec::new();
+    for (intersection_index, intersection) in comparison.intersections.iter().enumerate() {
+        for (mapper, stats) in intersection.mappers.iter().zip(&intersection.stats) {
+            bar_names.push(format!("{}: {mapper}", intersection_index + 1));
+            bar_stats.push(stats);
+        }
+    }
+
+    let category_counts = |count: fn(&ScopedStats) -> i64| -> Vec<i64> {
+        bar_stats.iter().map(|stats| count(stats)).collect()
+    };
+    let categories = [
+        (
+            "linear simple mapped",
+            category_counts(|stats| stats.num_basic),
+        ),
+        (
+            "linear large clipping mapped",
+            category_counts(|stats| stats.num_best_significantly_clipped),
+        ),
+        (
+            "linear large error rate mapped",
+            category_counts(|stats| stats.num_best_high_edit_distance),
+        ),
+        (
+            "chimeric or inversion mapped",
+            category_counts(|stats| stats.num_best_chimeric_or_inversion),
+        ),
+    ];
+
+    let num_bars = bar_names.len();
+    let mut category_chart = Chart::new()
+        .title(
+            Title::new()
+                .text(format!(
+                    "{benchmark_name} alignment categories per set of mappers"
+                ))
+                .left("center"),
+        )
+        .legend(Legend::new().top("5%"))
+        .background_color("white")
+        .grid(Grid::new().top("15%").bottom("25%"))
+        .x_axis(
+            Axis::new()
+                .data(bar_names)
+                .axis_label(AxisLabel::new().rotate(45).font_size(14).color("black")),
+        )
+        .y_axis(
+            Axis::new()
+                .name("#Reads")
+                .name_text_style(TextStyle::new().font_size(20).color("black"))
+                .axis_label(AxisLabel::new().font_size(14).color("black")),
+        );
+
+    for (category_name, counts) in categories {
+        category_chart = category_chart.series(
+            Bar::new()
+                .stack("all")
+                .name(category_name)
+                .data(counts)
+                .label(Label::new().show(true).position(LabelPosition::Inside)),
+        );
+    }
+
+    save_chart(
+        category_chart,
+        format!("{benchmark_name}_mapper_intersection_categories"),
+        (60 * num_bars as u32).max(800),
+        800,
+        benchmark_folder,
+        suite_config,
+    );
+
+    let mut table = Table::new(
+        format!("{benchmark_name}_mapper_comparison"),
+        [
+            "Mapper",
+            "Mapped reads",
+            "Mapped [%]",
+            "Only by this mapper",
+            "Basic",
+            "Chimeric or inversion",
+            "Significantly clipped",
+            "High edit distance",
+            "Avg. error rate of basic",
+        ],
+    );
+
+    for (mapper, stats) in zip(&comparison.mappers, &comparison.stats_if_mapped) {
+        let num_only_by_this_mapper: i64 = comparison
+            .intersections
+            .iter()
+            .filter(|intersection| intersection.mappers == [mapper.as_str()])
+            .map(|intersection| intersection.num_queries)
+            .sum();
+
+        table = table.row(vec![
+            mapper.clone(),
+            stats.num_queries.to_string(),
+            format!(
+                "{:.1}",
+                100.0 * stats.num_queries as f64 / comparison.number_of_queries.max(1) as f64
+            ),
+            num_only_by_this_mapper.to_string(),
+            stats.num_basic.to_string(),
+            stats.num_best_chimeric_or_inversion.to_string(),
+            stats.num_best_significantly_clipped.to_string(),
+            stats.num_best_high_edit_distance.to_string(),
+            format!("{:.4}", stats.basic_alignments_average_error_rate),
+        ]);
+    }
+
+    table
+        .save(benchmark_folder, suite_config)
+        .expect("failed to save the mapper comparison table");
+}
+
+// McNemar's test on the mapped status and the Wilcoxon test on the edit distances of the reads
+// mapped by both, for every pair of mappers
+pub fn plot_read_significance(
+    comparison: &MapperComparison,
+    benchmark_name: &str,
+    benchmark_folder: &BenchmarkFolder,
+    suite_config: &BenchmarkSuiteConfig,
+) {
+    if comparison.paired_tests.is_empty() {
+        return;
+    }
+
+    plot_p_values(
+        comparison
+            .paired_tests
+            .iter()
+            .map(|tests| format!("{} vs {}", tests.first_mapper, tests.second_mapper))
+            .collect(),
+        [
+            (
+                "mapped (McNemar)",
+                comparison
+                    .paired_tests
+                    .iter()
+                    .map(|tests| tests.mapped.p_value)
+                    .collect(),
+            ),
+            (
+                "edit distance (Wilcoxon)",
+                comparison
+                    .paired_tests
+                    .iter()
+                    .map(|tests| tests.edit_distance.p_value)
+                    .collect(),
+            ),
+        ],
+        format!("{benchmark_name} per read significance"),
+        format!("{benchmark_name}_read_significance"),
+        benchmark_folder,
+        suite_config,
+    );
+}
+
+// Welch's t-test on the resource usage of the repetitions, for every pair of repeated instances
+pub fn plot_resource_metrics_significance(
+    comparison: &ResourceMetricsComparison,
+    benchmark_name: &str,
+    benchmark_folder: &BenchmarkFolder,
+    suite_config: &BenchmarkSuiteConfig,
+) {
+    let p_values = |test: fn(&ResourceMetricsTests) -> f64| -> Vec<f64> {
+        comparison.pairs.iter().map(test).collect()
+    };
+
+    plot_p_values(
+        comparison
+            .pairs
+            .iter()
+            .map(|tests| {
+                format!("{} vs {}", tests.first_instance, tests.second_instance).replace("_", " ")
+            })
+            .collect(),
+        [
+            (
+                "wall clock",
+                p_values(|tests| tests.wall_clock_seconds.p_value),
+            ),
+            ("cpu", p_values(|tests| tests.cpu_seconds.p_value)),
+            (
+                "peak memory",
+                p_values(|tests| tests.peak_memory_kilobytes.p_value),
+            ),
+        ],
+        format!("{benchmark_name} resource usage significance"),
+        format!("{benchmark_name}_resource_metrics_significance"),
+        benchmark_folder,
+        suite_config,
+    );
+}
+
+// -log10 of the p-values as bars, with a line at the usual significance level of 0.05
+fn plot_p_values<const N: usize>(
+    pair_names: Vec<String>,
+    p_values_per_test: [(&str, Vec<f64>); N],
+    title: String,
+    plot_name: String,
+    benchmark_folder: &BenchmarkFolder,
+    suite_config: &BenchmarkSuiteConfig,
+) {
+    let num_pairs = pair_names.len();
+
+    let mut chart = Chart::new()
+        .title(Title::new().text(title).left("center"))
+        .legend(Legend::new().top("5%"))
+        .background_color("white")
+        .grid(Grid::new().top("15%").bottom("25%"))
+        .x_axis(
+            Axis::new()
+                .data(pair_names)
+                .axis_label(AxisLabel::new().rotate(45).font_size(14).color("black")),
+        )
+        .y_axis(
+            Axis::new()
+                .name("-log10(p)")
+                .name_text_style(TextStyle::new().font_size(20).color("black"))
+                .axis_label(AxisLabel::new().font_size(14).color("black")),
+        );
+
+    for (test_name, p_values) in p_values_per_test {
+        chart = chart.series(
+            Bar::new()
+                .name(test_name)
+                .data(
+                    p_values
+                        .into_iter()
+                        // p-values can underflow to 0
+                        .map(|p_value| -p_value.max(f64::MIN_POSITIVE).log10())
+                        .collect(),
+                )
+                .label(
+                    Label::new()
+                        .show(true)
+                        .position(LabelPosition::Top)
+                        .formatter(Formatter::Function(JS_FLOAT_FORMATTER_2.into())),
+                ),
+        );
+    }
+
+    chart = chart.series(
+        Line::new()
+            .name("p = 0.05")
+            .show_symbol(false)
+            .item_style(ItemStyle::new().color("gray"))
+            .data(vec![-0.05f64.log10(); num_pairs]),
+    );
+
+    save_chart(
+        chart,
+        plot_name,
+        (150 * num_pairs as u32).max(800),
+        800,
+        benchmark_folder,
+        suite_config,
+    );
+}
+
 fn save_chart(
     chart: Chart,
     plot_name: String,
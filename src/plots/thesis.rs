@@ -7,7 +7,10 @@ use charming::{
     Chart,
 };
 
-use super::{save_chart, JS_FLOAT_FORMATTER, JS_FLOAT_FORMATTER_0};
+use super::{
+    latex::{BarFigure, BarPanel},
+    save_chart, JS_FLOAT_FORMATTER, JS_FLOAT_FORMATTER_0,
+};
 use crate::{benchmarks::BenchmarkResult, config::BenchmarkSuiteConfig};
 
 use std::iter::zip;
@@ -32,6 +35,16 @@ pub fn plot_query_lengths(
     let mut x_names = histogram.axis_names();
     x_names.resize_with(thresholds.len(), String::new);
 
+    let plot_name = String::from("thesis_query_lengths_real");
+
+    BarFigure::new(plot_name.clone())
+        .panel(
+            BarPanel::new("#Reads", &x_names)
+                .category_label("Query length")
+                .series("reads", values.iter().map(|&value| value as f64)),
+        )
+        .save(&benchmark_result.folder, suite_config)?;
+
     let chart = Chart::new()
         .x_axis(
             Axis::new()
@@ -43,7 +56,7 @@ pub fn plot_query_lengths(
 
     save_chart(
         chart,
-        String::from("thesis_query_lengths_real"),
+        plot_name,
         1200,
         800,
         &benchmark_result.folder,
@@ -91,6 +104,19 @@ pub fn plot_avg_num_anchors_per_seed_and_seed_lengths(
         .map(|res| res.benchmark_instance_name.replace("_", " "))
         .collect();
 
+    let plot_name = format!("thesis_{}_avg_num_anchors", benchmark_result.benchmark_name);
+
+    BarFigure::new(plot_name.clone())
+        .panel(BarPanel::per_instance(
+            "Avg. seed length",
+            zip(&instance_names, &seed_lengths).map(|(name, &value)| (name, value)),
+        ))
+        .panel(BarPanel::per_instance(
+            "Avg. #kept anchors per seed",
+            zip(&instance_names, &kept_anchors).map(|(name, &value)| (name, value)),
+        ))
+        .save(&benchmark_result.folder, suite_config)?;
+
     let offset_str = "54%";
 
     let mut chart = Chart::new()
@@ -152,7 +178,7 @@ pub fn plot_avg_num_anchors_per_seed_and_seed_lengths(
 
     save_chart(
         chart,
-        format!("thesis_{}_avg_num_anchors", benchmark_result.benchmark_name),
+        plot_name,
         1200,
         800,
         &benchmark_result.folder,
@@ -192,6 +218,22 @@ pub fn plot_num_mapped_and_avg_num_anchors(
         .map(|res| res.benchmark_instance_name.replace("_", " "))
         .collect();
 
+    let plot_name = format!(
+        "thesis_{}_avg_num_anchors_and_num_mapped",
+        benchmark_result.benchmark_name
+    );
+
+    BarFigure::new(plot_name.clone())
+        .panel(BarPanel::per_instance(
+            "#mapped reads",
+            zip(&instance_names, &num_mapped).map(|(name, &value)| (name, value as f64)),
+        ))
+        .panel(BarPanel::per_instance(
+            "Avg. #kept anchors per seed",
+            zip(&instance_names, &kept_anchors).map(|(name, &value)| (name, value)),
+        ))
+        .save(&benchmark_result.folder, suite_config)?;
+
     let offset_str = "54%";
 
     let mut chart = Chart::new()
@@ -252,10 +294,7 @@ pub fn plot_num_mapped_and_avg_num_anchors(
 
     save_chart(
         chart,
-        format!(
-            "thesis_{}_avg_num_anchors_and_num_mapped",
-            benchmark_result.benchmark_name
-        ),
+        plot_name,
         1200,
         800,
         &benchmark_result.folder,
@@ -353,6 +392,23 @@ pub fn plot_profiles(
 
     let times = vec![search_times, locate_times, verify_times, other_times];
 
+    let plot_name = format!(
+        "thesis_{}_profile_comparison",
+        benchmark_result.benchmark_name
+    );
+
+    BarFigure::new(plot_name.clone())
+        .panel(
+            zip(&names, &times).fold(
+                BarPanel::new("CPU Seconds", ["3", "2", "1", "0"])
+                    .category_label("seed errors")
+                    .stacked()
+                    .horizontal(),
+                |panel, (name, data)| panel.series(name, data.iter().copied()),
+            ),
+        )
+        .save(&benchmark_result.folder, suite_config)?;
+
     let mut chart = Chart::new()
         .background_color("white")
         .legend(Legend::new().text_style(TextStyle::new().font_size(20).color("black")))
@@ -381,10 +437,7 @@ pub fn plot_profiles(
 
     save_chart(
         chart,
-        format!(
-            "thesis_{}_profile_comparison",
-            benchmark_result.benchmark_name
-        ),
+        plot_name,
         1200,
         800,
         &benchmark_result.folder,
@@ -398,6 +451,44 @@ pub fn plot_for_soft_anchor_cap(
     benchmark_result: &BenchmarkResult,
     suite_config: &BenchmarkSuiteConfig,
 ) -> Result<()> {
+    let plot_name = String::from("soft_anchor_cap");
+
+    BarFigure::new(plot_name.clone())
+        .panel(BarPanel::per_instance(
+            "CPU time in seconds",
+            benchmark_result.floxer_results.iter().map(|res| {
+                (
+                    res.benchmark_instance_name.replace("_", " "),
+                    res.resource_metrics.user_cpu_seconds,
+                )
+            }),
+        ))
+        .panel(BarPanel::per_instance(
+            "Avg #kept anchors per seed",
+            benchmark_result.floxer_results.iter().map(|res| {
+                (
+                    res.benchmark_instance_name.replace("_", " "),
+                    res.stats
+                        .anchor_stats_per_seed
+                        .kept_anchors_per_kept_seed
+                        .descriptive_stats
+                        .as_ref()
+                        .unwrap()
+                        .mean,
+                )
+            }),
+        ))
+        .panel(BarPanel::per_instance(
+            "#mapped reads in 1000",
+            benchmark_result.floxer_results.iter().map(|res| {
+                (
+                    res.benchmark_instance_name.replace("_", " "),
+                    res.mapped_read_stats.num_mapped as f64 / 1000.0,
+                )
+            }),
+        ))
+        .save(&benchmark_result.folder, suite_config)?;
+
     let mut chart = Chart::new()
         .background_color("white")
         .legend(Legend::new().text_style(TextStyle::new().font_size(20).color("black")))
@@ -488,7 +579,7 @@ pub fn plot_for_soft_anchor_cap(
 
     save_chart(
         chart,
-        "soft_anchor_cap".into(),
+        plot_name,
         1200,
         800,
         &benchmark_result.folder,
@@ -505,30 +596,47 @@ pub fn plot_for_soft_anchor_cap_real(
 ) -> Result<()> {
     let soft_anchor_cap_values = vec!["5", "10", "20", "50", "100"];
 
-    let cpu_times_0 = benchmark_result_0
+    let cpu_times_0: Vec<f64> = benchmark_result_0
         .floxer_results
         .iter()
         .map(|res| res.resource_metrics.user_cpu_seconds)
         .collect();
 
-    let cpu_times_1 = benchmark_result_1
+    let cpu_times_1: Vec<f64> = benchmark_result_1
         .floxer_results
         .iter()
         .map(|res| res.resource_metrics.user_cpu_seconds)
         .collect();
 
-    let num_mapped_0 = benchmark_result_0
+    let num_mapped_0: Vec<i32> = benchmark_result_0
         .floxer_results
         .iter()
         .map(|res| res.mapped_read_stats.num_mapped)
         .collect();
 
-    let num_mapped_1 = benchmark_result_1
+    let num_mapped_1: Vec<i32> = benchmark_result_1
         .floxer_results
         .iter()
         .map(|res| res.mapped_read_stats.num_mapped)
         .collect();
 
+    let plot_name = String::from("soft_anchor_cap_real");
+
+    BarFigure::new(plot_name.clone())
+        .panel(
+            BarPanel::new("CPU Time in Seconds", &soft_anchor_cap_values)
+                .category_label("Soft Anchor Cap")
+                .series("0 seed errors", cpu_times_0.iter().copied())
+                .series("1 seed errors", cpu_times_1.iter().copied()),
+        )
+        .panel(
+            BarPanel::new("#Mapped Reads", &soft_anchor_cap_values)
+                .category_label("Soft Anchor Cap")
+                .series("0 seed errors", num_mapped_0.iter().map(|&num| num as f64))
+                .series("1 seed errors", num_mapped_1.iter().map(|&num| num as f64)),
+        )
+        .save(&benchmark_result_0.folder, suite_config)?;
+
     let chart = Chart::new()
         .background_color("white")
         .legend(Legend::new().text_style(TextStyle::new().font_size(20).color("black")))
@@ -625,7 +733,7 @@ pub fn plot_for_soft_anchor_cap_real(
 
     save_chart(
         chart,
-        "soft_anchor_cap_real".into(),
+        plot_name,
         1200,
         800,
         &benchmark_result_0.folder,
@@ -657,6 +765,22 @@ pub fn plot_cpu_times_and_num_mapped(
         .map(|res| res.benchmark_instance_name.replace("_", " "))
         .collect();
 
+    let plot_name = format!(
+        "{}_cpu_times_and_num_mapped",
+        benchmark_result.benchmark_name
+    );
+
+    BarFigure::new(plot_name.clone())
+        .panel(BarPanel::per_instance(
+            "CPU Seconds",
+            zip(&instance_names, &cpu_times).map(|(name, &value)| (name, value)),
+        ))
+        .panel(BarPanel::per_instance(
+            "#Mapped Reads",
+            zip(&instance_names, &num_mapped).map(|(name, &value)| (name, value as f64)),
+        ))
+        .save(&benchmark_result.folder, suite_config)?;
+
     let offset_str = "54%";
 
     let mut chart = Chart::new()
@@ -717,10 +841,7 @@ pub fn plot_cpu_times_and_num_mapped(
 
     save_chart(
         chart,
-        format!(
-            "{}_cpu_times_and_num_mapped",
-            benchmark_result.benchmark_name
-        ),
+        plot_name,
         1200,
         800,
         &benchmark_result.folder,
@@ -766,6 +887,26 @@ pub fn plot_cpu_times_and_seed_lengths_and_num_mapped(
         .map(|res| res.benchmark_instance_name.replace("_", " "))
         .collect();
 
+    let plot_name = format!(
+        "{}_cpu_times_and_seed_lengths_and_num_mapped",
+        benchmark_result.benchmark_name
+    );
+
+    BarFigure::new(plot_name.clone())
+        .panel(BarPanel::per_instance(
+            "CPU Seconds",
+            zip(&instance_names, &cpu_times).map(|(name, &value)| (name, value)),
+        ))
+        .panel(BarPanel::per_instance(
+            "Avg. Seed Lengths",
+            zip(&instance_names, &seed_lengths).map(|(name, &value)| (name, value)),
+        ))
+        .panel(BarPanel::per_instance(
+            "#Mapped Reads",
+            zip(&instance_names, &num_mapped).map(|(name, &value)| (name, value as f64)),
+        ))
+        .save(&benchmark_result.folder, suite_config)?;
+
     let mut chart = Chart::new()
         .background_color("white")
         .legend(Legend::new().text_style(TextStyle::new().font_size(20).color("black")))
@@ -851,10 +992,7 @@ pub fn plot_cpu_times_and_seed_lengths_and_num_mapped(
 
     save_chart(
         chart,
-        format!(
-            "{}_cpu_times_and_seed_lengths_and_num_mapped",
-            benchmark_result.benchmark_name
-        ),
+        plot_name,
         1200,
         800,
         &benchmark_result.folder,
@@ -908,6 +1046,26 @@ pub fn plot_seed_errors_and_num_mapped_and_num_seeds(
         .map(|res| res.benchmark_instance_name.replace("_", " "))
         .collect();
 
+    let plot_name = format!(
+        "{}_num_errors_num_seeds_and_num_mapped",
+        benchmark_result.benchmark_name
+    );
+
+    BarFigure::new(plot_name.clone())
+        .panel(BarPanel::per_instance(
+            "Avg. #Seed Length",
+            zip(&instance_names, &seed_errors).map(|(name, &value)| (name, value)),
+        ))
+        .panel(BarPanel::per_instance(
+            "Avg. #Seeds Per Read",
+            zip(&instance_names, &num_seeds).map(|(name, &value)| (name, value)),
+        ))
+        .panel(BarPanel::per_instance(
+            "#Mapped Reads",
+            zip(&instance_names, &num_mapped).map(|(name, &value)| (name, value as f64)),
+        ))
+        .save(&benchmark_result.folder, suite_config)?;
+
     let mut chart = Chart::new()
         .background_color("white")
         .legend(Legend::new().text_style(TextStyle::new().font_size(20).color("black")))
@@ -992,10 +1150,7 @@ pub fn plot_seed_errors_and_num_mapped_and_num_seeds(
 
     save_chart(
         chart,
-        format!(
-            "{}_num_errors_num_seeds_and_num_mapped",
-            benchmark_result.benchmark_name
-        ),
+        plot_name,
         1200,
         800,
         &benchmark_result.folder,
@@ -1028,6 +1183,19 @@ pub fn plot_two_different_cpu_times(
         .unwrap()
         .push_str(" extra verification ratio");
 
+    let plot_name = format!("{}_cpu_times", benchmark_result.benchmark_name);
+
+    BarFigure::new(plot_name.clone())
+        .panel(zip(&instance_names, &cpu_times).fold(
+            BarPanel::new("CPU Seconds", ["Reduced Output Mode"]),
+            |panel, (name, &cpu_time)| panel.series(name, [cpu_time]),
+        ))
+        .panel(zip(&instance_names, &full_output_cpu_times).fold(
+            BarPanel::new("CPU Seconds", ["Full Output Mode"]),
+            |panel, (name, &cpu_time)| panel.series(name, [cpu_time]),
+        ))
+        .save(&benchmark_result.folder, suite_config)?;
+
     let offset_str = "54%";
 
     let mut chart = Chart::new()
@@ -1100,7 +1268,7 @@ pub fn plot_two_different_cpu_times(
 
     save_chart(
         chart,
-        format!("{}_cpu_times", benchmark_result.benchmark_name),
+        plot_name,
         1200,
         800,
         &benchmark_result.folder,
@@ -1144,6 +1312,26 @@ pub fn plot_cpu_times_and_num_root_alignments_and_num_mapped(
         .map(|res| res.benchmark_instance_name.replace("_", " "))
         .collect();
 
+    let plot_name = format!(
+        "{}_cpu_times_and_num_alignments_and_num_mapped",
+        benchmark_result.benchmark_name
+    );
+
+    BarFigure::new(plot_name.clone())
+        .panel(BarPanel::per_instance(
+            "CPU Seconds",
+            zip(&instance_names, &cpu_times).map(|(name, &value)| (name, value)),
+        ))
+        .panel(BarPanel::per_instance(
+            "#Long-Read Alignments in 1000",
+            zip(&instance_names, &num_root_alignments).map(|(name, &value)| (name, value as f64)),
+        ))
+        .panel(BarPanel::per_instance(
+            "#Mapped Reads",
+            zip(&instance_names, &num_mapped).map(|(name, &value)| (name, value as f64)),
+        ))
+        .save(&benchmark_result.folder, suite_config)?;
+
     let mut chart = Chart::new()
         .background_color("white")
         .legend(Legend::new().text_style(TextStyle::new().font_size(20).color("black")))
@@ -1229,10 +1417,7 @@ pub fn plot_cpu_times_and_num_root_alignments_and_num_mapped(
 
     save_chart(
         chart,
-        format!(
-            "{}_cpu_times_and_num_alignments_and_num_mapped",
-            benchmark_result.benchmark_name
-        ),
+        plot_name,
         1400,
         800,
         &benchmark_result.folder,
@@ -1277,6 +1462,22 @@ pub fn plot_avg_anchors_per_query_and_num_mapped(
         .unwrap()
         .push_str(" seed sampling step size");
 
+    let plot_name = format!(
+        "{}_avg_anchors_and_num_mapped",
+        benchmark_result.benchmark_name
+    );
+
+    BarFigure::new(plot_name.clone())
+        .panel(BarPanel::per_instance(
+            "Avg. #Anchors Per Read",
+            zip(&instance_names, &avg_seeds_per_query).map(|(name, &value)| (name, value)),
+        ))
+        .panel(BarPanel::per_instance(
+            "#Mapped Reads",
+            zip(&instance_names, &num_mapped).map(|(name, &value)| (name, value as f64)),
+        ))
+        .save(&benchmark_result.folder, suite_config)?;
+
     let offset_str = "54%";
 
     let mut chart = Chart::new()
@@ -1339,10 +1540,7 @@ pub fn plot_avg_anchors_per_query_and_num_mapped(
 
     save_chart(
         chart,
-        format!(
-            "{}_avg_anchors_and_num_mapped",
-            benchmark_result.benchmark_name
-        ),
+        plot_name,
         1200,
         800,
         &benchmark_result.folder,
@@ -1,28 +1,52 @@
 use crate::{
+    alignment_files::{self, AlignmentsPerQuery},
+    alignment_validation::validate_alignments,
     analyze_mapped_reads::{
-        analyze_alignments_simple, verify_simulated_dataset, SimpleMappedReadsStats,
+        analyze_alignments_simple,
+        conformance::check_conformance,
+        length_bins::{analyze_by_query_length, read_query_timings, LengthBinnedStats, LengthBins},
+        mapq_calibration::MapqCalibration,
+        oracle::{compare_with_oracle, exhaustive_oracle},
+        read_instance_alignments, verify_simulated_dataset, SimpleMappedReadsStats,
+        VerificationCounts,
     },
     benchmarks::ProfileConfig,
     cli::BenchmarkConfig,
     config::BenchmarkSuiteConfig,
     folder_structure::{BenchmarkFolder, BenchmarkInstanceFolder},
+    results_database::{self, InstanceRecord},
 };
 
-use std::{fs, process::Command};
+use std::{fs, path::PathBuf, process::Command, rc::Rc};
 
-use super::{IndexStrategy, Queries, Reference, ResourceMetrics};
+use super::{
+    floxer_command::{FloxerCapabilities, FloxerCommand},
+    IndexStrategy, Queries, Reference, ResourceMetrics,
+};
 use anyhow::{bail, Result};
 use clap::ValueEnum;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use strum::{Display, EnumIter};
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum QueryErrors {
     Exact(u16),
     Rate(f64),
 }
 
-#[derive(Debug, Copy, Clone, EnumIter, Display)]
+impl QueryErrors {
+    // rounded up, so that no alignment that floxer might consider valid is missed
+    pub fn max_errors(&self, query_length: usize) -> usize {
+        match self {
+            QueryErrors::Exact(num_errors) => *num_errors as usize,
+            QueryErrors::Rate(error_rate) => (query_length as f64 * error_rate).ceil() as usize,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, EnumIter, Display, Serialize)]
+#[serde(rename_all = "snake_case")]
 #[strum(serialize_all = "snake_case")]
 pub enum AnchorGroupOrder {
     ErrorsFirst,
@@ -30,7 +54,8 @@ pub enum AnchorGroupOrder {
     None,
 }
 
-#[derive(Debug, Copy, Clone, EnumIter, Display)]
+#[derive(Debug, Copy, Clone, EnumIter, Display, Serialize)]
+#[serde(rename_all = "snake_case")]
 #[strum(serialize_all = "snake_case")]
 pub enum AnchorChoiceStrategy {
     RoundRobin,
@@ -38,14 +63,16 @@ pub enum AnchorChoiceStrategy {
     FirstReported,
 }
 
-#[derive(Debug, Copy, Clone, EnumIter, Display)]
+#[derive(Debug, Copy, Clone, EnumIter, Display, Serialize)]
+#[serde(rename_all = "snake_case")]
 #[strum(serialize_all = "snake_case")]
 pub enum PexTreeConstruction {
     TopDown,
     BottomUp,
 }
 
-#[derive(Debug, Copy, Clone, EnumIter, Display)]
+#[derive(Debug, Copy, Clone, EnumIter, Display, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum IntervalOptimization {
     #[strum(serialize = "interval_optimization_on")]
     On,
@@ -53,7 +80,8 @@ pub enum IntervalOptimization {
     Off,
 }
 
-#[derive(Debug, Copy, Clone, EnumIter, Display)]
+#[derive(Debug, Copy, Clone, EnumIter, Display, Serialize)]
+#[serde(rename_all = "snake_case")]
 #[strum(serialize_all = "snake_case")]
 pub enum VerificationAlgorithm {
     DirectFull,
@@ -67,20 +95,22 @@ pub enum StatsInputHint {
     Simulated,
 }
 
-#[derive(Debug, Copy, Clone, EnumIter, Display)]
+#[derive(Debug, Copy, Clone, EnumIter, Display, Serialize)]
+#[serde(rename_all = "snake_case")]
 #[strum(serialize_all = "snake_case")]
 pub enum EraseUselessAnchors {
     On,
     Off,
 }
 
-#[derive(Debug, Copy, Clone, ValueEnum)]
+#[derive(Debug, Copy, Clone, ValueEnum, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum CigarOutput {
     On,
     Off,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct FloxerAlgorithmConfig {
     pub index_strategy: IndexStrategy,
     pub query_errors: QueryErrors,
@@ -133,19 +163,31 @@ pub struct FloxerConfig {
     pub reference: Reference,
     pub queries: Queries,
     pub only_analysis: bool,
+    pub position_tolerance: u64,
+    pub recompute_edit_distances: bool,
+    pub skip_reference_loading: bool,
+    pub length_bins: LengthBins,
+    pub exhaustive_oracle: bool,
     pub algorithm_config: FloxerAlgorithmConfig,
     pub cigar_output: CigarOutput,
+    pub repetitions: u16,
 }
 
 impl From<&BenchmarkConfig> for FloxerConfig {
     fn from(value: &BenchmarkConfig) -> Self {
         FloxerConfig {
             name: "unnamed_instance".into(),
-            reference: value.reference,
-            queries: value.queries,
+            reference: value.reference.clone(),
+            queries: value.queries.clone(),
             only_analysis: value.only_analysis,
+            position_tolerance: value.position_tolerance,
+            recompute_edit_distances: value.recompute_edit_distances,
+            skip_reference_loading: value.skip_reference_loading,
+            length_bins: value.length_bins.clone(),
+            exhaustive_oracle: value.exhaustive_oracle,
             algorithm_config: Default::default(),
             cigar_output: value.cigar_output,
+            repetitions: value.repetitions,
         }
     }
 }
@@ -159,48 +201,171 @@ impl FloxerConfig {
         suite_config: &BenchmarkSuiteConfig,
         profile_config: ProfileConfig,
     ) -> Result<FloxerRunResult> {
-        let instance_folder =
-            if self.only_analysis && benchmark_folder.most_recect_previous_run_folder().exists() {
-                BenchmarkInstanceFolder::most_recent_previous_run(benchmark_folder, &self.name)?
-            } else {
-                let instance_folder = BenchmarkInstanceFolder::new(benchmark_folder, &self.name)?;
+        let instance_folder = if self.only_analysis
+            && benchmark_folder.most_recect_previous_run_folder().exists()
+        {
+            BenchmarkInstanceFolder::most_recent_previous_run(benchmark_folder, &self.name)?
+        } else {
+            let instance_folder = BenchmarkInstanceFolder::new(benchmark_folder, &self.name)?;
 
+            // only the last repetition is profiled and analyzed
+            for repetition in 1..self.repetitions {
                 self.actually_run(
-                    profile_config,
+                    ProfileConfig::Off,
                     &instance_folder,
                     suite_config,
                     benchmark_name,
                 )?;
+                fs::rename(
+                    &instance_folder.timing_path,
+                    instance_folder.repetition_timing_path(repetition),
+                )?;
+            }
 
-                benchmark_folder.create_or_update_link_to_most_recent()?;
+            self.actually_run(
+                profile_config,
+                &instance_folder,
+                suite_config,
+                benchmark_name,
+            )?;
+            super::save_binary_version(
+                &instance_folder,
+                &FloxerCapabilities::probe_once(&suite_config.readmapper_binaries.floxer)?.version,
+            )?;
 
-                instance_folder
-            };
+            benchmark_folder.create_or_update_link_to_most_recent()?;
+
+            instance_folder
+        };
 
         if let ProfileConfig::On = profile_config {
             create_profile(&instance_folder)?;
         }
 
-        let stats_file_str = fs::read_to_string(instance_folder.stats_path)?;
+        let stats_file_str = fs::read_to_string(&instance_folder.stats_path)?;
         let stats: FloxerStats = toml::from_str(&stats_file_str)?;
 
-        let timings_file_str = fs::read_to_string(instance_folder.timing_path)?;
+        let timings_file_str = fs::read_to_string(&instance_folder.timing_path)?;
         let resource_metrics: ResourceMetrics = toml::from_str(&timings_file_str)?;
+        let repeated_resource_metrics = super::read_repeated_resource_metrics(&instance_folder)?;
+
+        // shared with the other instances of this run that use the same reference
+        let reference = if self.skip_reference_loading {
+            None
+        } else {
+            Some(alignment_files::load_reference(
+                self.reference.path(suite_config)?,
+            )?)
+        };
 
-        let mapped_read_stats = analyze_alignments_simple(&instance_folder.mapped_reads_bam_path)?;
+        let (alignments_per_query, edit_distance_check) = read_instance_alignments(
+            &instance_folder.mapped_reads_bam_path,
+            self.recompute_edit_distances,
+            reference.as_deref(),
+        )?;
+        let mapped_read_stats =
+            analyze_alignments_simple(&alignments_per_query, edit_distance_check);
+        mapped_read_stats.save(&instance_folder.mapped_reads_stats_path)?;
+
+        // older floxer builds and runs don't have the per query stats
+        let query_timings = if instance_folder.query_stats_path.exists() {
+            Some(read_query_timings(&instance_folder.query_stats_path)?)
+        } else {
+            None
+        };
+        let length_binned_stats = analyze_by_query_length(
+            &alignments_per_query,
+            self.queries.path(suite_config)?,
+            &self.length_bins,
+            query_timings.as_ref(),
+        )?;
+        length_binned_stats.save(&instance_folder.length_binned_stats_path)?;
+
+        let conformance_report = check_conformance(
+            &instance_folder.mapped_reads_bam_path,
+            self.reference.path(suite_config)?,
+        )?;
+        conformance_report.print();
+        conformance_report.save(&instance_folder.conformance_report_path)?;
+
+        if let Some(reference) = &reference {
+            let validation_report = validate_alignments(
+                &instance_folder.mapped_reads_bam_path,
+                reference,
+                self.algorithm_config.query_errors,
+            )?;
+            validation_report.print();
+            validation_report.save(&instance_folder.alignment_validation_path)?;
+        } else {
+            println!("- Skipped the alignment validation, because the reference is not loaded");
+        }
+
+        let oracle = if self.exhaustive_oracle {
+            exhaustive_oracle(
+                &self.reference,
+                &self.queries,
+                self.algorithm_config.query_errors,
+                suite_config,
+            )?
+        } else {
+            None
+        };
+        if let Some(oracle) = oracle {
+            let oracle_comparison =
+                compare_with_oracle(&instance_folder.mapped_reads_bam_path, &oracle)?;
 
-        if self.queries == Queries::Simulated && self.reference == Reference::Simulated {
-            let verification_summary =
-                verify_simulated_dataset(&instance_folder.mapped_reads_bam_path, suite_config)?;
+            oracle_comparison.print();
+            oracle_comparison.save(&instance_folder.oracle_comparison_path)?;
+        }
 
-            verification_summary.print_if_missed();
+        let mut simulated_verification_counts = None;
+        let mut mapq_calibration = None;
+        if let Some(truth) = super::simulated_truth(&self.queries, &self.reference, suite_config) {
+            let verification_summary = verify_simulated_dataset(
+                &instance_folder.mapped_reads_bam_path,
+                truth,
+                self.position_tolerance,
+                suite_config,
+            )?;
+
+            verification_summary.print();
+            verification_summary.save(&instance_folder.simulated_verification_path)?;
+            simulated_verification_counts = Some(verification_summary.all_reads);
+            mapq_calibration = verification_summary.mapq_calibration;
         }
 
+        results_database::record_instance(
+            InstanceRecord {
+                benchmark_folder,
+                instance_folder: &instance_folder,
+                instance_name: &self.name,
+                mapper: "floxer",
+                reference: &self.reference,
+                queries: &self.queries,
+                parameters: serde_json::to_string(&serde_json::json!({
+                    "algorithm_config": self.algorithm_config,
+                    "cigar_output": self.cigar_output,
+                }))?,
+                binary_version: super::read_binary_version(&instance_folder)?,
+                resource_metrics: &resource_metrics,
+                mapped_read_stats: &mapped_read_stats,
+                length_binned_stats: &length_binned_stats,
+                simulated_verification_counts: simulated_verification_counts.as_ref(),
+            },
+            suite_config,
+        )?;
+
         Ok(FloxerRunResult {
             benchmark_instance_name: self.name.clone(),
             stats,
             resource_metrics,
+            repeated_resource_metrics,
             mapped_read_stats,
+            length_binned_stats,
+            simulated_verification_counts,
+            mapq_calibration,
+            mapped_reads_path: instance_folder.mapped_reads_bam_path,
+            alignments_per_query: Rc::new(alignments_per_query),
         })
     }
 
@@ -231,88 +396,26 @@ impl FloxerConfig {
         };
 
         super::add_time_args(&mut command, &instance_folder.timing_path);
-        let reference_path = self.reference.path(suite_config);
-        let queries_path = self.queries.path(suite_config);
-
-        command
-            .arg(&suite_config.readmapper_binaries.floxer)
-            .arg("--reference")
-            .arg(reference_path)
-            .arg("--queries")
-            .arg(queries_path)
-            .arg("--output")
-            .arg(&instance_folder.mapped_reads_bam_path)
-            .arg("--logfile")
-            .arg(&instance_folder.logfile_path)
-            .arg("--stats")
-            .arg(&instance_folder.stats_path);
+
+        let mut floxer_command = FloxerCommand::new(&suite_config.readmapper_binaries.floxer)
+            .reference(self.reference.path(suite_config)?)
+            .queries(self.queries.path(suite_config)?)
+            .output(&instance_folder.mapped_reads_bam_path)
+            .logfile(&instance_folder.logfile_path)
+            .stats(&instance_folder.stats_path)
+            .query_stats(&instance_folder.query_stats_path)
+            .algorithm_config(&self.algorithm_config)
+            .stats_input_hint(self.queries.floxer_stats_input_hint(suite_config))
+            .cigar_output(self.cigar_output);
 
         if self.algorithm_config.index_strategy == IndexStrategy::ReadFromDiskIfStored {
             let mut index_path = suite_config.index_folder();
-            let index_file_name = format!("floxer-index-{}.flxi", self.reference);
-            index_path.push(index_file_name);
-
-            command.arg("--index");
-            command.arg(index_path);
-        }
+            index_path.push(index_file_name(&self.reference));
 
-        match self.algorithm_config.query_errors {
-            QueryErrors::Exact(num_errors) => {
-                command.args(["--query-errors", &num_errors.to_string()]);
-            }
-            QueryErrors::Rate(error_ratio) => {
-                command.args(["--error-probability", &error_ratio.to_string()]);
-            }
-        }
-
-        command.args([
-            "--seed-errors",
-            &self.algorithm_config.pex_seed_errors.to_string(),
-            "--max-anchors-hard",
-            &self.algorithm_config.max_num_anchors_hard.to_string(),
-            "--max-anchors-soft",
-            &self.algorithm_config.max_num_anchors_soft.to_string(),
-            "--anchor-group-order",
-            &self.algorithm_config.anchor_group_order.to_string(),
-            "--anchor-choice-strategy",
-            &self.algorithm_config.anchor_choice_strategy.to_string(),
-            "--seed-sampling-step-size",
-            &self.algorithm_config.seed_sampling_step_size.to_string(),
-            "--extra-verification-ratio",
-            &self.algorithm_config.extra_verification_ratio.to_string(),
-            "--threads",
-            &self.algorithm_config.num_threads.to_string(),
-            "--num-anchors-per-task",
-            &self
-                .algorithm_config
-                .num_anchors_per_verification_task
-                .to_string(),
-        ]);
-
-        if let PexTreeConstruction::BottomUp = self.algorithm_config.pex_tree_construction {
-            command.arg("--bottom-up-pex-tree");
-        }
-
-        if let IntervalOptimization::On = self.algorithm_config.interval_optimization {
-            command.args(["--interval-optimization"]);
+            floxer_command = floxer_command.index(index_path);
         }
 
-        if let VerificationAlgorithm::DirectFull = self.algorithm_config.verification_algorithm {
-            command.arg("--direct-full-verification");
-        }
-
-        if let EraseUselessAnchors::Off = self.algorithm_config.erase_useless_anchors {
-            command.arg("--dont-erase-useless-anchors");
-        }
-
-        if let Some(stats_input_hint) = self.queries.floxer_stats_input_hint() {
-            command.arg("--stats-input-hint");
-            command.arg(stats_input_hint.to_string());
-        }
-
-        if let CigarOutput::Off = self.cigar_output {
-            command.arg("--without-cigar");
-        }
+        command.args(floxer_command.build()?);
 
         println!(
             "- Running the benchmark: {}",
@@ -338,6 +441,11 @@ impl FloxerConfig {
     }
 }
 
+// stored in the index folder of the suite
+pub fn index_file_name(reference: &Reference) -> String {
+    format!("floxer-index-{reference}.flxi")
+}
+
 fn create_profile(instance_folder: &BenchmarkInstanceFolder) -> Result<()> {
     let flamegraph_output = Command::new("flamegraph")
         .arg("--deterministic")
@@ -357,15 +465,25 @@ fn create_profile(instance_folder: &BenchmarkInstanceFolder) -> Result<()> {
     Ok(())
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct FloxerRunResult {
     pub benchmark_instance_name: String,
     pub stats: FloxerStats,
     pub resource_metrics: ResourceMetrics,
+    // of all repetitions, including the last one
+    pub repeated_resource_metrics: Vec<ResourceMetrics>,
     pub mapped_read_stats: SimpleMappedReadsStats,
+    pub length_binned_stats: LengthBinnedStats,
+    // only for simulated datasets
+    pub simulated_verification_counts: Option<VerificationCounts>,
+    pub mapq_calibration: Option<MapqCalibration>,
+    pub mapped_reads_path: PathBuf,
+    // for the comparisons of the benchmark, so that they don't have to read the output again
+    #[serde(skip)]
+    pub alignments_per_query: Rc<AlignmentsPerQuery>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct FloxerStats {
     pub query_lengths: HistogramData,
     #[serde(flatten)]
@@ -406,7 +524,7 @@ impl FloxerStats {
     }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct SeedStats {
     pub seed_lengths: HistogramData,
     pub errors_per_seed: HistogramData,
@@ -428,7 +546,7 @@ impl SeedStats {
     }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct AnchorStatsPerQuery {
     pub completely_excluded_queries: usize,
     pub fully_excluded_seeds_per_query: HistogramData,
@@ -459,7 +577,7 @@ impl AnchorStatsPerQuery {
     }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct AnchorStatsPerSeed {
     pub kept_anchors_per_kept_seed: HistogramData,
     pub excluded_raw_anchors_by_soft_cap_per_kept_seed: HistogramData,
@@ -486,7 +604,7 @@ impl AnchorStatsPerSeed {
     }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct AlignmentStats {
     pub reference_span_sizes_aligned_of_inner_nodes: HistogramData,
     pub reference_span_sizes_aligned_of_roots: HistogramData,
@@ -513,7 +631,7 @@ impl AlignmentStats {
     }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct HistogramData {
     pub num_values: usize,
     pub thresholds: Vec<usize>,
@@ -523,6 +641,38 @@ pub struct HistogramData {
 }
 
 impl HistogramData {
+    // same binning as floxer: a value belongs to the first threshold that is at least as large,
+    // the last bin contains all values that are larger than every threshold
+    pub fn from_values(values: impl IntoIterator<Item = usize>, thresholds: Vec<usize>) -> Self {
+        let mut occurrences = vec![0; thresholds.len() + 1];
+        let mut num_values = 0;
+        let mut sum = 0;
+        let mut min_value = usize::MAX;
+        let mut max_value = 0;
+
+        for value in values {
+            occurrences[thresholds.partition_point(|threshold| *threshold < value)] += 1;
+
+            num_values += 1;
+            sum += value;
+            min_value = min_value.min(value);
+            max_value = max_value.max(value);
+        }
+
+        let descriptive_stats = (num_values > 0).then(|| DescriptiveStats {
+            min_value,
+            mean: sum as f64 / num_values as f64,
+            max_value,
+        });
+
+        Self {
+            num_values,
+            thresholds,
+            occurrences,
+            descriptive_stats,
+        }
+    }
+
     pub fn axis_names(&self) -> Vec<String> {
         self.thresholds
             .iter()
@@ -536,7 +686,7 @@ impl HistogramData {
     }
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct DescriptiveStats {
     pub min_value: usize,
     pub mean: f64,
@@ -0,0 +1,548 @@
+use std::{
+    collections::HashSet,
+    ffi::OsString,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::OnceLock,
+};
+
+use anyhow::{bail, Context, Result};
+
+use super::floxer::{
+    CigarOutput, EraseUselessAnchors, FloxerAlgorithmConfig, IntervalOptimization,
+    PexTreeConstruction, QueryErrors, StatsInputHint, VerificationAlgorithm,
+};
+
+// older floxer builds used different names for some flags. If the binary does not know
+// the current name, the first supported alias is used instead (with a warning).
+const FLAG_ALIASES: &[(&str, &[&str])] = &[
+    ("--max-anchors-hard", &["--max-anchors"]),
+    ("--seed-errors", &["--pex-seed-errors"]),
+];
+
+// these flags don't influence the mapping result, so they may be dropped for old builds
+const DROPPABLE_FLAGS: &[&str] = &[
+    "--logfile",
+    "--query-stats",
+    "--stats-input-hint",
+    "--num-anchors-per-task",
+];
+
+// queries of the long read datasets are usually at least about this long
+const MIN_LONG_READ_LENGTH: usize = 1_000;
+
+static FLOXER_CAPABILITIES: OnceLock<FloxerCapabilities> = OnceLock::new();
+
+#[derive(Debug)]
+pub struct FloxerCapabilities {
+    pub binary: PathBuf,
+    pub version: String,
+    supported_flags: HashSet<String>,
+}
+
+impl FloxerCapabilities {
+    // floxer is only probed once per run of this program
+    pub fn probe_once(floxer_binary: &Path) -> Result<&'static Self> {
+        if let Some(capabilities) = FLOXER_CAPABILITIES.get() {
+            if capabilities.binary != floxer_binary {
+                bail!(
+                    "floxer was already probed at {}, but now {} is used",
+                    capabilities.binary.display(),
+                    floxer_binary.display()
+                );
+            }
+
+            return Ok(capabilities);
+        }
+
+        let capabilities = Self::probe(floxer_binary)?;
+
+        Ok(FLOXER_CAPABILITIES.get_or_init(|| capabilities))
+    }
+
+    fn probe(floxer_binary: &Path) -> Result<Self> {
+        let help_output = Command::new(floxer_binary)
+            .arg("--help")
+            .output()
+            .with_context(|| format!("running {} --help", floxer_binary.display()))?;
+
+        if !help_output.status.success() {
+            bail!(
+                "floxer --help failed with stderr: {}",
+                String::from_utf8_lossy(&help_output.stderr)
+            );
+        }
+
+        let help_text = String::from_utf8_lossy(&help_output.stdout);
+        let supported_flags = parse_long_flags(&help_text);
+
+        if supported_flags.is_empty() {
+            bail!("could not find any flags in the output of floxer --help");
+        }
+
+        // very old builds don't have a --version flag
+        let version = match Command::new(floxer_binary).arg("--version").output() {
+            Ok(output) if output.status.success() => {
+                String::from_utf8_lossy(&output.stdout).trim().to_owned()
+            }
+            _ => String::from("unknown"),
+        };
+
+        Ok(Self {
+            binary: floxer_binary.to_owned(),
+            version,
+            supported_flags,
+        })
+    }
+
+    pub fn supports(&self, flag: &str) -> bool {
+        self.supported_flags.contains(flag)
+    }
+
+    // returns the name under which the flag should be passed to this floxer build
+    fn resolve(&self, flag: &'static str) -> Option<&'static str> {
+        if self.supports(flag) {
+            return Some(flag);
+        }
+
+        let alias = FLAG_ALIASES
+            .iter()
+            .find(|(current, _)| *current == flag)
+            .and_then(|(_, aliases)| aliases.iter().find(|alias| self.supports(alias)))?;
+
+        println!(
+            "Warning: floxer ({}) does not support {flag}, using {alias} instead",
+            self.version
+        );
+
+        Some(alias)
+    }
+}
+
+fn parse_long_flags(help_text: &str) -> HashSet<String> {
+    help_text
+        .split(|c: char| !(c.is_ascii_alphanumeric() || c == '-'))
+        .filter(|token| {
+            token.len() > 2
+                && token.starts_with("--")
+                && token[2..].starts_with(|c: char| c.is_ascii_alphanumeric())
+        })
+        .map(ToOwned::to_owned)
+        .collect()
+}
+
+// Typed builder for the floxer command line. The values are validated and the flags are
+// checked against the capabilities of the given floxer binary before anything is run.
+#[derive(Debug)]
+pub struct FloxerCommand<'a> {
+    binary: &'a Path,
+    reference: Option<&'a Path>,
+    queries: Option<&'a Path>,
+    output: Option<&'a Path>,
+    logfile: Option<&'a Path>,
+    stats: Option<&'a Path>,
+    query_stats: Option<&'a Path>,
+    index: Option<PathBuf>,
+    algorithm_config: Option<&'a FloxerAlgorithmConfig>,
+    stats_input_hint: Option<StatsInputHint>,
+    cigar_output: CigarOutput,
+}
+
+impl<'a> FloxerCommand<'a> {
+    pub fn new(binary: &'a Path) -> Self {
+        Self {
+            binary,
+            reference: None,
+            queries: None,
+            output: None,
+            logfile: None,
+            stats: None,
+            query_stats: None,
+            index: None,
+            algorithm_config: None,
+            stats_input_hint: None,
+            cigar_output: CigarOutput::On,
+        }
+    }
+
+    pub fn reference(mut self, path: &'a Path) -> Self {
+        self.reference = Some(path);
+        self
+    }
+
+    pub fn queries(mut self, path: &'a Path) -> Self {
+        self.queries = Some(path);
+        self
+    }
+
+    pub fn output(mut self, path: &'a Path) -> Self {
+        self.output = Some(path);
+        self
+    }
+
+    pub fn logfile(mut self, path: &'a Path) -> Self {
+        self.logfile = Some(path);
+        self
+    }
+
+    pub fn stats(mut self, path: &'a Path) -> Self {
+        self.stats = Some(path);
+        self
+    }
+
+    // per query TSV with the search and verification times, only written by newer floxer builds
+    pub fn query_stats(mut self, path: &'a Path) -> Self {
+        self.query_stats = Some(path);
+        self
+    }
+
+    pub fn index(mut self, path: PathBuf) -> Self {
+        self.index = Some(path);
+        self
+    }
+
+    pub fn algorithm_config(mut self, algorithm_config: &'a FloxerAlgorithmConfig) -> Self {
+        self.algorithm_config = Some(algorithm_config);
+        self
+    }
+
+    pub fn stats_input_hint(mut self, stats_input_hint: Option<StatsInputHint>) -> Self {
+        self.stats_input_hint = stats_input_hint;
+        self
+    }
+
+    pub fn cigar_output(mut self, cigar_output: CigarOutput) -> Self {
+        self.cigar_output = cigar_output;
+        self
+    }
+
+    // returns the floxer binary followed by all of its arguments
+    pub fn build(self) -> Result<Vec<OsString>> {
+        let Some(algorithm_config) = self.algorithm_config else {
+            bail!("floxer command is missing the algorithm config");
+        };
+
+        validate_algorithm_config(algorithm_config)?;
+
+        // not an error, because the datasets might not contain such short queries
+        if let Some(min_query_length) = min_query_length_for_seed_errors(algorithm_config)
+            .filter(|min_query_length| *min_query_length > MIN_LONG_READ_LENGTH)
+        {
+            println!(
+                "- Warning: floxer PEX seed errors ({}) exceed the query errors of queries \
+                shorter than {min_query_length}",
+                algorithm_config.pex_seed_errors
+            );
+        }
+
+        let capabilities = FloxerCapabilities::probe_once(self.binary)?;
+        let mut args = ArgList {
+            capabilities,
+            args: vec![self.binary.into()],
+        };
+
+        args.option("--reference", required(self.reference, "reference")?)?;
+        args.option("--queries", required(self.queries, "queries")?)?;
+        args.option("--output", required(self.output, "output")?)?;
+        args.option("--stats", required(self.stats, "stats")?)?;
+
+        if let Some(logfile) = self.logfile {
+            args.option("--logfile", logfile)?;
+        }
+
+        if let Some(query_stats) = self.query_stats {
+            args.option("--query-stats", query_stats)?;
+        }
+
+        if let Some(index) = &self.index {
+            args.option("--index", index)?;
+        }
+
+        match algorithm_config.query_errors {
+            QueryErrors::Exact(num_errors) => {
+                args.option("--query-errors", num_errors.to_string())?;
+            }
+            QueryErrors::Rate(error_ratio) => {
+                args.option("--error-probability", error_ratio.to_string())?;
+            }
+        }
+
+        args.option(
+            "--seed-errors",
+            algorithm_config.pex_seed_errors.to_string(),
+        )?;
+        args.option(
+            "--max-anchors-hard",
+            algorithm_config.max_num_anchors_hard.to_string(),
+        )?;
+        args.option(
+            "--max-anchors-soft",
+            algorithm_config.max_num_anchors_soft.to_string(),
+        )?;
+        args.option(
+            "--anchor-group-order",
+            algorithm_config.anchor_group_order.to_string(),
+        )?;
+        args.option(
+            "--anchor-choice-strategy",
+            algorithm_config.anchor_choice_strategy.to_string(),
+        )?;
+        args.option(
+            "--seed-sampling-step-size",
+            algorithm_config.seed_sampling_step_size.to_string(),
+        )?;
+        args.option(
+            "--extra-verification-ratio",
+            algorithm_config.extra_verification_ratio.to_string(),
+        )?;
+        args.option("--threads", algorithm_config.num_threads.to_string())?;
+        args.option(
+            "--num-anchors-per-task",
+            algorithm_config
+                .num_anchors_per_verification_task
+                .to_string(),
+        )?;
+
+        if let PexTreeConstruction::BottomUp = algorithm_config.pex_tree_construction {
+            args.flag("--bottom-up-pex-tree")?;
+        }
+
+        if let IntervalOptimization::On = algorithm_config.interval_optimization {
+            args.flag("--interval-optimization")?;
+        }
+
+        if let VerificationAlgorithm::DirectFull = algorithm_config.verification_algorithm {
+            args.flag("--direct-full-verification")?;
+        }
+
+        if let EraseUselessAnchors::Off = algorithm_config.erase_useless_anchors {
+            args.flag("--dont-erase-useless-anchors")?;
+        }
+
+        if let Some(stats_input_hint) = self.stats_input_hint {
+            args.option("--stats-input-hint", stats_input_hint.to_string())?;
+        }
+
+        if let CigarOutput::Off = self.cigar_output {
+            args.flag("--without-cigar")?;
+        }
+
+        Ok(args.args)
+    }
+}
+
+fn required<'a>(value: Option<&'a Path>, name: &str) -> Result<&'a Path> {
+    value.with_context(|| format!("floxer command is missing the {name} path"))
+}
+
+fn validate_algorithm_config(config: &FloxerAlgorithmConfig) -> Result<()> {
+    match config.query_errors {
+        QueryErrors::Rate(error_ratio) => {
+            if !(error_ratio > 0.0 && error_ratio < 1.0) {
+                bail!("floxer query error rate must be in (0,1), but is {error_ratio}");
+            }
+        }
+        QueryErrors::Exact(num_errors) => {
+            // the PEX tree root needs at least as many errors as each of its leaves
+            if config.pex_seed_errors as u16 > num_errors {
+                bail!(
+                    "floxer PEX seed errors ({}) must not exceed the query errors ({num_errors})",
+                    config.pex_seed_errors
+                );
+            }
+        }
+    }
+
+    if !(config.extra_verification_ratio.is_finite() && config.extra_verification_ratio >= 0.0) {
+        bail!(
+            "floxer extra verification ratio must be non-negative, but is {}",
+            config.extra_verification_ratio
+        );
+    }
+
+    if config.max_num_anchors_soft == 0 || config.max_num_anchors_hard == 0 {
+        bail!("floxer anchor caps must be at least 1");
+    }
+
+    if config.seed_sampling_step_size == 0 {
+        bail!("floxer seed sampling step size must be at least 1");
+    }
+
+    if config.num_threads == 0 {
+        bail!("floxer needs at least one thread");
+    }
+
+    if config.num_anchors_per_verification_task == 0 {
+        bail!("floxer needs at least one anchor per verification task");
+    }
+
+    Ok(())
+}
+
+// same bound as for exact errors, but floxer computes the errors per query from its length, so
+// only queries shorter than the returned length have fewer errors than the PEX seeds
+fn min_query_length_for_seed_errors(config: &FloxerAlgorithmConfig) -> Option<usize> {
+    match config.query_errors {
+        QueryErrors::Rate(error_ratio) => {
+            Some((config.pex_seed_errors as f64 / error_ratio).ceil() as usize)
+        }
+        QueryErrors::Exact(_) => None,
+    }
+}
+
+struct ArgList {
+    capabilities: &'static FloxerCapabilities,
+    args: Vec<OsString>,
+}
+
+impl ArgList {
+    fn flag(&mut self, flag: &'static str) -> Result<()> {
+        if let Some(resolved) = self.resolve(flag)? {
+            self.args.push(resolved.into());
+        }
+
+        Ok(())
+    }
+
+    fn option(&mut self, flag: &'static str, value: impl Into<OsString>) -> Result<()> {
+        if let Some(resolved) = self.resolve(flag)? {
+            self.args.push(resolved.into());
+            self.args.push(value.into());
+        }
+
+        Ok(())
+    }
+
+    fn resolve(&self, flag: &'static str) -> Result<Option<&'static str>> {
+        if let Some(resolved) = self.capabilities.resolve(flag) {
+            return Ok(Some(resolved));
+        }
+
+        if DROPPABLE_FLAGS.contains(&flag) {
+            println!(
+                "Warning: floxer ({}) does not support {flag}, leaving it out",
+                self.capabilities.version
+            );
+
+            return Ok(None);
+        }
+
+        bail!(
+            "floxer ({}) at {} does not support the flag {flag}",
+            self.capabilities.version,
+            self.capabilities.binary.display()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_long_flags_of_help_text() {
+        let help_text = "Usage: floxer [OPTIONS]\n\
+            -r, --reference <FILE>   reference (FASTA)\n\
+            --seed-errors=<N>        errors per seed, see also --query-errors\n\
+            --bottom-up-pex-tree     flag\n\
+            -- ends the options, --- and --! are not flags\n";
+
+        let flags = parse_long_flags(help_text);
+        let expected: HashSet<_> = [
+            "--reference",
+            "--seed-errors",
+            "--query-errors",
+            "--bottom-up-pex-tree",
+        ]
+        .into_iter()
+        .map(String::from)
+        .collect();
+
+        assert_eq!(flags, expected);
+    }
+
+    #[test]
+    fn parse_long_flags_of_empty_help_text() {
+        assert!(parse_long_flags("").is_empty());
+        assert!(parse_long_flags("-h, -v and - are short").is_empty());
+    }
+
+    #[test]
+    fn default_algorithm_config_is_valid() {
+        assert!(validate_algorithm_config(&FloxerAlgorithmConfig::default()).is_ok());
+    }
+
+    #[test]
+    fn seed_errors_are_bounded_by_exact_query_errors() {
+        let mut config = FloxerAlgorithmConfig {
+            query_errors: QueryErrors::Exact(2),
+            pex_seed_errors: 2,
+            ..Default::default()
+        };
+        assert!(validate_algorithm_config(&config).is_ok());
+
+        config.pex_seed_errors = 3;
+        assert!(validate_algorithm_config(&config).is_err());
+    }
+
+    #[test]
+    fn seed_errors_with_query_error_rate_only_bound_the_query_length() {
+        // 1000 * 0.002 = 2 errors
+        let mut config = FloxerAlgorithmConfig {
+            query_errors: QueryErrors::Rate(0.002),
+            pex_seed_errors: 2,
+            ..Default::default()
+        };
+        assert_eq!(min_query_length_for_seed_errors(&config), Some(1000));
+
+        // valid for datasets without short queries
+        config.pex_seed_errors = 3;
+        assert!(validate_algorithm_config(&config).is_ok());
+        assert_eq!(min_query_length_for_seed_errors(&config), Some(1500));
+    }
+
+    #[test]
+    fn query_error_rate_must_be_a_fraction() {
+        for error_ratio in [0.0, 1.0, -0.1, f64::NAN] {
+            let config = FloxerAlgorithmConfig {
+                query_errors: QueryErrors::Rate(error_ratio),
+                ..Default::default()
+            };
+            assert!(validate_algorithm_config(&config).is_err());
+        }
+    }
+
+    #[test]
+    fn zero_counts_are_invalid() {
+        let configs = [
+            FloxerAlgorithmConfig {
+                max_num_anchors_soft: 0,
+                ..Default::default()
+            },
+            FloxerAlgorithmConfig {
+                max_num_anchors_hard: 0,
+                ..Default::default()
+            },
+            FloxerAlgorithmConfig {
+                seed_sampling_step_size: 0,
+                ..Default::default()
+            },
+            FloxerAlgorithmConfig {
+                num_threads: 0,
+                ..Default::default()
+            },
+            FloxerAlgorithmConfig {
+                num_anchors_per_verification_task: 0,
+                ..Default::default()
+            },
+            FloxerAlgorithmConfig {
+                extra_verification_ratio: -1.0,
+                ..Default::default()
+            },
+        ];
+
+        for config in configs {
+            assert!(validate_algorithm_config(&config).is_err());
+        }
+    }
+}
@@ -1,14 +1,29 @@
-use std::{fs, process::Command};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+    rc::Rc,
+};
 
 use crate::{
-    analyze_mapped_reads::verify_simulated_dataset,
+    alignment_files::{self, AlignmentsPerQuery},
+    analyze_mapped_reads::{
+        analyze_alignments_simple,
+        conformance::check_conformance,
+        length_bins::{analyze_by_query_length, LengthBinnedStats, LengthBins},
+        mapq_calibration::MapqCalibration,
+        read_instance_alignments, verify_simulated_dataset, SimpleMappedReadsStats,
+        VerificationCounts,
+    },
     cli::BenchmarkConfig,
     config::BenchmarkSuiteConfig,
     folder_structure::{BenchmarkFolder, BenchmarkInstanceFolder},
+    results_database::{self, InstanceRecord},
 };
 
 use super::{IndexStrategy, Queries, Reference, ResourceMetrics};
 use anyhow::{bail, Result};
+use serde::Serialize;
 
 static MINIMAP_NAME: &str = "minimap";
 
@@ -19,16 +34,26 @@ pub struct MinimapConfig {
     pub index_strategy: IndexStrategy,
     pub num_threads: u16,
     pub only_analysis: bool,
+    pub position_tolerance: u64,
+    pub recompute_edit_distances: bool,
+    pub skip_reference_loading: bool,
+    pub length_bins: LengthBins,
+    pub repetitions: u16,
 }
 
 impl From<&BenchmarkConfig> for MinimapConfig {
     fn from(value: &BenchmarkConfig) -> Self {
         Self {
-            reference: value.reference,
-            queries: value.queries,
+            reference: value.reference.clone(),
+            queries: value.queries.clone(),
             index_strategy: IndexStrategy::ReadFromDiskIfStored,
             num_threads: super::NUM_THREADS_FOR_READMAPPERS,
             only_analysis: value.only_analysis,
+            position_tolerance: value.position_tolerance,
+            recompute_edit_distances: value.recompute_edit_distances,
+            skip_reference_loading: value.skip_reference_loading,
+            length_bins: value.length_bins.clone(),
+            repetitions: value.repetitions,
         }
     }
 }
@@ -45,7 +70,20 @@ impl MinimapConfig {
             } else {
                 let instance_folder = BenchmarkInstanceFolder::new(benchmark_folder, MINIMAP_NAME)?;
 
+                // the last repetition is the one that is analyzed
+                for repetition in 1..self.repetitions {
+                    self.actually_run(&instance_folder, suite_config)?;
+                    fs::rename(
+                        &instance_folder.timing_path,
+                        instance_folder.repetition_timing_path(repetition),
+                    )?;
+                }
+
                 self.actually_run(&instance_folder, suite_config)?;
+                super::save_binary_version(
+                    &instance_folder,
+                    &reported_version(&suite_config.readmapper_binaries.minimap),
+                )?;
 
                 benchmark_folder.create_or_update_link_to_most_recent()?;
 
@@ -60,17 +98,90 @@ impl MinimapConfig {
         };
         let map_timings_file_str = fs::read_to_string(&instance_folder.timing_path)?;
         let map_resource_metrics: ResourceMetrics = toml::from_str(&map_timings_file_str)?;
+        let repeated_map_resource_metrics =
+            super::read_repeated_resource_metrics(&instance_folder)?;
+
+        // only the edit distance recomputation needs the sequence of the reference
+        let reference = if self.recompute_edit_distances && !self.skip_reference_loading {
+            Some(alignment_files::load_reference(
+                self.reference.path(suite_config)?,
+            )?)
+        } else {
+            None
+        };
+
+        let (alignments_per_query, edit_distance_check) = read_instance_alignments(
+            &instance_folder.mapped_reads_sam_path,
+            self.recompute_edit_distances,
+            reference.as_deref(),
+        )?;
+        let mapped_read_stats =
+            analyze_alignments_simple(&alignments_per_query, edit_distance_check);
+        mapped_read_stats.save(&instance_folder.mapped_reads_stats_path)?;
 
-        if self.queries == Queries::Simulated && self.reference == Reference::Simulated {
-            let verification_summary =
-                verify_simulated_dataset(&instance_folder.mapped_reads_sam_path, suite_config)?;
+        let length_binned_stats = analyze_by_query_length(
+            &alignments_per_query,
+            self.queries.path(suite_config)?,
+            &self.length_bins,
+            None,
+        )?;
+        length_binned_stats.save(&instance_folder.length_binned_stats_path)?;
 
-            verification_summary.print_if_missed();
+        let conformance_report = check_conformance(
+            &instance_folder.mapped_reads_sam_path,
+            self.reference.path(suite_config)?,
+        )?;
+        conformance_report.print();
+        conformance_report.save(&instance_folder.conformance_report_path)?;
+
+        let mut simulated_verification_counts = None;
+        let mut mapq_calibration = None;
+        if let Some(truth) = super::simulated_truth(&self.queries, &self.reference, suite_config) {
+            let verification_summary = verify_simulated_dataset(
+                &instance_folder.mapped_reads_sam_path,
+                truth,
+                self.position_tolerance,
+                suite_config,
+            )?;
+
+            verification_summary.print();
+            verification_summary.save(&instance_folder.simulated_verification_path)?;
+            simulated_verification_counts = Some(verification_summary.all_reads);
+            mapq_calibration = verification_summary.mapq_calibration;
         }
 
+        results_database::record_instance(
+            InstanceRecord {
+                benchmark_folder,
+                instance_folder: &instance_folder,
+                instance_name: MINIMAP_NAME,
+                mapper: MINIMAP_NAME,
+                reference: &self.reference,
+                queries: &self.queries,
+                parameters: serde_json::to_string(&serde_json::json!({
+                    "preset": self.queries.minimap_preset(),
+                    "index_strategy": self.index_strategy,
+                    "num_threads": self.num_threads,
+                }))?,
+                binary_version: super::read_binary_version(&instance_folder)?,
+                resource_metrics: &map_resource_metrics,
+                mapped_read_stats: &mapped_read_stats,
+                length_binned_stats: &length_binned_stats,
+                simulated_verification_counts: simulated_verification_counts.as_ref(),
+            },
+            suite_config,
+        )?;
+
         Ok(MinimapRunResult {
             map_resource_metrics,
+            repeated_map_resource_metrics,
             index_resource_metrics,
+            mapped_read_stats,
+            length_binned_stats,
+            simulated_verification_counts,
+            mapq_calibration,
+            mapped_reads_path: instance_folder.mapped_reads_sam_path,
+            alignments_per_query: Rc::new(alignments_per_query),
         })
     }
 
@@ -96,7 +207,7 @@ impl MinimapConfig {
             index_command.arg(self.queries.minimap_preset());
             index_command.arg("-d");
             index_command.arg(&index_path);
-            index_command.arg(self.reference.path(suite_config));
+            index_command.arg(self.reference.path(suite_config)?);
             index_command.arg("-t");
             index_command.arg(self.num_threads.to_string());
 
@@ -119,7 +230,7 @@ impl MinimapConfig {
             .arg(&suite_config.readmapper_binaries.minimap)
             .arg("-a")
             .arg(&index_path)
-            .arg(self.queries.path(suite_config))
+            .arg(self.queries.path(suite_config)?)
             .arg("-t")
             .arg(self.num_threads.to_string())
             .arg("-o")
@@ -137,7 +248,29 @@ impl MinimapConfig {
     }
 }
 
+// builds without a --version flag are recorded as unknown
+fn reported_version(minimap_binary: &Path) -> String {
+    match Command::new(minimap_binary).arg("--version").output() {
+        Ok(output) if output.status.success() => {
+            String::from_utf8_lossy(&output.stdout).trim().to_owned()
+        }
+        _ => String::from("unknown"),
+    }
+}
+
+#[derive(Debug, Serialize)]
 pub struct MinimapRunResult {
     pub map_resource_metrics: ResourceMetrics,
+    // of all repetitions, including the last one
+    pub repeated_map_resource_metrics: Vec<ResourceMetrics>,
     pub index_resource_metrics: Option<ResourceMetrics>,
+    pub mapped_read_stats: SimpleMappedReadsStats,
+    pub length_binned_stats: LengthBinnedStats,
+    // only for simulated datasets
+    pub simulated_verification_counts: Option<VerificationCounts>,
+    pub mapq_calibration: Option<MapqCalibration>,
+    pub mapped_reads_path: PathBuf,
+    // for the comparisons of the benchmark, so that they don't have to read the output again
+    #[serde(skip)]
+    pub alignments_per_query: Rc<AlignmentsPerQuery>,
 }
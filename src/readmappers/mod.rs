@@ -1,13 +1,20 @@
-use std::{path::Path, process::Command};
+use std::{convert::Infallible, fs, path::Path, process::Command, str::FromStr};
 
-use clap::ValueEnum;
+use anyhow::Result;
 use floxer::StatsInputHint;
-use serde::Deserialize;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 use strum::Display;
 
-use crate::config::BenchmarkSuiteConfig;
+use crate::{
+    analyze_mapped_reads::SimulatedTruth,
+    config::BenchmarkSuiteConfig,
+    datasets::simulated::Simulator,
+    folder_structure::{BenchmarkFolder, BenchmarkInstanceFolder},
+    statistics::{self, WelchTest},
+};
 
 pub mod floxer;
+pub mod floxer_command;
 pub mod minimap;
 
 const TIME_TOOL_FORMAT_STRING: &str = "wall_clock_seconds = %e
@@ -25,7 +32,7 @@ fn add_time_args(command: &mut Command, timing_path: &Path) {
         .arg(TIME_TOOL_FORMAT_STRING);
 }
 
-#[derive(Debug, Default, Copy, Clone, ValueEnum, Display, PartialEq, Eq)]
+#[derive(Debug, Default, Clone, Display, PartialEq, Eq)]
 #[strum(serialize_all = "snake_case")]
 pub enum Reference {
     #[default]
@@ -33,22 +40,67 @@ pub enum Reference {
     MaskedHumanGenomeHg38,
     Debug,
     Simulated,
+    // created by this program and looked up in the dataset registry
+    #[strum(default)]
+    Registered(String),
 }
 
 impl Reference {
-    fn path<'a>(&self, suite_config: &'a BenchmarkSuiteConfig) -> &'a Path {
-        match self {
+    pub const BUILTIN: [Reference; 4] = [
+        Reference::HumanGenomeHg38,
+        Reference::MaskedHumanGenomeHg38,
+        Reference::Debug,
+        Reference::Simulated,
+    ];
+
+    pub fn path<'a>(&self, suite_config: &'a BenchmarkSuiteConfig) -> Result<&'a Path> {
+        Ok(match self {
             Reference::HumanGenomeHg38 => &suite_config.reference_paths.human_genome_hg38,
             Reference::MaskedHumanGenomeHg38 => {
                 &suite_config.reference_paths.masked_human_genome_hg38
             }
             Reference::Debug => &suite_config.reference_paths.debug,
             Reference::Simulated => &suite_config.reference_paths.simulated,
+            Reference::Registered(name) => suite_config.datasets.reference_path(name)?,
+        })
+    }
+
+    pub fn is_simulated(&self, suite_config: &BenchmarkSuiteConfig) -> bool {
+        match self {
+            Reference::Simulated => true,
+            Reference::Registered(name) => suite_config.datasets.simulated_dataset(name).is_ok(),
+            _ => false,
         }
     }
 }
 
-#[derive(Debug, Default, Copy, Clone, ValueEnum, Display, PartialEq, Eq)]
+impl FromStr for Reference {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(parse_builtin_or_registered(
+            s,
+            &Reference::BUILTIN,
+            Reference::Registered,
+        ))
+    }
+}
+
+impl Serialize for Reference {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Reference {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
+
+#[derive(Debug, Default, Clone, Display, PartialEq, Eq)]
 #[strum(serialize_all = "snake_case")]
 pub enum Queries {
     #[default]
@@ -58,18 +110,31 @@ pub enum Queries {
     ProblemQuery,
     Simulated,
     SimulatedSmall,
+    // created by this program and looked up in the dataset registry
+    #[strum(default)]
+    Registered(String),
 }
 
 impl Queries {
-    fn path<'a>(&self, suite_config: &'a BenchmarkSuiteConfig) -> &'a Path {
-        match self {
+    pub const BUILTIN: [Queries; 6] = [
+        Queries::HumanWgsNanopore,
+        Queries::HumanWgsNanoporeSmall,
+        Queries::Debug,
+        Queries::ProblemQuery,
+        Queries::Simulated,
+        Queries::SimulatedSmall,
+    ];
+
+    pub fn path<'a>(&self, suite_config: &'a BenchmarkSuiteConfig) -> Result<&'a Path> {
+        Ok(match self {
             Queries::HumanWgsNanopore => &suite_config.query_paths.human_wgs_nanopore,
             Queries::HumanWgsNanoporeSmall => &suite_config.query_paths.human_wgs_nanopore_small,
             Queries::Debug => &suite_config.query_paths.debug,
             Queries::ProblemQuery => &suite_config.query_paths.problem_query,
             Queries::Simulated => &suite_config.query_paths.simulated,
             Queries::SimulatedSmall => &suite_config.query_paths.simulated_small,
-        }
+            Queries::Registered(name) => suite_config.datasets.queries_path(name)?,
+        })
     }
 
     fn minimap_preset(&self) -> &str {
@@ -80,10 +145,14 @@ impl Queries {
             Queries::ProblemQuery => "map-ont",
             Queries::Simulated => "map-ont",
             Queries::SimulatedSmall => "map-ont",
+            Queries::Registered(_) => "map-ont",
         }
     }
 
-    fn floxer_stats_input_hint(&self) -> Option<StatsInputHint> {
+    fn floxer_stats_input_hint(
+        &self,
+        suite_config: &BenchmarkSuiteConfig,
+    ) -> Option<StatsInputHint> {
         match self {
             Queries::HumanWgsNanopore => Some(StatsInputHint::RealNanopore),
             Queries::HumanWgsNanoporeSmall => Some(StatsInputHint::RealNanopore),
@@ -91,20 +160,294 @@ impl Queries {
             Queries::ProblemQuery => None,
             Queries::Simulated => Some(StatsInputHint::Simulated),
             Queries::SimulatedSmall => Some(StatsInputHint::Simulated),
+            Queries::Registered(name) => {
+                if let Ok(derived) = suite_config.datasets.derived_queries(name) {
+                    derived.source.floxer_stats_input_hint(suite_config)
+                } else if suite_config.datasets.simulated_dataset(name).is_ok() {
+                    Some(StatsInputHint::Simulated)
+                } else {
+                    None
+                }
+            }
+        }
+    }
+}
+
+impl FromStr for Queries {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(parse_builtin_or_registered(
+            s,
+            &Queries::BUILTIN,
+            Queries::Registered,
+        ))
+    }
+}
+
+impl Serialize for Queries {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Queries {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
+
+// builtin datasets can be given in kebab-case (like the other cli values) or snake_case,
+// everything else is interpreted as the name of a registered dataset
+fn parse_builtin_or_registered<T: Clone + ToString>(
+    s: &str,
+    builtin: &[T],
+    registered: impl FnOnce(String) -> T,
+) -> T {
+    let snake_case = s.replace('-', "_");
+
+    builtin
+        .iter()
+        .find(|dataset| dataset.to_string() == snake_case)
+        .cloned()
+        .unwrap_or_else(|| registered(s.to_owned()))
+}
+
+// the verification of simulated datasets needs queries from a simulation (or derived from one) and the
+// reference that they were simulated from
+pub fn simulated_truth<'a>(
+    queries: &Queries,
+    reference: &Reference,
+    suite_config: &'a BenchmarkSuiteConfig,
+) -> Option<SimulatedTruth<'a>> {
+    match (queries, reference) {
+        (Queries::Simulated | Queries::SimulatedSmall, Reference::Simulated) => {
+            Some(SimulatedTruth::External)
+        }
+        (Queries::Registered(queries_name), _) => {
+            // derived queries keep the read names of their source, so the truth only has to be restricted
+            if let Ok(derived) = suite_config.datasets.derived_queries(queries_name) {
+                return Some(
+                    match simulated_truth(&derived.source, reference, suite_config)? {
+                        SimulatedTruth::Native { dataset, .. } => SimulatedTruth::Native {
+                            dataset,
+                            queries_path: &derived.path,
+                        },
+                        SimulatedTruth::External => SimulatedTruth::External,
+                    },
+                );
+            }
+
+            let dataset = suite_config.datasets.simulated_dataset(queries_name).ok()?;
+
+            let is_simulation_reference = *reference == Reference::Registered(queries_name.clone())
+                || dataset.parameters.source_reference.as_ref() == Some(reference);
+            if !is_simulation_reference {
+                return None;
+            }
+
+            Some(match dataset.parameters.simulator {
+                Simulator::Native => SimulatedTruth::Native {
+                    dataset,
+                    queries_path: &dataset.queries_path,
+                },
+                Simulator::External => SimulatedTruth::External,
+            })
+        }
+        _ => None,
+    }
+}
+
+// stored indices and oracles are named after the reference, so they are outdated when a registered
+// reference is generated again
+pub fn remove_stored_indices(
+    reference: &Reference,
+    suite_config: &BenchmarkSuiteConfig,
+) -> Result<()> {
+    let floxer_index_file_name = floxer::index_file_name(reference);
+
+    for entry in fs::read_dir(suite_config.index_folder())? {
+        let entry = entry?;
+        let file_name = entry.file_name().to_string_lossy().into_owned();
+
+        if file_name == floxer_index_file_name
+            || stored_reference_name(&file_name, "minimap-index-") == Some(reference.to_string())
+        {
+            fs::remove_file(entry.path())?;
+        }
+    }
+
+    for entry in fs::read_dir(suite_config.oracle_folder())? {
+        let entry = entry?;
+        let file_name = entry.file_name().to_string_lossy().into_owned();
+
+        if stored_reference_name(&file_name, "oracle-") == Some(reference.to_string()) {
+            fs::remove_file(entry.path())?;
         }
     }
+
+    Ok(())
+}
+
+// minimap indices and oracles are named <prefix><reference>-<queries>..., dataset names can't
+// contain '-', so the reference is the part up to the next '-'
+fn stored_reference_name(file_name: &str, prefix: &str) -> Option<String> {
+    let (reference_name, _) = file_name.strip_prefix(prefix)?.split_once('-')?;
+    Some(reference_name.to_owned())
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum IndexStrategy {
     AlwaysRebuild,
     ReadFromDiskIfStored,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct ResourceMetrics {
     pub wall_clock_seconds: f64,
     pub user_cpu_seconds: f64,
     pub system_cpu_seconds: f64,
     pub peak_memory_kilobytes: usize,
 }
+
+// written when the read mapper runs, such that rerunning only the analysis records the version
+// that produced the mapped reads
+fn save_binary_version(instance_folder: &BenchmarkInstanceFolder, version: &str) -> Result<()> {
+    fs::write(&instance_folder.binary_version_path, version)?;
+    Ok(())
+}
+
+// runs from before the version was stored don't have the file
+fn read_binary_version(instance_folder: &BenchmarkInstanceFolder) -> Result<String> {
+    if !instance_folder.binary_version_path.exists() {
+        return Ok(String::from("unknown"));
+    }
+
+    Ok(fs::read_to_string(&instance_folder.binary_version_path)?
+        .trim()
+        .to_owned())
+}
+
+// the additional repetitions (if there were any) and the last run
+fn read_repeated_resource_metrics(
+    instance_folder: &BenchmarkInstanceFolder,
+) -> Result<Vec<ResourceMetrics>> {
+    let mut repeated_resource_metrics = Vec::new();
+
+    for repetition in 1.. {
+        let repetition_timing_path = instance_folder.repetition_timing_path(repetition);
+        if !repetition_timing_path.exists() {
+            break;
+        }
+
+        repeated_resource_metrics.push(toml::from_str(&fs::read_to_string(
+            repetition_timing_path,
+        )?)?);
+    }
+
+    repeated_resource_metrics.push(toml::from_str(&fs::read_to_string(
+        &instance_folder.timing_path,
+    )?)?);
+
+    Ok(repeated_resource_metrics)
+}
+
+// Welch's t-test for every pair of instances that were run at least twice, written to the benchmark folder.
+// None if there are no such pairs.
+pub fn compare_repeated_resource_metrics<'a>(
+    metrics_and_names_of_runs: impl IntoIterator<Item = (&'a [ResourceMetrics], &'a str)>,
+    benchmark_folder: &BenchmarkFolder,
+) -> Result<Option<ResourceMetricsComparison>> {
+    let repeated_runs: Vec<_> = metrics_and_names_of_runs
+        .into_iter()
+        .filter(|(metrics, _)| metrics.len() > 1)
+        .collect();
+
+    let values = |metrics: &[ResourceMetrics], value: fn(&ResourceMetrics) -> f64| -> Vec<f64> {
+        metrics.iter().map(value).collect()
+    };
+    let wall_clock = |metrics: &ResourceMetrics| metrics.wall_clock_seconds;
+    let cpu = |metrics: &ResourceMetrics| metrics.user_cpu_seconds + metrics.system_cpu_seconds;
+    let memory = |metrics: &ResourceMetrics| metrics.peak_memory_kilobytes as f64;
+
+    let mut pairs = Vec::new();
+    for (first_index, (first_metrics, first_name)) in repeated_runs.iter().enumerate() {
+        for (second_metrics, second_name) in &repeated_runs[first_index + 1..] {
+            let test = |value| {
+                statistics::welch_t_test(
+                    &values(first_metrics, value),
+                    &values(second_metrics, value),
+                )
+                .expect("all runs have at least two repetitions")
+            };
+
+            pairs.push(ResourceMetricsTests {
+                first_instance: (*first_name).to_owned(),
+                second_instance: (*second_name).to_owned(),
+                num_repetitions_first: first_metrics.len(),
+                num_repetitions_second: second_metrics.len(),
+                wall_clock_seconds: test(wall_clock),
+                cpu_seconds: test(cpu),
+                peak_memory_kilobytes: test(memory),
+            });
+        }
+    }
+
+    if pairs.is_empty() {
+        return Ok(None);
+    }
+
+    let comparison = ResourceMetricsComparison { pairs };
+
+    let mut result_file_path = benchmark_folder.get().to_owned();
+    result_file_path.push("resource_metrics_comparison.toml");
+
+    fs::write(result_file_path, toml::to_string(&comparison)?)?;
+
+    Ok(Some(comparison))
+}
+
+#[derive(Debug, Serialize)]
+pub struct ResourceMetricsComparison {
+    pub pairs: Vec<ResourceMetricsTests>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ResourceMetricsTests {
+    pub first_instance: String,
+    pub second_instance: String,
+    pub num_repetitions_first: usize,
+    pub num_repetitions_second: usize,
+    pub wall_clock_seconds: WelchTest,
+    // user and system
+    pub cpu_seconds: WelchTest,
+    pub peak_memory_kilobytes: WelchTest,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stored_files_match_the_exact_reference() {
+        assert_eq!(
+            stored_reference_name("oracle-sim-debug-2_errors.toml", "oracle-").as_deref(),
+            Some("sim")
+        );
+        assert_eq!(
+            stored_reference_name("oracle-sim_large-debug-2_errors.toml", "oracle-").as_deref(),
+            Some("sim_large")
+        );
+        assert_eq!(
+            stored_reference_name("minimap-index-debug-simulated.mmi", "minimap-index-").as_deref(),
+            Some("debug")
+        );
+        assert_eq!(
+            stored_reference_name("floxer-index-debug.flxi", "minimap-index-"),
+            None
+        );
+    }
+}
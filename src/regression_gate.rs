@@ -0,0 +1,362 @@
+use std::collections::HashMap;
+
+use anyhow::{bail, Result};
+use jiff::Timestamp;
+use rusqlite::{params, params_from_iter, Connection, Row};
+
+use crate::{
+    cli::{BaselineArgs, BaselineCommandArgs, RegressionThresholds, ResultsFilterArgs},
+    config::BenchmarkSuiteConfig,
+    results_database,
+};
+
+// the same benchmark instance in different runs
+const INSTANCE_KEY: &str = "benchmark_name, instance_name, reference, queries";
+const INSTANCE_METRICS: &str = "instance_folder, benchmark_name, instance_name, reference, queries,
+    wall_clock_seconds, user_cpu_seconds + system_cpu_seconds, peak_memory_kilobytes, num_mapped,
+    num_simulated_found_by_primary";
+// the same columns in the baselines table
+const BASELINE_METRICS: &str = "instance_folder, benchmark_name, instance_name, reference, queries,
+    wall_clock_seconds, cpu_seconds, peak_memory_kilobytes, num_mapped,
+    num_simulated_found_by_primary";
+
+pub fn run_baseline_command(
+    args: &BaselineArgs,
+    suite_config: &BenchmarkSuiteConfig,
+) -> Result<()> {
+    let connection = results_database::open(suite_config)?;
+
+    match &args.command {
+        BaselineCommandArgs::Promote { name, filter } => promote(&connection, name, filter),
+        BaselineCommandArgs::List => list(&connection),
+        BaselineCommandArgs::Check {
+            name,
+            filter,
+            thresholds,
+        } => check(&connection, name, filter, thresholds),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct InstanceKey {
+    benchmark_name: String,
+    instance_name: String,
+    reference: String,
+    queries: String,
+}
+
+impl std::fmt::Display for InstanceKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {} ({} in {})",
+            self.benchmark_name, self.instance_name, self.queries, self.reference
+        )
+    }
+}
+
+#[derive(Debug)]
+struct InstanceMetrics {
+    instance_folder: String,
+    key: InstanceKey,
+    wall_clock_seconds: f64,
+    cpu_seconds: f64,
+    peak_memory_kilobytes: i64,
+    num_mapped: i64,
+    num_simulated_found_by_primary: Option<i64>,
+}
+
+impl InstanceMetrics {
+    // the columns of INSTANCE_METRICS or BASELINE_METRICS
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(Self {
+            instance_folder: row.get(0)?,
+            key: InstanceKey {
+                benchmark_name: row.get(1)?,
+                instance_name: row.get(2)?,
+                reference: row.get(3)?,
+                queries: row.get(4)?,
+            },
+            wall_clock_seconds: row.get(5)?,
+            cpu_seconds: row.get(6)?,
+            peak_memory_kilobytes: row.get(7)?,
+            num_mapped: row.get(8)?,
+            num_simulated_found_by_primary: row.get(9)?,
+        })
+    }
+}
+
+// the most recent run of every instance that matches the filter
+fn latest_instances(
+    connection: &Connection,
+    filter: &ResultsFilterArgs,
+) -> Result<Vec<InstanceMetrics>> {
+    let (condition, values) = results_database::filter_clause(filter);
+
+    // with MAX, SQLite takes the other columns from the row with the maximum
+    let mut statement = connection.prepare(&format!(
+        "SELECT {INSTANCE_METRICS}, MAX(completed_at) FROM instances WHERE {condition}
+        GROUP BY {INSTANCE_KEY}"
+    ))?;
+
+    let instances = statement
+        .query_map(params_from_iter(values), InstanceMetrics::from_row)?
+        .collect::<rusqlite::Result<_>>()?;
+
+    Ok(instances)
+}
+
+fn baseline_instances(connection: &Connection, name: &str) -> Result<Vec<InstanceMetrics>> {
+    let mut statement = connection.prepare(&format!(
+        "SELECT {BASELINE_METRICS} FROM baselines WHERE baseline_name = ?1"
+    ))?;
+
+    let instances = statement
+        .query_map(params![name], InstanceMetrics::from_row)?
+        .collect::<rusqlite::Result<_>>()?;
+
+    Ok(instances)
+}
+
+// replaces the baseline if it already exists
+fn promote(connection: &Connection, name: &str, filter: &ResultsFilterArgs) -> Result<()> {
+    let instances = latest_instances(connection, filter)?;
+
+    if instances.is_empty() {
+        bail!("no recorded instances match the filter, nothing to promote to baseline {name}");
+    }
+
+    let promoted_at = Timestamp::now().as_second();
+
+    let transaction = connection.unchecked_transaction()?;
+    transaction.execute(
+        "DELETE FROM baselines WHERE baseline_name = ?1",
+        params![name],
+    )?;
+    for instance in &instances {
+        transaction.execute(
+            "INSERT INTO baselines VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+            params![
+                name,
+                promoted_at,
+                instance.instance_folder,
+                instance.key.benchmark_name,
+                instance.key.instance_name,
+                instance.key.reference,
+                instance.key.queries,
+                instance.wall_clock_seconds,
+                instance.cpu_seconds,
+                instance.peak_memory_kilobytes,
+                instance.num_mapped,
+                instance.num_simulated_found_by_primary,
+            ],
+        )?;
+    }
+    transaction.commit()?;
+
+    println!(
+        "- promoted {} instances to baseline {name}:",
+        instances.len()
+    );
+    for instance in &instances {
+        println!("  - {}", instance.key);
+    }
+
+    Ok(())
+}
+
+fn list(connection: &Connection) -> Result<()> {
+    let mut statement = connection.prepare(
+        "SELECT baseline_name, MAX(promoted_at), COUNT(*) FROM baselines
+        GROUP BY baseline_name ORDER BY baseline_name",
+    )?;
+
+    println!("baseline\tpromoted_at\tnum_instances");
+
+    let mut rows = statement.query(())?;
+    while let Some(row) = rows.next()? {
+        println!(
+            "{}\t{}\t{}",
+            row.get::<_, String>(0)?,
+            results_database::format_timestamp(row.get(1)?)?,
+            row.get::<_, i64>(2)?,
+        );
+    }
+
+    Ok(())
+}
+
+#[derive(Debug)]
+struct Regression<'a> {
+    key: &'a InstanceKey,
+    metric: &'static str,
+    baseline: f64,
+    checked: f64,
+}
+
+// resource usage may grow and the (simulated) mapped reads may drop by the relative thresholds
+fn regressions<'a>(
+    baseline: &'a InstanceMetrics,
+    checked: &InstanceMetrics,
+    thresholds: &RegressionThresholds,
+) -> Vec<Regression<'a>> {
+    let increases = [
+        (
+            "wall clock seconds",
+            baseline.wall_clock_seconds,
+            checked.wall_clock_seconds,
+            thresholds.max_wall_clock_increase,
+        ),
+        (
+            "cpu seconds",
+            baseline.cpu_seconds,
+            checked.cpu_seconds,
+            thresholds.max_cpu_increase,
+        ),
+        (
+            "peak memory kilobytes",
+            baseline.peak_memory_kilobytes as f64,
+            checked.peak_memory_kilobytes as f64,
+            thresholds.max_memory_increase,
+        ),
+    ];
+
+    let mut decreases = vec![(
+        "mapped reads",
+        baseline.num_mapped as f64,
+        checked.num_mapped as f64,
+        thresholds.max_mapped_decrease,
+    )];
+    if let (Some(baseline_found), Some(checked_found)) = (
+        baseline.num_simulated_found_by_primary,
+        checked.num_simulated_found_by_primary,
+    ) {
+        decreases.push((
+            "simulated reads found by primary",
+            baseline_found as f64,
+            checked_found as f64,
+            thresholds.max_simulated_found_decrease,
+        ));
+    }
+
+    let regression = |(metric, baseline_value, checked_value, _)| Regression {
+        key: &baseline.key,
+        metric,
+        baseline: baseline_value,
+        checked: checked_value,
+    };
+
+    increases
+        .into_iter()
+        .filter(|(_, baseline_value, checked_value, max_increase)| {
+            *checked_value > baseline_value * (1.0 + max_increase)
+        })
+        .map(regression)
+        .chain(
+            decreases
+                .into_iter()
+                .filter(|(_, baseline_value, checked_value, max_decrease)| {
+                    *checked_value < baseline_value * (1.0 - max_decrease)
+                })
+                .map(regression),
+        )
+        .collect()
+}
+
+// fails if any instance of the baseline got worse in the most recent matching run, if there is
+// no newer matching run of it or if that run lacks the simulated results of the baseline
+fn check(
+    connection: &Connection,
+    name: &str,
+    filter: &ResultsFilterArgs,
+    thresholds: &RegressionThresholds,
+) -> Result<()> {
+    let baseline = baseline_instances(connection, name)?;
+
+    if baseline.is_empty() {
+        bail!("there is no baseline named {name}");
+    }
+
+    let checked_instances: HashMap<_, _> = latest_instances(connection, filter)?
+        .into_iter()
+        .map(|instance| (instance.key.clone(), instance))
+        .collect();
+
+    let mut unmatched = Vec::new();
+    let mut baseline_runs = Vec::new();
+    let mut missing_simulated_results = Vec::new();
+    let mut all_regressions = Vec::new();
+
+    for baseline_instance in &baseline {
+        let Some(checked_instance) = checked_instances.get(&baseline_instance.key) else {
+            unmatched.push(&baseline_instance.key);
+            continue;
+        };
+
+        // without a newer run, the baseline would be checked against itself
+        if checked_instance.instance_folder == baseline_instance.instance_folder {
+            baseline_runs.push(&baseline_instance.key);
+            continue;
+        }
+
+        // the simulated reads found can't be compared, but the other metrics can
+        if baseline_instance.num_simulated_found_by_primary.is_some()
+            && checked_instance.num_simulated_found_by_primary.is_none()
+        {
+            missing_simulated_results.push(&baseline_instance.key);
+        }
+
+        all_regressions.extend(regressions(baseline_instance, checked_instance, thresholds));
+    }
+
+    println!(
+        "- checked {} of {} instances against baseline {name}",
+        baseline.len() - unmatched.len() - baseline_runs.len(),
+        baseline.len()
+    );
+
+    for key in &unmatched {
+        println!("- not checked, no matching run of {key}");
+    }
+
+    for key in &baseline_runs {
+        println!("- not checked, the most recent matching run of {key} is the baseline run");
+    }
+
+    for key in &missing_simulated_results {
+        println!(
+            "- simulated reads found by primary not checked, the most recent matching run of {key} \
+            has no results on simulated reads"
+        );
+    }
+
+    for regression in &all_regressions {
+        println!(
+            "- regression in {}: {} {} -> {} ({:+.1}%)",
+            regression.key,
+            regression.metric,
+            regression.baseline,
+            regression.checked,
+            (regression.checked / regression.baseline - 1.0) * 100.0
+        );
+    }
+
+    if !unmatched.is_empty() || !baseline_runs.is_empty() || !missing_simulated_results.is_empty() {
+        bail!(
+            "{} instances of baseline {name} have no matching run, {} only the baseline run and {} \
+            no results on simulated reads",
+            unmatched.len(),
+            baseline_runs.len(),
+            missing_simulated_results.len()
+        );
+    }
+
+    if !all_regressions.is_empty() {
+        bail!(
+            "{} regressions against baseline {name}",
+            all_regressions.len()
+        );
+    }
+
+    Ok(())
+}
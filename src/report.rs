@@ -0,0 +1,505 @@
+use std::{
+    fmt::Write,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+use crate::{
+    analyze_mapped_reads::VerificationCounts, benchmarks::Benchmark, config::BenchmarkSuiteConfig,
+    readmappers::ResourceMetrics, results_database::InstanceManifest,
+};
+
+static REPORT_FILE_NAME: &str = "report.html";
+
+static STYLE: &str = "body { font-family: sans-serif; margin: 2em auto; max-width: 1600px; }
+table { border-collapse: collapse; margin-bottom: 1em; }
+th, td { border: 1px solid #bbb; padding: 0.3em 0.6em; text-align: left; vertical-align: top; }
+th { background: #eee; }
+td.number { text-align: right; }
+code { font-size: 0.85em; }
+figure { margin: 1em 0; }
+figure img { max-width: 100%; height: auto; }";
+
+// the parts of simulated_verification.toml that are shown in the report
+#[derive(Debug, Deserialize)]
+struct SimulatedVerificationFile {
+    all_reads: VerificationCounts,
+}
+
+// an instance folder of a benchmark run, with the files that were written to it
+struct InstanceReport {
+    name: String,
+    manifest: Option<InstanceManifest>,
+    resource_metrics: Option<ResourceMetrics>,
+    index_resource_metrics: Option<ResourceMetrics>,
+    simulated_verification: Option<VerificationCounts>,
+    // relative to the benchmark folder
+    linked_files: Vec<PathBuf>,
+}
+
+impl InstanceReport {
+    fn load(benchmark_folder: &Path, instance_folder: &Path) -> Result<Self> {
+        let name = file_name(instance_folder);
+
+        let read_toml = |file_name: &str| -> Result<Option<String>> {
+            let path = instance_folder.join(file_name);
+            if !path.exists() {
+                return Ok(None);
+            }
+
+            Ok(Some(
+                fs::read_to_string(&path).with_context(|| format!("reading {}", path.display()))?,
+            ))
+        };
+
+        let manifest = read_toml("manifest.toml")?
+            .map(|content| toml::from_str(&content))
+            .transpose()?;
+        let resource_metrics = read_toml("timing.toml")?
+            .map(|content| toml::from_str(&content))
+            .transpose()?;
+        let index_resource_metrics = read_toml("index_timing.toml")?
+            .map(|content| toml::from_str(&content))
+            .transpose()?;
+        let simulated_verification = read_toml("simulated_verification.toml")?
+            .map(|content| toml::from_str::<SimulatedVerificationFile>(&content))
+            .transpose()?
+            .map(|file| file.all_reads);
+
+        // the manifest first, then the log and the other result files
+        let mut linked_files: Vec<_> = fs::read_dir(instance_folder)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<Result<_, _>>()?;
+        linked_files.retain(|path| {
+            matches!(
+                path.extension().and_then(|extension| extension.to_str()),
+                Some("toml" | "txt")
+            )
+        });
+        linked_files.sort_by_key(|path| {
+            let file_name = file_name(path);
+            (
+                file_name != "manifest.toml",
+                file_name != "log.txt",
+                file_name,
+            )
+        });
+
+        let linked_files = linked_files
+            .into_iter()
+            .map(|path| {
+                path.strip_prefix(benchmark_folder)
+                    .map(Path::to_path_buf)
+                    .unwrap_or(path)
+            })
+            .collect();
+
+        Ok(Self {
+            name,
+            manifest,
+            resource_metrics,
+            index_resource_metrics,
+            simulated_verification,
+            linked_files,
+        })
+    }
+}
+
+pub struct BenchmarkReportSummary {
+    pub benchmark_name: String,
+    pub inputs: String,
+    pub run: String,
+    pub num_instances: usize,
+    pub report_path: PathBuf,
+}
+
+// writes report.html into a benchmark run folder (<output>/<benchmark>/<inputs>/<run>), the plots are
+// embedded such that the file can be sent on its own
+pub fn write_benchmark_report(benchmark_folder: &Path) -> Result<BenchmarkReportSummary> {
+    let benchmark_folder = fs::canonicalize(benchmark_folder)
+        .with_context(|| format!("benchmark folder {}", benchmark_folder.display()))?;
+
+    let run = file_name(&benchmark_folder);
+    let inputs_folder = benchmark_folder
+        .parent()
+        .context("benchmark folder has no inputs folder")?;
+    let inputs = file_name(inputs_folder);
+    let benchmark_name = file_name(
+        inputs_folder
+            .parent()
+            .context("benchmark folder has no benchmark name folder")?,
+    );
+
+    let mut instance_folders: Vec<_> = fs::read_dir(&benchmark_folder)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<_, _>>()?;
+    instance_folders.retain(|path| path.join("timing.toml").exists());
+    instance_folders.sort();
+
+    let instances = instance_folders
+        .iter()
+        .map(|instance_folder| InstanceReport::load(&benchmark_folder, instance_folder))
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut html = String::new();
+    writeln!(
+        html,
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{0}</title>\n\
+        <style>\n{STYLE}\n</style>\n</head>\n<body>\n<h1>{0}</h1>",
+        escape(&benchmark_name)
+    )?;
+    writeln!(
+        html,
+        "<p>{} &middot; run {}</p>",
+        escape(&inputs.replace("_in_", " in ")),
+        escape(&run)
+    )?;
+
+    if let Some(benchmark) = Benchmark::from_folder_name(&benchmark_name) {
+        writeln!(html, "<p>{}</p>", escape(benchmark.description()))?;
+    }
+
+    write_parameter_table(&mut html, &instances)?;
+    write_resource_metrics_table(&mut html, &instances)?;
+    write_simulated_verification_table(&mut html, &instances)?;
+    write_plots(&mut html, &benchmark_folder.join("plots"))?;
+    write_file_links(&mut html, &instances)?;
+
+    writeln!(html, "</body>\n</html>")?;
+
+    let report_path = benchmark_folder.join(REPORT_FILE_NAME);
+    fs::write(&report_path, html)?;
+
+    Ok(BenchmarkReportSummary {
+        benchmark_name,
+        inputs,
+        run,
+        num_instances: instances.len(),
+        report_path,
+    })
+}
+
+fn write_parameter_table(html: &mut String, instances: &[InstanceReport]) -> Result<()> {
+    writeln!(
+        html,
+        "<h2>Parameters</h2>\n<table>\n<tr><th>Instance</th><th>Mapper</th><th>Parameters</th>\
+        <th>Version</th><th>Host</th><th>Completed</th></tr>"
+    )?;
+
+    for instance in instances {
+        match &instance.manifest {
+            Some(manifest) => writeln!(
+                html,
+                "<tr><td>{}</td><td>{}</td><td><code>{}</code></td><td>{}</td><td>{}</td>\
+                <td>{}</td></tr>",
+                escape(&instance.name),
+                escape(&manifest.mapper),
+                escape(&manifest.parameters),
+                escape(&manifest.binary_version),
+                escape(&manifest.host),
+                escape(&manifest.completed_at),
+            )?,
+            // instances of runs before the manifests were written
+            None => writeln!(
+                html,
+                "<tr><td>{}</td><td colspan=\"5\">no manifest</td></tr>",
+                escape(&instance.name)
+            )?,
+        }
+    }
+
+    writeln!(html, "</table>")?;
+
+    Ok(())
+}
+
+fn write_resource_metrics_table(html: &mut String, instances: &[InstanceReport]) -> Result<()> {
+    writeln!(
+        html,
+        "<h2>Resource usage</h2>\n<table>\n<tr><th>Instance</th><th>Wall clock seconds</th>\
+        <th>User CPU seconds</th><th>System CPU seconds</th><th>Peak memory GB</th></tr>"
+    )?;
+
+    for instance in instances {
+        let rows = [
+            (instance.name.clone(), &instance.resource_metrics),
+            (
+                format!("{} (index)", instance.name),
+                &instance.index_resource_metrics,
+            ),
+        ];
+
+        for (name, metrics) in rows {
+            let Some(metrics) = metrics else {
+                continue;
+            };
+
+            writeln!(
+                html,
+                "<tr><td>{}</td><td class=\"number\">{:.1}</td><td class=\"number\">{:.1}</td>\
+                <td class=\"number\">{:.1}</td><td class=\"number\">{:.2}</td></tr>",
+                escape(&name),
+                metrics.wall_clock_seconds,
+                metrics.user_cpu_seconds,
+                metrics.system_cpu_seconds,
+                metrics.peak_memory_kilobytes as f64 / 1_000_000.0,
+            )?;
+        }
+    }
+
+    writeln!(html, "</table>")?;
+
+    Ok(())
+}
+
+fn write_simulated_verification_table(
+    html: &mut String,
+    instances: &[InstanceReport],
+) -> Result<()> {
+    if instances
+        .iter()
+        .all(|instance| instance.simulated_verification.is_none())
+    {
+        return Ok(());
+    }
+
+    writeln!(
+        html,
+        "<h2>Simulated reads</h2>\n<table>\n<tr><th>Instance</th><th>Reads</th>\
+        <th>Found by primary</th><th>Found only by secondary</th><th>Found suboptimal</th>\
+        <th>Mapped elsewhere</th><th>Unmapped</th></tr>"
+    )?;
+
+    for instance in instances {
+        let Some(counts) = &instance.simulated_verification else {
+            continue;
+        };
+
+        let percentage = |count: usize| 100.0 * count as f64 / counts.num_reads.max(1) as f64;
+
+        writeln!(
+            html,
+            "<tr><td>{}</td><td class=\"number\">{}</td>\
+            <td class=\"number\">{} ({:.1}%)</td><td class=\"number\">{} ({:.1}%)</td>\
+            <td class=\"number\">{} ({:.1}%)</td><td class=\"number\">{} ({:.1}%)</td>\
+            <td class=\"number\">{} ({:.1}%)</td></tr>",
+            escape(&instance.name),
+            counts.num_reads,
+            counts.found_by_primary,
+            percentage(counts.found_by_primary),
+            counts.found_only_by_secondary,
+            percentage(counts.found_only_by_secondary),
+            counts.found_suboptimal,
+            percentage(counts.found_suboptimal),
+            counts.mapped_elsewhere,
+            percentage(counts.mapped_elsewhere),
+            counts.unmapped,
+            percentage(counts.unmapped),
+        )?;
+    }
+
+    writeln!(html, "</table>")?;
+
+    Ok(())
+}
+
+// the SVGs are inlined
+fn write_plots(html: &mut String, plot_folder: &Path) -> Result<()> {
+    if !plot_folder.exists() {
+        return Ok(());
+    }
+
+    let mut plot_paths: Vec<_> = fs::read_dir(plot_folder)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<_, _>>()?;
+    plot_paths.retain(|path| path.extension().is_some_and(|extension| extension == "svg"));
+    plot_paths.sort();
+
+    if plot_paths.is_empty() {
+        return Ok(());
+    }
+
+    writeln!(html, "<h2>Plots</h2>")?;
+
+    // embedded as images, because the inline SVGs of different plots would share element ids
+    for plot_path in plot_paths {
+        let svg = fs::read(&plot_path)?;
+        let caption = escape(&file_name(&plot_path.with_extension("")));
+
+        writeln!(
+            html,
+            "<figure>\n<img src=\"data:image/svg+xml;base64,{}\" alt=\"{caption}\">\n\
+            <figcaption>{caption}</figcaption>\n</figure>",
+            base64(&svg),
+        )?;
+    }
+
+    Ok(())
+}
+
+// standard alphabet with padding (RFC 4648)
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+
+    for chunk in bytes.chunks(3) {
+        let group = chunk.iter().enumerate().fold(0u32, |group, (index, byte)| {
+            group | (*byte as u32) << (16 - 8 * index)
+        });
+
+        for index in 0..4 {
+            if index <= chunk.len() {
+                encoded.push(ALPHABET[(group >> (18 - 6 * index) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+
+    encoded
+}
+
+fn write_file_links(html: &mut String, instances: &[InstanceReport]) -> Result<()> {
+    writeln!(html, "<h2>Files</h2>\n<ul>")?;
+
+    for instance in instances {
+        let links: Vec<_> = instance
+            .linked_files
+            .iter()
+            .map(|path| {
+                let path = path.to_string_lossy();
+                format!(
+                    "<a href=\"{}\">{}</a>",
+                    escape(&path),
+                    escape(path.rsplit('/').next().unwrap_or(&path))
+                )
+            })
+            .collect();
+
+        writeln!(
+            html,
+            "<li>{}: {}</li>",
+            escape(&instance.name),
+            links.join(", ")
+        )?;
+    }
+
+    writeln!(html, "</ul>")?;
+
+    Ok(())
+}
+
+// writes the report of the most recent run of every benchmark (and inputs) and an index page
+// linking them to the output folder
+pub fn write_suite_report(suite_config: &BenchmarkSuiteConfig) -> Result<PathBuf> {
+    let mut summaries = Vec::new();
+
+    let mut benchmark_name_folders: Vec<_> = fs::read_dir(&suite_config.output_folder)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<_, _>>()?;
+    benchmark_name_folders.retain(|path| path.is_dir());
+    benchmark_name_folders.sort();
+
+    for benchmark_name_folder in benchmark_name_folders {
+        let mut inputs_folders: Vec<_> = fs::read_dir(&benchmark_name_folder)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<Result<_, _>>()?;
+        inputs_folders.sort();
+
+        for inputs_folder in inputs_folders {
+            // only benchmark folders have this link, the datasets, indices etc. don't
+            let most_recent_run = inputs_folder.join("most_recent");
+            if !most_recent_run.exists() {
+                continue;
+            }
+
+            // a broken run (e.g. from an older version) should not prevent the other reports
+            match write_benchmark_report(&most_recent_run) {
+                Ok(summary) => summaries.push(summary),
+                Err(err) => println!(
+                    "- Warning: skipped the report of {}: {err:#}",
+                    most_recent_run.display()
+                ),
+            }
+        }
+    }
+
+    let mut html = String::new();
+    writeln!(
+        html,
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Benchmarks</title>\n\
+        <style>\n{STYLE}\n</style>\n</head>\n<body>\n<h1>Benchmarks</h1>\n<table>\n\
+        <tr><th>Benchmark</th><th>Description</th><th>Inputs</th><th>Run</th><th>Instances</th></tr>"
+    )?;
+
+    let output_folder = fs::canonicalize(&suite_config.output_folder)?;
+    for summary in &summaries {
+        let relative_report_path = summary
+            .report_path
+            .strip_prefix(&output_folder)
+            .unwrap_or(&summary.report_path);
+
+        writeln!(
+            html,
+            "<tr><td><a href=\"{}\">{}</a></td><td>{}</td><td>{}</td><td>{}</td>\
+            <td class=\"number\">{}</td></tr>",
+            escape(&relative_report_path.to_string_lossy()),
+            escape(&summary.benchmark_name),
+            escape(
+                Benchmark::from_folder_name(&summary.benchmark_name)
+                    .map(|benchmark| benchmark.description())
+                    .unwrap_or_default()
+            ),
+            escape(&summary.inputs.replace("_in_", " in ")),
+            escape(&summary.run),
+            summary.num_instances,
+        )?;
+    }
+
+    writeln!(html, "</table>\n</body>\n</html>")?;
+
+    let report_path = suite_config.output_folder.join(REPORT_FILE_NAME);
+    fs::write(&report_path, html)?;
+
+    Ok(report_path)
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .into_owned()
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base64_matches_the_rfc_test_vectors() {
+        let vectors = [
+            ("", ""),
+            ("f", "Zg=="),
+            ("fo", "Zm8="),
+            ("foo", "Zm9v"),
+            ("foob", "Zm9vYg=="),
+            ("fooba", "Zm9vYmE="),
+            ("foobar", "Zm9vYmFy"),
+        ];
+
+        for (input, expected) in vectors {
+            assert_eq!(base64(input.as_bytes()), expected);
+        }
+    }
+}
@@ -0,0 +1,333 @@
+use std::{fs, time::SystemTime};
+
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use jiff::{tz::TimeZone, Timestamp};
+use rusqlite::{params, params_from_iter, types::Value, Connection};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    analyze_mapped_reads::{
+        length_bins::LengthBinnedStats, SimpleMappedReadsStats, VerificationCounts,
+    },
+    cli::{ResultsArgs, ResultsFilterArgs, ResultsQueryArgs},
+    config::BenchmarkSuiteConfig,
+    folder_structure::{BenchmarkFolder, BenchmarkInstanceFolder},
+    readmappers::{Queries, Reference, ResourceMetrics},
+};
+
+// one row per completed benchmark instance, the instance folder is the key such that rerunning
+// the analysis of an instance updates its row
+const CREATE_INSTANCES_TABLE: &str = "CREATE TABLE IF NOT EXISTS instances (
+    instance_folder TEXT PRIMARY KEY,
+    completed_at INTEGER NOT NULL,
+    benchmark_name TEXT NOT NULL,
+    instance_name TEXT NOT NULL,
+    mapper TEXT NOT NULL,
+    tag TEXT,
+    reference TEXT NOT NULL,
+    queries TEXT NOT NULL,
+    parameters TEXT NOT NULL,
+    binary_version TEXT NOT NULL,
+    host TEXT NOT NULL,
+    wall_clock_seconds REAL NOT NULL,
+    user_cpu_seconds REAL NOT NULL,
+    system_cpu_seconds REAL NOT NULL,
+    peak_memory_kilobytes INTEGER NOT NULL,
+    num_queries INTEGER NOT NULL,
+    num_mapped INTEGER NOT NULL,
+    num_simulated_found_by_primary INTEGER,
+    num_simulated_mapped_elsewhere INTEGER
+)";
+
+// named sets of instances that later runs are checked against, see the regression gate. The
+// metrics are copied, because rerunning the analysis of an instance replaces its row in instances
+const CREATE_BASELINES_TABLE: &str = "CREATE TABLE IF NOT EXISTS baselines (
+    baseline_name TEXT NOT NULL,
+    promoted_at INTEGER NOT NULL,
+    instance_folder TEXT NOT NULL,
+    benchmark_name TEXT NOT NULL,
+    instance_name TEXT NOT NULL,
+    reference TEXT NOT NULL,
+    queries TEXT NOT NULL,
+    wall_clock_seconds REAL NOT NULL,
+    cpu_seconds REAL NOT NULL,
+    peak_memory_kilobytes INTEGER NOT NULL,
+    num_mapped INTEGER NOT NULL,
+    num_simulated_found_by_primary INTEGER,
+    PRIMARY KEY (baseline_name, instance_folder)
+)";
+
+// the SQL metric is aggregated per benchmark instance by the summarize subcommand
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum ResultsMetric {
+    WallClockSeconds,
+    CpuSeconds,
+    PeakMemoryKilobytes,
+    MappingRate,
+    // fraction of the reads of a simulated dataset that the primary alignment found
+    SimulatedSensitivity,
+}
+
+impl ResultsMetric {
+    fn sql_expression(&self) -> &'static str {
+        match self {
+            ResultsMetric::WallClockSeconds => "wall_clock_seconds",
+            ResultsMetric::CpuSeconds => "user_cpu_seconds + system_cpu_seconds",
+            ResultsMetric::PeakMemoryKilobytes => "peak_memory_kilobytes",
+            ResultsMetric::MappingRate => "CAST(num_mapped AS REAL) / MAX(num_queries, 1)",
+            ResultsMetric::SimulatedSensitivity => {
+                "CAST(num_simulated_found_by_primary AS REAL) / MAX(num_queries, 1)"
+            }
+        }
+    }
+}
+
+pub struct InstanceRecord<'a> {
+    pub benchmark_folder: &'a BenchmarkFolder,
+    pub instance_folder: &'a BenchmarkInstanceFolder,
+    pub instance_name: &'a str,
+    pub mapper: &'a str,
+    pub reference: &'a Reference,
+    pub queries: &'a Queries,
+    pub parameters: String,
+    pub binary_version: String,
+    pub resource_metrics: &'a ResourceMetrics,
+    pub mapped_read_stats: &'a SimpleMappedReadsStats,
+    pub length_binned_stats: &'a LengthBinnedStats,
+    pub simulated_verification_counts: Option<&'a VerificationCounts>,
+}
+
+// what ran in a benchmark instance, written to its folder for the reports
+#[derive(Debug, Serialize, Deserialize)]
+pub struct InstanceManifest {
+    pub benchmark_name: String,
+    pub instance_name: String,
+    pub mapper: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+    pub reference: String,
+    pub queries: String,
+    pub parameters: String,
+    pub binary_version: String,
+    pub host: String,
+    pub completed_at: String,
+}
+
+pub fn open(suite_config: &BenchmarkSuiteConfig) -> Result<Connection> {
+    let database_path = suite_config.results_database_path();
+    let connection = Connection::open(&database_path)
+        .with_context(|| format!("opening results database {}", database_path.display()))?;
+
+    connection.execute(CREATE_INSTANCES_TABLE, ())?;
+    connection.execute(CREATE_BASELINES_TABLE, ())?;
+
+    Ok(connection)
+}
+
+// writes the manifest of the instance and records it in the results database
+pub fn record_instance(record: InstanceRecord, suite_config: &BenchmarkSuiteConfig) -> Result<()> {
+    let connection = open(suite_config)?;
+
+    // the timing file is written when the read mapper finishes
+    let completed_at = Timestamp::try_from(
+        fs::metadata(&record.instance_folder.timing_path)
+            .and_then(|metadata| metadata.modified())
+            .unwrap_or_else(|_| SystemTime::now()),
+    )?;
+
+    let num_queries: i64 = record
+        .length_binned_stats
+        .bins
+        .iter()
+        .map(|bin| bin.num_queries)
+        .sum();
+
+    let manifest = InstanceManifest {
+        benchmark_name: record.benchmark_folder.benchmark_name().to_owned(),
+        instance_name: record.instance_name.to_owned(),
+        mapper: record.mapper.to_owned(),
+        tag: record.benchmark_folder.tag().map(ToOwned::to_owned),
+        reference: record.reference.to_string(),
+        queries: record.queries.to_string(),
+        parameters: record.parameters,
+        binary_version: record.binary_version,
+        host: host_name(),
+        completed_at: format_timestamp(completed_at.as_second())?,
+    };
+    fs::write(
+        &record.instance_folder.manifest_path,
+        toml::to_string(&manifest)?,
+    )?;
+
+    connection.execute(
+        "INSERT OR REPLACE INTO instances VALUES
+            (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19)",
+        params![
+            record
+                .instance_folder
+                .canonical_base_folder()?
+                .to_string_lossy(),
+            completed_at.as_second(),
+            manifest.benchmark_name,
+            manifest.instance_name,
+            manifest.mapper,
+            manifest.tag,
+            manifest.reference,
+            manifest.queries,
+            manifest.parameters,
+            manifest.binary_version,
+            manifest.host,
+            record.resource_metrics.wall_clock_seconds,
+            record.resource_metrics.user_cpu_seconds,
+            record.resource_metrics.system_cpu_seconds,
+            record.resource_metrics.peak_memory_kilobytes as i64,
+            num_queries,
+            record.mapped_read_stats.num_mapped,
+            record
+                .simulated_verification_counts
+                .map(|counts| counts.found_by_primary as i64),
+            record
+                .simulated_verification_counts
+                .map(|counts| counts.mapped_elsewhere as i64),
+        ],
+    )?;
+
+    Ok(())
+}
+
+fn host_name() -> String {
+    fs::read_to_string("/proc/sys/kernel/hostname")
+        .map(|host_name| host_name.trim().to_owned())
+        .unwrap_or_else(|_| String::from("unknown"))
+}
+
+pub fn query_results(args: &ResultsArgs, suite_config: &BenchmarkSuiteConfig) -> Result<()> {
+    let database_path = suite_config.results_database_path();
+    if !database_path.exists() {
+        println!("- no results recorded yet in {}", database_path.display());
+        return Ok(());
+    }
+
+    let connection = open(suite_config)?;
+
+    match &args.query {
+        ResultsQueryArgs::List(filter) => list(&connection, filter),
+        ResultsQueryArgs::Summarize { filter, metric } => summarize(&connection, filter, *metric),
+    }
+}
+
+// WHERE clause and its parameters
+pub fn filter_clause(filter: &ResultsFilterArgs) -> (String, Vec<Value>) {
+    let mut conditions = vec![String::from("1")];
+    let mut values = Vec::new();
+
+    let mut add_condition = |column: &str, value: Option<String>| {
+        if let Some(value) = value {
+            values.push(Value::Text(value));
+            conditions.push(format!("{column} = ?{}", values.len()));
+        }
+    };
+
+    add_condition("benchmark_name", filter.benchmark.clone());
+    add_condition("instance_name", filter.instance.clone());
+    add_condition("mapper", filter.mapper.clone());
+    add_condition("tag", filter.tag.clone());
+    add_condition("host", filter.host.clone());
+    add_condition(
+        "reference",
+        filter.reference.as_ref().map(ToString::to_string),
+    );
+    add_condition("queries", filter.queries.as_ref().map(ToString::to_string));
+
+    if let Some(since) = filter.since {
+        values.push(Value::Integer(since.as_second()));
+        conditions.push(format!("completed_at >= ?{}", values.len()));
+    }
+
+    (conditions.join(" AND "), values)
+}
+
+fn list(connection: &Connection, filter: &ResultsFilterArgs) -> Result<()> {
+    let (condition, values) = filter_clause(filter);
+    let mut statement = connection.prepare(&format!(
+        "SELECT completed_at, benchmark_name, instance_name, queries, reference, binary_version,
+            host, wall_clock_seconds, user_cpu_seconds + system_cpu_seconds,
+            peak_memory_kilobytes, num_mapped, num_queries, instance_folder
+        FROM instances WHERE {condition} ORDER BY completed_at"
+    ))?;
+
+    println!(
+        "completed_at\tbenchmark\tinstance\tqueries\treference\tversion\thost\twall_clock_seconds\t\
+        cpu_seconds\tpeak_memory_kilobytes\tnum_mapped\tnum_queries\tinstance_folder"
+    );
+
+    let mut rows = statement.query(params_from_iter(values))?;
+    while let Some(row) = rows.next()? {
+        println!(
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{:.2}\t{:.2}\t{}\t{}\t{}\t{}",
+            format_timestamp(row.get(0)?)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, String>(2)?,
+            row.get::<_, String>(3)?,
+            row.get::<_, String>(4)?,
+            row.get::<_, String>(5)?,
+            row.get::<_, String>(6)?,
+            row.get::<_, f64>(7)?,
+            row.get::<_, f64>(8)?,
+            row.get::<_, i64>(9)?,
+            row.get::<_, i64>(10)?,
+            row.get::<_, i64>(11)?,
+            row.get::<_, String>(12)?,
+        );
+    }
+
+    Ok(())
+}
+
+fn summarize(
+    connection: &Connection,
+    filter: &ResultsFilterArgs,
+    metric: ResultsMetric,
+) -> Result<()> {
+    let (condition, values) = filter_clause(filter);
+    let metric_expression = metric.sql_expression();
+    let mut statement = connection.prepare(&format!(
+        "SELECT benchmark_name, instance_name, queries, reference, COUNT(value), AVG(value),
+            MIN(value), MAX(value), MIN(completed_at), MAX(completed_at)
+        FROM (SELECT *, {metric_expression} AS value FROM instances WHERE {condition})
+        WHERE value IS NOT NULL
+        GROUP BY benchmark_name, instance_name, queries, reference
+        ORDER BY benchmark_name, instance_name"
+    ))?;
+
+    println!(
+        "benchmark\tinstance\tqueries\treference\tnum_runs\tmean\tmin\tmax\tfirst_run\tlast_run"
+    );
+
+    let mut rows = statement.query(params_from_iter(values))?;
+    while let Some(row) = rows.next()? {
+        println!(
+            "{}\t{}\t{}\t{}\t{}\t{:.4}\t{:.4}\t{:.4}\t{}\t{}",
+            row.get::<_, String>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, String>(2)?,
+            row.get::<_, String>(3)?,
+            row.get::<_, i64>(4)?,
+            row.get::<_, f64>(5)?,
+            row.get::<_, f64>(6)?,
+            row.get::<_, f64>(7)?,
+            format_timestamp(row.get(8)?)?,
+            format_timestamp(row.get(9)?)?,
+        );
+    }
+
+    Ok(())
+}
+
+pub fn format_timestamp(seconds: i64) -> Result<String> {
+    Ok(Timestamp::from_second(seconds)?
+        .to_zoned(TimeZone::system())
+        .strftime("%F %H:%M:%S")
+        .to_string())
+}
@@ -0,0 +1,443 @@
+use serde::Serialize;
+
+// below this number of discordant pairs, McNemar's test uses the exact binomial distribution
+const MIN_DISCORDANT_PAIRS_FOR_CHI_SQUARED: usize = 25;
+
+// McNemar's test on paired binary outcomes (e.g. mapped or not by two mappers). Only the discordant
+// pairs matter: the ones where only the first or only the second outcome is positive.
+#[derive(Debug, Clone, Serialize)]
+pub struct McNemarTest {
+    pub num_only_first: usize,
+    pub num_only_second: usize,
+    pub exact: bool,
+    pub p_value: f64,
+    // num_only_first / num_only_second, not available if the latter is 0
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub odds_ratio: Option<f64>,
+}
+
+pub fn mcnemar_test(num_only_first: usize, num_only_second: usize) -> McNemarTest {
+    let num_discordant = num_only_first + num_only_second;
+    let exact = num_discordant < MIN_DISCORDANT_PAIRS_FOR_CHI_SQUARED;
+
+    let p_value = if num_discordant == 0 {
+        1.0
+    } else if exact {
+        // two-sided binomial test with p = 0.5
+        let smaller = num_only_first.min(num_only_second);
+        let tail: f64 = (0..=smaller)
+            .map(|k| (ln_binomial(num_discordant, k) - num_discordant as f64 * 2f64.ln()).exp())
+            .sum();
+        (2.0 * tail).min(1.0)
+    } else {
+        // with continuity correction, chi squared with one degree of freedom
+        let difference = num_only_first.abs_diff(num_only_second) as f64 - 1.0;
+        let chi_squared = difference.max(0.0).powi(2) / num_discordant as f64;
+        erfc((chi_squared / 2.0).sqrt())
+    };
+
+    McNemarTest {
+        num_only_first,
+        num_only_second,
+        exact,
+        p_value,
+        odds_ratio: (num_only_second > 0).then(|| num_only_first as f64 / num_only_second as f64),
+    }
+}
+
+// Wilcoxon signed-rank test on paired values (first - second), with the normal approximation,
+// tie and continuity correction. Pairs without difference are dropped.
+#[derive(Debug, Clone, Serialize)]
+pub struct WilcoxonTest {
+    pub num_pairs: usize,
+    pub num_zero_differences: usize,
+    pub mean_difference: f64,
+    pub z: f64,
+    pub p_value: f64,
+    // matched-pairs rank-biserial correlation in [-1, 1], positive if the first values are larger
+    pub rank_biserial_correlation: f64,
+}
+
+pub fn wilcoxon_signed_rank_test(pairs: impl IntoIterator<Item = (f64, f64)>) -> WilcoxonTest {
+    let differences: Vec<f64> = pairs
+        .into_iter()
+        .map(|(first, second)| first - second)
+        .collect();
+    let num_pairs = differences.len();
+    let mean_difference = if num_pairs == 0 {
+        0.0
+    } else {
+        differences.iter().sum::<f64>() / num_pairs as f64
+    };
+
+    let mut non_zero: Vec<f64> = differences.into_iter().filter(|d| *d != 0.0).collect();
+    let num_zero_differences = num_pairs - non_zero.len();
+    let n = non_zero.len() as f64;
+
+    if non_zero.is_empty() {
+        return WilcoxonTest {
+            num_pairs,
+            num_zero_differences,
+            mean_difference,
+            z: 0.0,
+            p_value: 1.0,
+            rank_biserial_correlation: 0.0,
+        };
+    }
+
+    non_zero.sort_unstable_by(|a, b| a.abs().total_cmp(&b.abs()));
+
+    // average ranks for ties of the absolute differences
+    let mut positive_rank_sum = 0.0;
+    let mut tie_correction = 0.0;
+    let mut start = 0;
+    while start < non_zero.len() {
+        let mut end = start + 1;
+        while end < non_zero.len() && non_zero[end].abs() == non_zero[start].abs() {
+            end += 1;
+        }
+
+        let average_rank = (start + end + 1) as f64 / 2.0;
+        positive_rank_sum += average_rank
+            * non_zero[start..end]
+                .iter()
+                .filter(|difference| **difference > 0.0)
+                .count() as f64;
+
+        let num_tied = (end - start) as f64;
+        tie_correction += num_tied.powi(3) - num_tied;
+
+        start = end;
+    }
+
+    let total_rank_sum = n * (n + 1.0) / 2.0;
+    let mean = total_rank_sum / 2.0;
+    let variance = n * (n + 1.0) * (2.0 * n + 1.0) / 24.0 - tie_correction / 48.0;
+
+    let deviation = positive_rank_sum - mean;
+    let z = if variance > 0.0 {
+        (deviation - 0.5 * deviation.signum()) / variance.sqrt()
+    } else {
+        0.0
+    };
+
+    WilcoxonTest {
+        num_pairs,
+        num_zero_differences,
+        mean_difference,
+        z,
+        p_value: erfc(z.abs() / 2f64.sqrt()),
+        rank_biserial_correlation: (2.0 * positive_rank_sum - total_rank_sum) / total_rank_sum,
+    }
+}
+
+// Welch's t-test on two independent samples (e.g. the wall clock times of repeated runs)
+#[derive(Debug, Clone, Serialize)]
+pub struct WelchTest {
+    pub mean_first: f64,
+    pub mean_second: f64,
+    pub t: f64,
+    pub degrees_of_freedom: f64,
+    pub p_value: f64,
+    // bias corrected standardized mean difference, not available without variance
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hedges_g: Option<f64>,
+}
+
+// None if a sample has less than two values
+pub fn welch_t_test(first: &[f64], second: &[f64]) -> Option<WelchTest> {
+    if first.len() < 2 || second.len() < 2 {
+        return None;
+    }
+
+    let (n1, n2) = (first.len() as f64, second.len() as f64);
+    let (mean_first, variance_first) = mean_and_variance(first);
+    let (mean_second, variance_second) = mean_and_variance(second);
+
+    let standard_error_squared = variance_first / n1 + variance_second / n2;
+    let pooled_variance =
+        ((n1 - 1.0) * variance_first + (n2 - 1.0) * variance_second) / (n1 + n2 - 2.0);
+
+    let hedges_g = (pooled_variance > 0.0).then(|| {
+        (mean_first - mean_second) / pooled_variance.sqrt() * (1.0 - 3.0 / (4.0 * (n1 + n2) - 9.0))
+    });
+
+    if standard_error_squared == 0.0 {
+        return Some(WelchTest {
+            mean_first,
+            mean_second,
+            t: 0.0,
+            degrees_of_freedom: n1 + n2 - 2.0,
+            p_value: if mean_first == mean_second { 1.0 } else { 0.0 },
+            hedges_g,
+        });
+    }
+
+    let t = (mean_first - mean_second) / standard_error_squared.sqrt();
+    let degrees_of_freedom = standard_error_squared.powi(2)
+        / ((variance_first / n1).powi(2) / (n1 - 1.0)
+            + (variance_second / n2).powi(2) / (n2 - 1.0));
+
+    // two-sided p-value of the t distribution
+    let p_value = regularized_incomplete_beta(
+        degrees_of_freedom / (degrees_of_freedom + t * t),
+        degrees_of_freedom / 2.0,
+        0.5,
+    );
+
+    Some(WelchTest {
+        mean_first,
+        mean_second,
+        t,
+        degrees_of_freedom,
+        p_value,
+        hedges_g,
+    })
+}
+
+// sample variance
+fn mean_and_variance(values: &[f64]) -> (f64, f64) {
+    let n = values.len() as f64;
+    let mean = values.iter().sum::<f64>() / n;
+    let variance = values
+        .iter()
+        .map(|value| (value - mean).powi(2))
+        .sum::<f64>()
+        / (n - 1.0);
+
+    (mean, variance)
+}
+
+// complementary error function with a relative error below 1.2e-7 (Numerical Recipes)
+fn erfc(x: f64) -> f64 {
+    let z = x.abs();
+    let t = 1.0 / (1.0 + 0.5 * z);
+    let polynomial = -z * z - 1.26551223
+        + t * (1.00002368
+            + t * (0.37409196
+                + t * (0.09678418
+                    + t * (-0.18628806
+                        + t * (0.27886807
+                            + t * (-1.13520398
+                                + t * (1.48851587 + t * (-0.82215223 + t * 0.17087277))))))));
+    let value = t * polynomial.exp();
+
+    if x >= 0.0 {
+        value
+    } else {
+        2.0 - value
+    }
+}
+
+// Lanczos approximation
+fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 6] = [
+        76.18009172947146,
+        -86.50532032941677,
+        24.01409824083091,
+        -1.231739572450155,
+        0.1208650973866179e-2,
+        -0.5395239384953e-5,
+    ];
+
+    let tmp = x + 5.5;
+    let tmp = tmp - (x + 0.5) * tmp.ln();
+    let series = COEFFICIENTS
+        .iter()
+        .enumerate()
+        .fold(1.000000000190015, |sum, (index, coefficient)| {
+            sum + coefficient / (x + 1.0 + index as f64)
+        });
+
+    -tmp + (2.5066282746310005 * series / x).ln()
+}
+
+fn ln_binomial(n: usize, k: usize) -> f64 {
+    ln_gamma(n as f64 + 1.0) - ln_gamma(k as f64 + 1.0) - ln_gamma((n - k) as f64 + 1.0)
+}
+
+// I_x(a, b) via its continued fraction (Numerical Recipes)
+fn regularized_incomplete_beta(x: f64, a: f64, b: f64) -> f64 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+    if x >= 1.0 {
+        return 1.0;
+    }
+
+    let front =
+        (ln_gamma(a + b) - ln_gamma(a) - ln_gamma(b) + a * x.ln() + b * (1.0 - x).ln()).exp();
+
+    if x < (a + 1.0) / (a + b + 2.0) {
+        front * beta_continued_fraction(x, a, b) / a
+    } else {
+        1.0 - front * beta_continued_fraction(1.0 - x, b, a) / b
+    }
+}
+
+fn beta_continued_fraction(x: f64, a: f64, b: f64) -> f64 {
+    const MAX_ITERATIONS: usize = 300;
+    const EPSILON: f64 = 3e-14;
+    const TINY: f64 = 1e-300;
+
+    let clamp = |value: f64| if value.abs() < TINY { TINY } else { value };
+
+    let mut c = 1.0;
+    let mut d = 1.0 / clamp(1.0 - (a + b) * x / (a + 1.0));
+    let mut result = d;
+
+    for m in 1..=MAX_ITERATIONS {
+        let m = m as f64;
+
+        let even_step = m * (b - m) * x / ((a + 2.0 * m - 1.0) * (a + 2.0 * m));
+        d = 1.0 / clamp(1.0 + even_step * d);
+        c = clamp(1.0 + even_step / c);
+        result *= d * c;
+
+        let odd_step = -(a + m) * (a + b + m) * x / ((a + 2.0 * m) * (a + 2.0 * m + 1.0));
+        d = 1.0 / clamp(1.0 + odd_step * d);
+        c = clamp(1.0 + odd_step / c);
+        let delta = d * c;
+        result *= delta;
+
+        if (delta - 1.0).abs() < EPSILON {
+            break;
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the reference values were computed with arbitrary precision (mpmath) from the definitions of
+    // R's binom.test, mcnemar.test, wilcox.test (exact = FALSE) and t.test and of
+    // scipy.special.betainc
+    fn assert_close(actual: f64, expected: f64, tolerance: f64) {
+        assert!(
+            (actual - expected).abs() <= tolerance,
+            "{actual} is not within {tolerance} of {expected}"
+        );
+    }
+
+    #[test]
+    fn mcnemar_exact_for_few_discordant_pairs() {
+        let test = mcnemar_test(2, 8);
+
+        assert!(test.exact);
+        assert_close(test.p_value, 0.109375, 1e-9);
+        assert_close(test.odds_ratio.unwrap(), 0.25, 1e-12);
+
+        // the two-sided p-value is capped at 1
+        assert_close(mcnemar_test(12, 12).p_value, 1.0, 1e-12);
+    }
+
+    #[test]
+    fn mcnemar_chi_squared_with_continuity_correction() {
+        let test = mcnemar_test(10, 30);
+
+        assert!(!test.exact);
+        assert_close(test.p_value, 0.002663119, 1e-6);
+    }
+
+    #[test]
+    fn mcnemar_without_discordant_pairs() {
+        let test = mcnemar_test(0, 0);
+
+        assert_eq!(test.p_value, 1.0);
+        assert!(test.odds_ratio.is_none());
+        assert!(mcnemar_test(3, 0).odds_ratio.is_none());
+    }
+
+    #[test]
+    // 3.14 is a measured value and not pi
+    #[allow(clippy::approx_constant)]
+    fn wilcoxon_normal_approximation() {
+        // the paired depression scores of the wilcox.test examples
+        let x = [1.83, 0.50, 1.62, 2.48, 1.68, 1.88, 1.55, 3.06, 1.30];
+        let y = [0.878, 0.647, 0.598, 2.05, 1.06, 1.29, 1.06, 3.14, 1.29];
+
+        let test = wilcoxon_signed_rank_test(x.into_iter().zip(y));
+
+        assert_eq!(test.num_pairs, 9);
+        assert_eq!(test.num_zero_differences, 0);
+        assert_close(test.z, 2.013986184, 1e-8);
+        assert_close(test.p_value, 0.04401098, 1e-6);
+        assert_close(test.rank_biserial_correlation, 7.0 / 9.0, 1e-12);
+    }
+
+    #[test]
+    fn wilcoxon_with_ties_and_zero_differences() {
+        let differences = [1.0, 1.0, 1.0, -1.0, 2.0, 0.0];
+
+        let test = wilcoxon_signed_rank_test(differences.map(|difference| (difference, 0.0)));
+
+        assert_eq!(test.num_zero_differences, 1);
+        assert_close(test.z, 1.272792206, 1e-8);
+        assert_close(test.p_value, 0.2030918, 1e-6);
+    }
+
+    #[test]
+    fn wilcoxon_with_only_ties() {
+        let test = wilcoxon_signed_rank_test([(1.0, 1.0), (2.0, 2.0), (3.0, 3.0)]);
+
+        assert_eq!(test.num_zero_differences, 3);
+        assert_eq!(test.z, 0.0);
+        assert_eq!(test.p_value, 1.0);
+
+        let empty = wilcoxon_signed_rank_test([]);
+        assert_eq!(empty.num_pairs, 0);
+        assert_eq!(empty.p_value, 1.0);
+    }
+
+    #[test]
+    fn welch_t_test_and_hedges_g() {
+        let test = welch_t_test(&[1.0, 2.0, 3.0, 4.0, 5.0], &[2.0, 4.0, 6.0, 8.0, 10.0]).unwrap();
+
+        assert_close(test.t, -1.897366596, 1e-8);
+        assert_close(test.degrees_of_freedom, 5.882352941, 1e-8);
+        assert_close(test.p_value, 0.1075312, 1e-6);
+        assert_close(test.hedges_g.unwrap(), -1.083870968, 1e-8);
+
+        let test = welch_t_test(&[10.1, 9.8, 10.4, 10.0, 9.9], &[11.2, 10.9, 11.5, 11.0]).unwrap();
+
+        assert_close(test.t, -6.621704848, 1e-8);
+        assert_close(test.degrees_of_freedom, 6.065835297, 1e-8);
+        assert_close(test.p_value, 0.0005463842, 1e-8);
+        assert_close(test.hedges_g.unwrap(), -4.018493321, 1e-8);
+    }
+
+    #[test]
+    fn welch_t_test_edge_cases() {
+        assert!(welch_t_test(&[1.0], &[1.0, 2.0]).is_none());
+        assert!(welch_t_test(&[1.0, 2.0], &[]).is_none());
+
+        let constant = welch_t_test(&[3.0, 3.0], &[3.0, 3.0, 3.0]).unwrap();
+        assert_eq!(constant.p_value, 1.0);
+        assert!(constant.hedges_g.is_none());
+
+        let different_constants = welch_t_test(&[3.0, 3.0], &[4.0, 4.0]).unwrap();
+        assert_eq!(different_constants.p_value, 0.0);
+    }
+
+    #[test]
+    fn regularized_incomplete_beta_reference_values() {
+        assert_close(regularized_incomplete_beta(0.4, 2.0, 3.0), 0.5248, 1e-10);
+        assert_close(
+            regularized_incomplete_beta(0.3, 0.5, 0.5),
+            0.3690101196,
+            1e-9,
+        );
+        assert_close(
+            regularized_incomplete_beta(0.9, 10.0, 5.0),
+            0.9907697875,
+            1e-9,
+        );
+        assert_close(regularized_incomplete_beta(0.5, 7.0, 7.0), 0.5, 1e-10);
+
+        assert_eq!(regularized_incomplete_beta(0.0, 2.0, 3.0), 0.0);
+        assert_eq!(regularized_incomplete_beta(1.0, 2.0, 3.0), 1.0);
+    }
+}